@@ -0,0 +1,356 @@
+//! Adaptive concurrency and batch sizing.
+//!
+//! A load normally runs `concurrency` transactions of `batch_size` rows at a
+//! time. With [`Adaptive`] limits those are only the starting point: after
+//! every window of `concurrency` transactions the controller compares the
+//! window's throughput with the previous one and, while it keeps improving,
+//! adds one concurrent transaction and `min_batch_size` rows per batch.
+//! A transaction that had to be retried, which is how deadlocks and lock
+//! timeouts show up, or whose latency per row exceeds `latency_spike` times
+//! the best window seen so far halves both, down to one transaction of
+//! `min_batch_size` rows (additive increase, multiplicative decrease).
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::Notify;
+
+use crate::throttle::{Pacer, Throttle};
+
+/// Bounds of adaptive concurrency and batch sizing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adaptive {
+    /// Upper bound of concurrent transactions.
+    pub max_concurrency: usize,
+    /// Lower bound of rows per transaction, also the step it grows by.
+    pub min_batch_size: usize,
+    /// Upper bound of rows per transaction.
+    pub max_batch_size: usize,
+    /// Back off when a transaction's latency per row exceeds the best
+    /// window's by this factor.
+    pub latency_spike: f64,
+}
+
+impl Default for Adaptive {
+    fn default() -> Self {
+        Self {
+            max_concurrency: 32,
+            min_batch_size: 100,
+            max_batch_size: 20_000,
+            latency_spike: 2.0,
+        }
+    }
+}
+
+impl Adaptive {
+    pub(crate) fn validate(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if self.max_concurrency == 0 || self.min_batch_size == 0 {
+            return Err("adaptive limits must be at least 1".into());
+        }
+        if self.min_batch_size > self.max_batch_size {
+            return Err("adaptive min_batch_size exceeds max_batch_size".into());
+        }
+        if self.latency_spike.is_nan() || self.latency_spike <= 1.0 {
+            return Err("adaptive latency_spike must be greater than 1".into());
+        }
+        Ok(())
+    }
+}
+
+/// Hands out transaction slots and, when adaptive, tunes how many there
+/// are and how large their batches should be. A [`Throttle`] caps both the
+/// slots and the rows sent per second.
+#[derive(Debug)]
+pub(crate) struct Controller {
+    limits: Option<Adaptive>,
+    pacer: Option<Pacer>,
+    state: Mutex<State>,
+    released: Notify,
+}
+
+#[derive(Debug)]
+struct State {
+    concurrency: usize,
+    batch_size: usize,
+    in_flight: usize,
+    window: Window,
+    /// Throughput of the previous window, in rows per second.
+    throughput: f64,
+    /// Lowest average latency per row of any window.
+    baseline: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct Window {
+    started: Instant,
+    transactions: usize,
+    rows: usize,
+    latency: Duration,
+}
+
+impl Window {
+    fn new(started: Instant) -> Self {
+        Self {
+            started,
+            transactions: 0,
+            rows: 0,
+            latency: Duration::ZERO,
+        }
+    }
+}
+
+/// A transaction slot, released when dropped.
+pub(crate) struct Permit {
+    controller: Arc<Controller>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.controller.lock().in_flight -= 1;
+        self.controller.released.notify_waiters();
+    }
+}
+
+impl Controller {
+    /// A controller starting at `concurrency` transactions of `batch_size`
+    /// rows, adapting within `limits` and held back by `throttle` if given.
+    pub(crate) fn new(
+        concurrency: usize,
+        batch_size: usize,
+        limits: Option<Adaptive>,
+        throttle: Option<Throttle>,
+    ) -> Self {
+        let (concurrency, batch_size) = match limits {
+            Some(limits) => (
+                concurrency.clamp(1, limits.max_concurrency),
+                batch_size.clamp(limits.min_batch_size, limits.max_batch_size),
+            ),
+            None => (concurrency.max(1), batch_size.max(1)),
+        };
+        Self {
+            limits,
+            pacer: throttle.map(Pacer::new),
+            state: Mutex::new(State {
+                concurrency,
+                batch_size,
+                in_flight: 0,
+                window: Window::new(Instant::now()),
+                throughput: 0.0,
+                baseline: None,
+            }),
+            released: Notify::new(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Rows to put in the next transaction.
+    pub(crate) fn batch_size(&self) -> usize {
+        self.lock().batch_size
+    }
+
+    #[cfg(test)]
+    fn concurrency(&self) -> usize {
+        self.lock().concurrency
+    }
+
+    /// Wait for a free slot.
+    pub(crate) async fn acquire(self: &Arc<Self>) -> Permit {
+        loop {
+            let released = self.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+            {
+                let mut state = self.lock();
+                let limit = match &self.pacer {
+                    Some(pacer) => pacer.throttle().concurrency(state.concurrency),
+                    None => state.concurrency,
+                };
+                if state.in_flight < limit {
+                    state.in_flight += 1;
+                    return Permit {
+                        controller: self.clone(),
+                    };
+                }
+            }
+            released.await;
+        }
+    }
+
+    /// Wait until the throttle allows sending `rows` more rows.
+    pub(crate) async fn admit(&self, rows: usize) {
+        if let Some(pacer) = &self.pacer {
+            pacer.admit(rows).await;
+        }
+    }
+
+    /// Record a finished transaction of `rows` rows that took `latency` and
+    /// needed `retries` retries.
+    pub(crate) fn record(&self, rows: usize, latency: Duration, retries: u32) {
+        self.record_at(Instant::now(), rows, latency, retries);
+    }
+
+    fn record_at(&self, now: Instant, rows: usize, latency: Duration, retries: u32) {
+        let Some(limits) = self.limits else { return };
+        let mut state = self.lock();
+        let per_row = latency / rows.max(1) as u32;
+        let spike = state.baseline.is_some_and(|baseline| {
+            per_row.as_secs_f64() > baseline.as_secs_f64() * limits.latency_spike
+        });
+        if retries > 0 || spike {
+            state.concurrency = (state.concurrency / 2).max(1);
+            state.batch_size = (state.batch_size / 2).max(limits.min_batch_size);
+            state.window = Window::new(now);
+            state.throughput = 0.0;
+            return;
+        }
+
+        state.window.transactions += 1;
+        state.window.rows += rows;
+        state.window.latency += latency;
+        if state.window.transactions < state.concurrency {
+            return;
+        }
+        let elapsed = now.duration_since(state.window.started).as_secs_f64();
+        let throughput = state.window.rows as f64 / elapsed.max(f64::EPSILON);
+        let average = state.window.latency / state.window.rows.max(1) as u32;
+        state.baseline = Some(state.baseline.map_or(average, |b| b.min(average)));
+        if throughput > state.throughput {
+            state.concurrency = (state.concurrency + 1).min(limits.max_concurrency);
+            state.batch_size =
+                (state.batch_size + limits.min_batch_size).min(limits.max_batch_size);
+        }
+        state.throughput = throughput;
+        state.window = Window::new(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> Adaptive {
+        Adaptive {
+            max_concurrency: 4,
+            min_batch_size: 100,
+            max_batch_size: 400,
+            latency_spike: 2.0,
+        }
+    }
+
+    #[test]
+    fn grows_while_throughput_improves() {
+        let controller = Controller::new(1, 100, Some(limits()), None);
+        let start = Instant::now();
+        // Each window commits more rows per second than the last.
+        for i in 1..=10u64 {
+            let rows = controller.batch_size();
+            let now = start + Duration::from_millis(100 * i);
+            for _ in 0..controller.concurrency() {
+                controller.record_at(now, rows, Duration::from_millis(10), 0);
+            }
+        }
+        assert_eq!(controller.concurrency(), 4);
+        assert_eq!(controller.batch_size(), 400);
+
+        // A flat window holds the current limits.
+        let before = controller.lock().throughput;
+        controller.lock().throughput = before * 100.0;
+        for _ in 0..4 {
+            controller.record_at(
+                start + Duration::from_secs(2),
+                400,
+                Duration::from_millis(10),
+                0,
+            );
+        }
+        assert_eq!(controller.concurrency(), 4);
+    }
+
+    #[test]
+    fn backs_off_on_retries_and_latency_spikes() {
+        let controller = Controller::new(4, 400, Some(limits()), None);
+        let start = Instant::now();
+        controller.record_at(start, 400, Duration::from_millis(40), 1);
+        assert_eq!(
+            (controller.concurrency(), controller.batch_size()),
+            (2, 200)
+        );
+
+        for _ in 0..2 {
+            controller.record_at(
+                start + Duration::from_millis(50),
+                200,
+                Duration::from_millis(20),
+                0,
+            );
+        }
+        assert_eq!(controller.lock().baseline, Some(Duration::from_micros(100)));
+        assert_eq!(
+            (controller.concurrency(), controller.batch_size()),
+            (3, 300)
+        );
+        controller.record_at(
+            start + Duration::from_millis(60),
+            300,
+            Duration::from_millis(100),
+            0,
+        );
+        assert_eq!(
+            (controller.concurrency(), controller.batch_size()),
+            (1, 150)
+        );
+        controller.record_at(
+            start + Duration::from_millis(70),
+            100,
+            Duration::from_secs(1),
+            3,
+        );
+        assert_eq!(
+            (controller.concurrency(), controller.batch_size()),
+            (1, 100)
+        );
+    }
+
+    #[test]
+    fn fixed_limits_never_change() {
+        let controller = Controller::new(3, 500, None, None);
+        controller.record(500, Duration::from_secs(5), 2);
+        assert_eq!(
+            (controller.concurrency(), controller.batch_size()),
+            (3, 500)
+        );
+        assert!(
+            Adaptive {
+                latency_spike: 1.0,
+                ..Adaptive::default()
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(Adaptive::default().validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn limits_slots_in_flight() {
+        let throttle = Throttle {
+            max_concurrency: Some(1),
+            ..Throttle::default()
+        };
+        let controller = Arc::new(Controller::new(4, 1, None, Some(throttle)));
+        let first = controller.acquire().await;
+        let waiting = tokio::spawn({
+            let controller = controller.clone();
+            async move { controller.acquire().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+        drop(first);
+        let _second = tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
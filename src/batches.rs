@@ -0,0 +1,151 @@
+//! In-memory Arrow sources.
+//!
+//! These loaders accept record batches produced elsewhere in the process, for
+//! example by DataFusion or Polars, without a round trip through a file.
+
+use arrow::record_batch::RecordBatch;
+use futures::{Stream, StreamExt};
+use neo4rs::Graph;
+
+use crate::job::{NodeLoad, RelationshipLoad};
+
+/// Load a stream of record batches into Neo4j in parallel.
+/// Each row is mapped to properties of a node with the given label.
+///
+/// The stream is drained before writing; use [`NodeLoad::from_batches`] for
+/// further options.
+pub async fn load_batch_stream_nodes_parallel<S, E>(
+    graph: Graph,
+    batches: S,
+    label: &str,
+    concurrency: usize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    S: Stream<Item = Result<RecordBatch, E>>,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    load_batches_nodes_parallel(graph, collect_batches(batches).await?, label, concurrency).await
+}
+
+/// Load relationships from a stream of record batches in parallel.
+///
+/// Columns are mapped exactly as in
+/// [`load_parquet_relationships_parallel`](crate::load_parquet_relationships_parallel).
+#[allow(clippy::too_many_arguments)]
+pub async fn load_batch_stream_relationships_parallel<S, E>(
+    graph: Graph,
+    batches: S,
+    rel_type: &str,
+    start_label: &str,
+    start_id_col: &str,
+    start_id_prop: &str,
+    end_label: &str,
+    end_id_col: &str,
+    end_id_prop: &str,
+    concurrency: usize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    S: Stream<Item = Result<RecordBatch, E>>,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    load_batches_relationships_parallel(
+        graph,
+        collect_batches(batches).await?,
+        rel_type,
+        start_label,
+        start_id_col,
+        start_id_prop,
+        end_label,
+        end_id_col,
+        end_id_prop,
+        concurrency,
+    )
+    .await
+}
+
+/// Load record batches held in memory as nodes.
+///
+/// Wrapper around [`NodeLoad::from_batches`].
+pub async fn load_batches_nodes_parallel(
+    graph: Graph,
+    batches: Vec<RecordBatch>,
+    label: &str,
+    concurrency: usize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    NodeLoad::from_batches(batches, label)
+        .concurrency(concurrency)
+        .run(&graph)
+        .await?;
+    Ok(())
+}
+
+/// Load record batches held in memory as relationships.
+///
+/// Wrapper around [`RelationshipLoad::from_batches`].
+#[allow(clippy::too_many_arguments)]
+pub async fn load_batches_relationships_parallel(
+    graph: Graph,
+    batches: Vec<RecordBatch>,
+    rel_type: &str,
+    start_label: &str,
+    start_id_col: &str,
+    start_id_prop: &str,
+    end_label: &str,
+    end_id_col: &str,
+    end_id_prop: &str,
+    concurrency: usize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    RelationshipLoad::from_batches(batches, rel_type)
+        .start(start_label, start_id_col)
+        .start_property(start_id_prop)
+        .end(end_label, end_id_col)
+        .end_property(end_id_prop)
+        .concurrency(concurrency)
+        .run(&graph)
+        .await?;
+    Ok(())
+}
+
+/// Drain `batches`, stopping at the first error.
+async fn collect_batches<S, E>(
+    batches: S,
+) -> Result<Vec<RecordBatch>, Box<dyn std::error::Error + Send + Sync>>
+where
+    S: Stream<Item = Result<RecordBatch, E>>,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let mut batches = std::pin::pin!(batches);
+    let mut collected = Vec::new();
+    while let Some(batch) = batches.next().await {
+        collected.push(batch.map_err(Into::into)?);
+    }
+    Ok(collected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::Int64Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn collects_every_batch() {
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        let batch = |ids: Vec<i64>| {
+            RecordBatch::try_new(schema.clone(), vec![Arc::new(Int64Array::from(ids))]).unwrap()
+        };
+        let stream = futures::stream::iter(vec![
+            Ok::<_, arrow::error::ArrowError>(batch(vec![1, 2])),
+            Ok(batch(vec![3])),
+        ]);
+        let batches = collect_batches(stream).await.unwrap();
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 3);
+
+        let failing = futures::stream::iter(vec![
+            Ok(batch(vec![1])),
+            Err(arrow::error::ArrowError::ComputeError("boom".into())),
+        ]);
+        assert!(collect_batches(failing).await.is_err());
+    }
+}
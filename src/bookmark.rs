@@ -0,0 +1,185 @@
+//! Causal consistency between loads.
+//!
+//! neo4rs 0.9 neither returns the bookmark of a commit nor accepts bookmarks
+//! when beginning a transaction, so the loader keeps bookmarks of its own. A
+//! [`Bookmark`] names a database and the last transaction its writer had
+//! committed when a load finished, as reported by the `lastCommittedTxn`
+//! column of `SHOW DATABASES`. Every load on Neo4j returns one in
+//! [`LoadSummary::bookmarks`](crate::LoadSummary::bookmarks), unless reading
+//! it failed after the commit, which only fails loads that set
+//! `capture_bookmarks`.
+//!
+//! A load given bookmarks with `bookmarks` waits until the writer it sends
+//! to has committed those transactions before writing anything. The
+//! `MATCH`es of a relationship load then see every node of an earlier node
+//! load, even when leadership moved in between or the node load ran through
+//! another [`Router`] or process. Bookmarks print as `database:transaction`
+//! and parse back from that form, which the CLI's `--bookmark` accepts.
+//!
+//! Memgraph writes to a single main instance and reports no transaction ids,
+//! so loads there return no bookmarks and refuse given ones.
+
+use std::time::{Duration, Instant};
+
+use neo4rs::{Graph, query};
+
+use crate::routing::Router;
+
+/// How long a load waits for the writer to catch up with its bookmarks.
+const WAIT_LIMIT: Duration = Duration::from_secs(60);
+
+/// Delay between checks of the writer's last transaction.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A point in the history of a database that later loads wait for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Bookmark {
+    pub database: String,
+    /// Id of the last transaction committed on the database's writer.
+    pub transaction: i64,
+}
+
+impl std::str::FromStr for Bookmark {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid bookmark `{s}`; expected DATABASE:TRANSACTION");
+        let (database, transaction) = s.rsplit_once(':').ok_or_else(invalid)?;
+        if database.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Bookmark {
+            database: database.to_string(),
+            transaction: transaction.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl std::fmt::Display for Bookmark {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.database, self.transaction)
+    }
+}
+
+/// Add `other` to `bookmarks`, keeping the latest bookmark per database.
+pub fn merge(bookmarks: &mut Vec<Bookmark>, other: impl IntoIterator<Item = Bookmark>) {
+    for bookmark in other {
+        match bookmarks
+            .iter_mut()
+            .find(|b| b.database == bookmark.database)
+        {
+            Some(known) => known.transaction = known.transaction.max(bookmark.transaction),
+            None => bookmarks.push(bookmark),
+        }
+    }
+}
+
+/// The bookmark of everything committed so far on the database `router`
+/// writes to, or `None` on servers without bookmarks.
+pub(crate) async fn capture(
+    router: &Router,
+) -> Result<Option<Bookmark>, Box<dyn std::error::Error + Send + Sync>> {
+    let dialect = router.dialect();
+    let (Some(database_query), Some(cypher)) =
+        (dialect.database_query(), dialect.last_transaction_query())
+    else {
+        return Ok(None);
+    };
+    let graph = router.graph();
+    let mut stream = graph.execute(query(database_query)).await?;
+    let Some(row) = stream.next().await? else {
+        return Ok(None);
+    };
+    let database: String = row.get("name")?;
+    while stream.next().await?.is_some() {}
+    let transaction = last_transaction(&graph, cypher, &database).await?;
+    Ok(transaction.map(|transaction| Bookmark {
+        database,
+        transaction,
+    }))
+}
+
+/// Wait until the writer of `router` has committed the transactions of
+/// `bookmarks`.
+pub(crate) async fn wait(
+    router: &Router,
+    bookmarks: &[Bookmark],
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if bookmarks.is_empty() {
+        return Ok(());
+    }
+    let dialect = router.dialect();
+    let Some(cypher) = dialect.last_transaction_query() else {
+        return Err(format!("{dialect} does not support bookmarks").into());
+    };
+    let deadline = Instant::now() + WAIT_LIMIT;
+    for bookmark in bookmarks {
+        loop {
+            let applied = last_transaction(&router.graph(), cypher, &bookmark.database).await?;
+            if applied.is_some_and(|applied| applied >= bookmark.transaction) {
+                break;
+            }
+            if Instant::now() >= deadline {
+                return Err(format!(
+                    "the writer of {} has not committed transaction {} within {WAIT_LIMIT:?}; \
+                     is bookmark {bookmark} from this cluster?",
+                    bookmark.database, bookmark.transaction
+                )
+                .into());
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+    Ok(())
+}
+
+/// Id of the last transaction committed on the writer of `database`, asked
+/// through `graph` with the dialect's `cypher`.
+async fn last_transaction(
+    graph: &Graph,
+    cypher: &str,
+    database: &str,
+) -> Result<Option<i64>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut stream = graph
+        .execute_on("system", query(cypher).param("database", database))
+        .await?;
+    let mut last = None;
+    while let Some(row) = stream.next().await? {
+        last = last.max(row.get::<Option<i64>>("transaction")?);
+    }
+    Ok(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_merges_bookmarks() {
+        let bookmark: Bookmark = "neo4j:42".parse().unwrap();
+        assert_eq!(
+            bookmark,
+            Bookmark {
+                database: "neo4j".into(),
+                transaction: 42
+            }
+        );
+        assert_eq!(bookmark.to_string(), "neo4j:42");
+        assert!("neo4j".parse::<Bookmark>().is_err());
+        assert!(":42".parse::<Bookmark>().is_err());
+        assert!("neo4j:latest".parse::<Bookmark>().is_err());
+
+        let mut bookmarks = vec![bookmark];
+        merge(
+            &mut bookmarks,
+            ["neo4j:40", "neo4j:50", "graph:7"].map(|b| b.parse().unwrap()),
+        );
+        assert_eq!(
+            bookmarks
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["neo4j:50", "graph:7"]
+        );
+    }
+}
@@ -1,22 +1,1128 @@
+//! Connection settings from the environment and config files.
+//!
+//! A config file holds shared settings at the top level and named profiles
+//! under `[profiles.<name>]`:
+//!
+//! ```toml
+//! default_profile = "dev"
+//! username = "neo4j"
+//!
+//! [profiles.dev]
+//! uri = "bolt://localhost:7687"
+//! password = "${NEO4J_DEV_PASSWORD:-neo4j}"
+//!
+//! [profiles.prod]
+//! uri = "neo4j+s://db.example.com"
+//! password = "${NEO4J_PROD_PASSWORD}"
+//! database = "graph"
+//! ```
+//!
+//! `${VAR}` in a string is replaced by the environment variable `VAR`, and
+//! `${VAR:-default}` falls back to `default` when it is unset; `$$` is a
+//! literal `$`. Only the values actually used are interpolated, so a profile
+//! may reference variables that are unset while another one is selected.
+//!
+//! Each setting is taken from the first of these that provides it:
+//!
+//! 1. explicit overrides such as command line flags,
+//! 2. the selected profile,
+//! 3. the top level of the config file,
+//! 4. the `NEO4J_*` environment variables.
+//!
+//! The profile is chosen by the caller, else by `NEO4J_PROFILE`, else by the
+//! file's `default_profile`.
+//!
+//! TLS is configured with `tls_trust` (`system` or `all`) and `tls_ca_file`,
+//! or the matching `NEO4J_TLS_*` variables; see [`TlsConfig`].
+//!
+//! The connection pool is tuned with `max_connections`, `fetch_size`,
+//! `connect_timeout` and `transaction_timeout`. Timeouts are integer seconds
+//! or strings such as `"500ms"`, `"30s"` or `"2m"`.
+//!
+//! `dialect` (`auto`, `neo4j-4`, `neo4j-5`, `neo4j-5.<minor>` or `memgraph`)
+//! selects the Cypher variant to generate; see [`Dialect`]. It is detected
+//! from the server when unset or `auto`.
+//!
+//! Instead of `password`, any source may give `password_file` (or
+//! `NEO4J_PASSWORD_FILE`), the path of a file holding the password such as a
+//! Docker or Kubernetes secret; a trailing newline is removed. Setting both at
+//! the same level is an error. Passwords are held in a [`Secret`], which never
+//! prints its value.
+//!
+//! `auth_scheme` selects `basic` (user name and password, the default) or
+//! `empty-basic`; see [`Auth`]. The file supports the subset of TOML needed here:
+//! tables, and string, integer and boolean values.
+
+use std::collections::BTreeMap;
 use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::dialect::Dialect;
+
+/// Connection pool size used when neither the config nor the load asks for
+/// more, matching the driver's default.
+pub const DEFAULT_MAX_CONNECTIONS: usize = 16;
+
+/// Time allowed to open and verify the first connection when
+/// `connect_timeout` is not set.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Config file read by [`Neo4jConfig::load`] when no path is given.
+pub const DEFAULT_CONFIG_FILE: &str = "neo4j-loader.toml";
+
+/// Settings that are blocked on driver support, with the reason given when
+/// a file sets them. The neo4rs driver builds its TLS configuration without
+/// client authentication, sends the URI host as the SNI name and logs in
+/// with the basic scheme only, and offers no way to change any of these.
+const UNSUPPORTED: &[(&str, &str)] = &[
+    (
+        "tls_client_cert",
+        "the neo4rs driver cannot present a client certificate",
+    ),
+    (
+        "tls_client_key",
+        "the neo4rs driver cannot present a client certificate",
+    ),
+    (
+        "tls_server_name",
+        "the neo4rs driver always sends the URI host as the SNI name",
+    ),
+    (
+        "bearer_token",
+        "the neo4rs driver only sends basic authentication",
+    ),
+    (
+        "bearer_token_file",
+        "the neo4rs driver only sends basic authentication",
+    ),
+    (
+        "impersonate",
+        "the neo4rs driver cannot impersonate another user",
+    ),
+];
+
+/// Settings a config file or override may provide.
+const KEYS: &[&str] = &[
+    "uri",
+    "username",
+    "password",
+    "password_file",
+    "auth_scheme",
+    "database",
+    "tls_trust",
+    "tls_ca_file",
+    "max_connections",
+    "fetch_size",
+    "connect_timeout",
+    "transaction_timeout",
+    "dialect",
+];
+
+/// A string that is redacted when formatted, for passwords and tokens.
+///
+/// Use [`expose`](Self::expose) where the plain value is needed.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Secret(value.into())
+    }
+
+    /// The secret value.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Read a secret from a file, dropping a trailing newline.
+    pub fn from_file<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let path = path.as_ref();
+        let mut value = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read secret file {}: {e}", path.display()))?;
+        let len = value.trim_end_matches(['\n', '\r']).len();
+        value.truncate(len);
+        Ok(Secret(value))
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+impl std::fmt::Display for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Secret(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Secret(value.to_owned())
+    }
+}
+
+/// How a connection authenticates.
+///
+/// The neo4rs driver only sends the `basic` scheme, so bearer tokens,
+/// impersonation and Bolt's `none` scheme are not offered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Auth {
+    /// User name and password.
+    Basic { username: String, password: Secret },
+    /// The `basic` scheme with an empty user name and password, which servers
+    /// running with authentication disabled accept. This is not Bolt's
+    /// `none` scheme; servers requiring authentication reject it.
+    EmptyBasic,
+}
+
+impl Auth {
+    pub fn basic(username: impl Into<String>, password: impl Into<Secret>) -> Self {
+        Auth::Basic {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    pub fn scheme(&self) -> AuthScheme {
+        match self {
+            Auth::Basic { .. } => AuthScheme::Basic,
+            Auth::EmptyBasic => AuthScheme::EmptyBasic,
+        }
+    }
+
+    /// Resolve the credentials of the selected scheme. `secret` finds a value
+    /// given directly or through its `_file` key.
+    fn resolve(
+        lookup: impl Fn(&str) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>>,
+        secret: impl Fn(&str) -> Result<Option<Secret>, Box<dyn std::error::Error + Send + Sync>>,
+        missing: impl Fn(&str) -> String,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let scheme = match lookup("auth_scheme")? {
+            Some(scheme) => scheme.parse()?,
+            Option::None => AuthScheme::Basic,
+        };
+        match scheme {
+            AuthScheme::Basic => Ok(Auth::Basic {
+                username: lookup("username")?.ok_or_else(|| missing("username"))?,
+                password: secret("password")?.ok_or_else(|| missing("password"))?,
+            }),
+            AuthScheme::EmptyBasic => Ok(Auth::EmptyBasic),
+        }
+    }
+}
+
+/// The kind of [`Auth`], as selected by `auth_scheme`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthScheme {
+    Basic,
+    EmptyBasic,
+}
+
+impl std::str::FromStr for AuthScheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "basic" => Ok(AuthScheme::Basic),
+            "empty-basic" => Ok(AuthScheme::EmptyBasic),
+            "none" | "bearer" => Err(format!(
+                "auth scheme `{s}` is not supported: the neo4rs driver only sends basic \
+                 authentication; use `empty-basic` for servers with authentication disabled"
+            )),
+            _ => Err(format!(
+                "unknown auth scheme `{s}`; expected `basic` or `empty-basic`"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for AuthScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AuthScheme::Basic => "basic",
+            AuthScheme::EmptyBasic => "empty-basic",
+        })
+    }
+}
+
+/// How the server certificate is verified.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrustMode {
+    /// Verify against the system roots and [`TlsConfig::ca_file`], if set.
+    #[default]
+    System,
+    /// Accept any certificate, as the `+ssc` URI schemes do. Only meant for
+    /// self-signed development clusters.
+    All,
+}
+
+impl std::str::FromStr for TrustMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "system" => Ok(TrustMode::System),
+            "all" => Ok(TrustMode::All),
+            _ => Err(format!(
+                "unknown TLS trust mode `{s}`; expected `system` or `all`"
+            )),
+        }
+    }
+}
+
+/// TLS settings of a connection.
+///
+/// Setting any of these turns on encryption for `bolt://` and `neo4j://`
+/// URIs; see [`connect`](crate::connect).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsConfig {
+    pub trust: TrustMode,
+    /// PEM file of additional CA certificates, for clusters signed by a
+    /// private CA.
+    pub ca_file: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    /// Whether any setting asks for an encrypted connection.
+    pub fn is_requested(&self) -> bool {
+        self.trust == TrustMode::All || self.ca_file.is_some()
+    }
+
+    fn from_lookup(
+        lookup: impl Fn(&str) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(TlsConfig {
+            trust: lookup("tls_trust")?
+                .map(|t| t.parse())
+                .transpose()?
+                .unwrap_or_default(),
+            ca_file: lookup("tls_ca_file")?.map(PathBuf::from),
+        })
+    }
+}
 
 /// Configuration for connecting to a Neo4j instance.
 #[derive(Debug, Clone)]
 pub struct Neo4jConfig {
     pub uri: String,
-    pub username: String,
-    pub password: String,
+    pub auth: Auth,
     pub database: Option<String>,
+    pub tls: TlsConfig,
+    /// Size of the connection pool. Defaults to
+    /// [`DEFAULT_MAX_CONNECTIONS`], raised by
+    /// [`with_concurrency`](Self::with_concurrency).
+    pub max_connections: Option<usize>,
+    /// Number of records fetched per round trip when streaming results.
+    pub fetch_size: Option<usize>,
+    /// Time allowed to open and verify the connection in
+    /// [`connect`](crate::connect), [`DEFAULT_CONNECT_TIMEOUT`] if unset.
+    pub connect_timeout: Option<Duration>,
+    /// Default per-transaction timeout for loads run with this config.
+    pub transaction_timeout: Option<Duration>,
+    /// Cypher dialect of the server, detected when connecting if unset.
+    pub dialect: Option<Dialect>,
 }
 
 impl Neo4jConfig {
-    /// Load configuration from environment variables.
-    pub fn from_env() -> Result<Self, env::VarError> {
+    /// Load configuration from environment variables. The password is read
+    /// from `NEO4J_PASSWORD`, or from the file named by `NEO4J_PASSWORD_FILE`,
+    /// the authentication scheme from `NEO4J_AUTH_SCHEME`, and TLS settings
+    /// from the `NEO4J_TLS_*` variables.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let var = |name: &str| env::var(name).map_err(|e| format!("{name}: {e}"));
+        let auth = Auth::resolve(
+            env_lookup,
+            |key| {
+                secret(
+                    env_lookup(key)?,
+                    env_lookup(&format!("{key}_file"))?,
+                    key,
+                    "environment",
+                )
+            },
+            |key| {
+                format!(
+                    "NEO4J_{}: environment variable not found",
+                    key.to_ascii_uppercase()
+                )
+            },
+        )?;
         Ok(Self {
-            uri: env::var("NEO4J_URI")?,
-            username: env::var("NEO4J_USERNAME")?,
-            password: env::var("NEO4J_PASSWORD")?,
+            uri: var("NEO4J_URI")?,
+            auth,
             database: env::var("NEO4J_DATABASE").ok(),
+            tls: TlsConfig::from_lookup(env_lookup)?,
+            max_connections: count("max_connections", env_lookup("max_connections")?)?,
+            fetch_size: count("fetch_size", env_lookup("fetch_size")?)?,
+            connect_timeout: duration("connect_timeout", env_lookup("connect_timeout")?)?,
+            transaction_timeout: duration(
+                "transaction_timeout",
+                env_lookup("transaction_timeout")?,
+            )?,
+            dialect: dialect(env_lookup("dialect")?)?,
         })
     }
+
+    /// Check that the pool can serve `concurrency` transactions at once and
+    /// size it accordingly.
+    ///
+    /// Without an explicit `max_connections` the pool is grown to at least
+    /// `concurrency` connections. An explicit `max_connections` smaller than
+    /// `concurrency` is an error, as the extra workers would only queue for a
+    /// connection.
+    pub fn with_concurrency(
+        mut self,
+        concurrency: usize,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        match self.max_connections {
+            Some(max) if max < concurrency => Err(format!(
+                "concurrency {concurrency} exceeds max_connections {max}; raise \
+                 max_connections or lower the concurrency"
+            )
+            .into()),
+            Some(_) => Ok(self),
+            None => {
+                self.max_connections = Some(concurrency.max(DEFAULT_MAX_CONNECTIONS));
+                Ok(self)
+            }
+        }
+    }
+
+    /// Resolve the configuration from a config file, a profile, overrides and
+    /// the environment, in the precedence order described in the
+    /// [module documentation](self).
+    ///
+    /// Without `path`, `NEO4J_CONFIG` names the file, falling back to
+    /// [`DEFAULT_CONFIG_FILE`] in the working directory if it exists.
+    pub fn load(
+        path: Option<&Path>,
+        profile: Option<&str>,
+        overrides: &ConfigOverrides,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let path = match path {
+            Some(path) => Some(path.to_path_buf()),
+            None => env::var_os("NEO4J_CONFIG")
+                .map(PathBuf::from)
+                .or_else(|| Some(PathBuf::from(DEFAULT_CONFIG_FILE)).filter(|p| p.is_file())),
+        };
+        let file = path.as_deref().map(ConfigFile::from_path).transpose()?;
+        Self::resolve(file.as_ref(), profile, overrides)
+    }
+
+    /// Resolve the configuration from an already parsed config file.
+    pub fn resolve(
+        file: Option<&ConfigFile>,
+        profile: Option<&str>,
+        overrides: &ConfigOverrides,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let env_profile = env::var("NEO4J_PROFILE").ok();
+        let profile = profile
+            .or(env_profile.as_deref())
+            .or(file.and_then(|f| f.default_profile.as_deref()));
+        let profile_table = match (profile, file) {
+            (Some(name), Some(file)) => Some(file.profiles.get(name).ok_or_else(|| {
+                let known: Vec<&str> = file.profiles.keys().map(String::as_str).collect();
+                format!(
+                    "unknown profile `{name}`; the config file defines: {}",
+                    known.join(", ")
+                )
+            })?),
+            (Some(name), None) => {
+                return Err(format!("profile `{name}` requested but no config file found").into());
+            }
+            (None, _) => None,
+        };
+
+        // The value of `key` at each level, highest precedence first.
+        let at = |level: usize,
+                  key: &str|
+         -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+            let from_file = match level {
+                0 => return Ok(overrides.get(key)),
+                1 => profile_table.and_then(|t| t.get(key)),
+                2 => file.and_then(|f| f.base.get(key)),
+                _ => return Ok(env::var(format!("NEO4J_{}", key.to_ascii_uppercase())).ok()),
+            };
+            from_file
+                .map(|value| interpolate(&value.to_string()))
+                .transpose()
+                .map_err(|e| format!("`{key}`: {e}").into())
+        };
+        let lookup =
+            |key: &str| -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+                for level in 0..4 {
+                    if let Some(value) = at(level, key)? {
+                        return Ok(Some(value));
+                    }
+                }
+                Ok(None)
+            };
+        let missing = |key: &str| {
+            format!(
+                "`{key}` is not set; pass it as an override, in the config file or as NEO4J_{}",
+                key.to_ascii_uppercase()
+            )
+        };
+        let required = |key: &str| -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+            lookup(key)?.ok_or_else(|| missing(key).into())
+        };
+
+        // A secret from the highest level that gives it directly or as a file.
+        let secret_at =
+            |key: &str| -> Result<Option<Secret>, Box<dyn std::error::Error + Send + Sync>> {
+                for level in 0..4 {
+                    let source = ["override", "profile", "config file", "environment"][level];
+                    let file = at(level, &format!("{key}_file"))?;
+                    if let Some(value) = secret(at(level, key)?, file, key, source)? {
+                        return Ok(Some(value));
+                    }
+                }
+                Ok(None)
+            };
+        Ok(Self {
+            uri: required("uri")?,
+            auth: Auth::resolve(lookup, secret_at, missing)?,
+            database: lookup("database")?,
+            tls: TlsConfig::from_lookup(lookup)?,
+            max_connections: count("max_connections", lookup("max_connections")?)?,
+            fetch_size: count("fetch_size", lookup("fetch_size")?)?,
+            connect_timeout: duration("connect_timeout", lookup("connect_timeout")?)?,
+            transaction_timeout: duration("transaction_timeout", lookup("transaction_timeout")?)?,
+            dialect: dialect(lookup("dialect")?)?,
+        })
+    }
+}
+
+fn env_lookup(key: &str) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(env::var(format!("NEO4J_{}", key.to_ascii_uppercase())).ok())
+}
+
+/// Parse a positive count such as `max_connections`.
+fn count(
+    key: &str,
+    value: Option<String>,
+) -> Result<Option<usize>, Box<dyn std::error::Error + Send + Sync>> {
+    value
+        .map(|v| match v.trim().parse() {
+            Ok(0) | Err(_) => Err(format!("`{key}` must be a positive integer, got `{v}`").into()),
+            Ok(n) => Ok(n),
+        })
+        .transpose()
+}
+
+fn duration(
+    key: &str,
+    value: Option<String>,
+) -> Result<Option<Duration>, Box<dyn std::error::Error + Send + Sync>> {
+    value
+        .map(|v| parse_duration(&v).map_err(|e| format!("`{key}`: {e}").into()))
+        .transpose()
+}
+
+/// Parse `dialect`, where `auto` asks for detection.
+fn dialect(
+    value: Option<String>,
+) -> Result<Option<Dialect>, Box<dyn std::error::Error + Send + Sync>> {
+    match value.as_deref().map(str::trim) {
+        None | Some("auto") => Ok(None),
+        Some(name) => Ok(Some(name.parse().map_err(|e| format!("`dialect`: {e}"))?)),
+    }
+}
+
+/// Parse a duration given as integer seconds or with an `ms`, `s`, `m` or
+/// `h` suffix.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let invalid = || format!("invalid duration `{text}`; use e.g. `500ms`, `30s` or `2m`");
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    let duration = match unit.trim() {
+        "ms" => Duration::from_millis(number),
+        "" | "s" => Duration::from_secs(number),
+        "m" => Duration::from_secs(number * 60),
+        "h" => Duration::from_secs(number * 3600),
+        _ => return Err(invalid()),
+    };
+    if duration.is_zero() {
+        return Err(format!("duration `{text}` must be greater than zero"));
+    }
+    Ok(duration)
+}
+
+/// Pick the secret `key` given directly or read it from `file`.
+fn secret(
+    value: Option<String>,
+    file: Option<String>,
+    key: &str,
+    source: &str,
+) -> Result<Option<Secret>, Box<dyn std::error::Error + Send + Sync>> {
+    match (value, file) {
+        (Some(_), Some(_)) => {
+            Err(format!("{source}: set either `{key}` or `{key}_file`, not both").into())
+        }
+        (Some(value), None) => Ok(Some(Secret(value))),
+        (None, Some(file)) => Secret::from_file(file).map(Some),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Settings that take precedence over the config file and the environment,
+/// typically taken from command line flags.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    pub uri: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    pub password_file: Option<PathBuf>,
+    pub auth_scheme: Option<AuthScheme>,
+    pub database: Option<String>,
+    pub tls_trust: Option<TrustMode>,
+    pub tls_ca_file: Option<PathBuf>,
+    pub max_connections: Option<usize>,
+    pub fetch_size: Option<usize>,
+    pub connect_timeout: Option<Duration>,
+    pub transaction_timeout: Option<Duration>,
+    pub dialect: Option<Dialect>,
+}
+
+impl ConfigOverrides {
+    fn get(&self, key: &str) -> Option<String> {
+        match key {
+            "uri" => self.uri.clone(),
+            "username" => self.username.clone(),
+            "password" => self.password.as_ref().map(|p| p.expose().to_owned()),
+            "password_file" => path_string(&self.password_file),
+            "auth_scheme" => self.auth_scheme.map(|s| s.to_string()),
+            "database" => self.database.clone(),
+            "tls_trust" => self.tls_trust.map(|t| match t {
+                TrustMode::System => "system".to_owned(),
+                TrustMode::All => "all".to_owned(),
+            }),
+            "tls_ca_file" => path_string(&self.tls_ca_file),
+            "max_connections" => self.max_connections.map(|n| n.to_string()),
+            "fetch_size" => self.fetch_size.map(|n| n.to_string()),
+            "connect_timeout" => self.connect_timeout.map(|d| format!("{}ms", d.as_millis())),
+            "transaction_timeout" => self
+                .transaction_timeout
+                .map(|d| format!("{}ms", d.as_millis())),
+            "dialect" => self.dialect.map(|d| d.to_string()),
+            _ => None,
+        }
+    }
+}
+
+fn path_string(path: &Option<PathBuf>) -> Option<String> {
+    path.as_ref().map(|p| p.to_string_lossy().into_owned())
+}
+
+/// A parsed config file.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigFile {
+    default_profile: Option<String>,
+    base: BTreeMap<String, Value>,
+    profiles: BTreeMap<String, BTreeMap<String, Value>>,
+}
+
+impl ConfigFile {
+    /// Read and parse the config file at `path`.
+    pub fn from_path<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read config file {}: {e}", path.display()))?;
+        Self::parse(&text).map_err(|e| format!("{}: {e}", path.display()).into())
+    }
+
+    /// Parse config file contents.
+    pub fn parse(text: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let mut file = ConfigFile::default();
+        let mut profile: Option<String> = None;
+        for (number, line) in text.lines().enumerate() {
+            let error = |message: String| format!("line {}: {message}", number + 1);
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[') {
+                let header = header
+                    .strip_suffix(']')
+                    .ok_or_else(|| error("unterminated table header".into()))?;
+                let path = parse_key_path(header.trim()).map_err(error)?;
+                match path.as_slice() {
+                    [table, name] if table == "profiles" => {
+                        if file.profiles.contains_key(name) {
+                            return Err(error(format!("profile `{name}` defined twice")).into());
+                        }
+                        file.profiles.insert(name.clone(), BTreeMap::new());
+                        profile = Some(name.clone());
+                    }
+                    _ => {
+                        return Err(error(format!(
+                            "unexpected table `[{header}]`; use `[profiles.<name>]`"
+                        ))
+                        .into());
+                    }
+                }
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected `key = value`".into()))?;
+            let key = match parse_key_path(key.trim()).map_err(error)?.as_slice() {
+                [key] => key.clone(),
+                _ => return Err(error("dotted keys are not supported".into()).into()),
+            };
+            let value = Value::parse(value.trim()).map_err(error)?;
+            match &profile {
+                None if key == "default_profile" => match value {
+                    Value::String(name) => file.default_profile = Some(name),
+                    _ => return Err(error("`default_profile` must be a string".into()).into()),
+                },
+                _ if !KEYS.contains(&key.as_str()) => {
+                    let message = match UNSUPPORTED.iter().find(|(k, _)| *k == key) {
+                        Some((_, reason)) => format!("`{key}` is not supported: {reason}"),
+                        None => format!(
+                            "unknown setting `{key}`; expected one of {}",
+                            KEYS.join(", ")
+                        ),
+                    };
+                    return Err(error(message).into());
+                }
+                None => insert(&mut file.base, key, value).map_err(error)?,
+                Some(name) => {
+                    let table = file.profiles.get_mut(name).expect("profile table exists");
+                    insert(table, key, value).map_err(error)?
+                }
+            }
+        }
+        if let Some(name) = &file.default_profile
+            && !file.profiles.contains_key(name)
+        {
+            return Err(format!("default_profile `{name}` is not defined").into());
+        }
+        Ok(file)
+    }
+
+    /// Names of the profiles defined in the file.
+    pub fn profiles(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(String::as_str)
+    }
+}
+
+fn insert(table: &mut BTreeMap<String, Value>, key: String, value: Value) -> Result<(), String> {
+    if table.contains_key(&key) {
+        return Err(format!("`{key}` set twice"));
+    }
+    table.insert(key, value);
+    Ok(())
+}
+
+/// A config file value.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::String(s) => f.write_str(s),
+            Value::Integer(i) => write!(f, "{i}"),
+            Value::Bool(b) => write!(f, "{b}"),
+        }
+    }
+}
+
+impl Value {
+    fn parse(text: &str) -> Result<Self, String> {
+        if text.starts_with('"') || text.starts_with('\'') {
+            let (value, rest) = parse_string(text)?;
+            if !rest.trim().is_empty() {
+                return Err(format!("unexpected `{}` after string", rest.trim()));
+            }
+            return Ok(Value::String(value));
+        }
+        match text {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            _ => text
+                .replace('_', "")
+                .parse()
+                .map(Value::Integer)
+                .map_err(|_| format!("unsupported value `{text}`")),
+        }
+    }
+}
+
+/// Parse a basic (`"..."`) or literal (`'...'`) string at the start of
+/// `text`, returning it and the remaining text.
+fn parse_string(text: &str) -> Result<(String, &str), String> {
+    if let Some(rest) = text.strip_prefix('\'') {
+        let end = rest.find('\'').ok_or("unterminated string")?;
+        return Ok((rest[..end].to_owned(), &rest[end + 1..]));
+    }
+    let rest = text.strip_prefix('"').ok_or("expected a string")?;
+    let mut out = String::new();
+    let mut chars = rest.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((out, &rest[i + 1..])),
+            '\\' => match chars.next().map(|(_, c)| c) {
+                Some('"') => out.push('"'),
+                Some('\\') => out.push('\\'),
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('r') => out.push('\r'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                    let code = u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| format!("invalid escape `\\u{hex}`"))?;
+                    out.push(code);
+                }
+                other => return Err(format!("invalid escape `\\{}`", other.unwrap_or(' '))),
+            },
+            c => out.push(c),
+        }
+    }
+    Err("unterminated string".into())
+}
+
+/// Split a possibly dotted key such as `profiles."eu-west"` into its parts.
+fn parse_key_path(text: &str) -> Result<Vec<String>, String> {
+    let mut parts = Vec::new();
+    let mut rest = text.trim();
+    loop {
+        let (part, tail) = if rest.starts_with('"') || rest.starts_with('\'') {
+            parse_string(rest)?
+        } else {
+            let end = rest.find('.').unwrap_or(rest.len());
+            let part = rest[..end].trim();
+            let bare = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+            if part.is_empty() || !part.chars().all(bare) {
+                return Err(format!("invalid key `{text}`"));
+            }
+            (part.to_owned(), &rest[end..])
+        };
+        parts.push(part);
+        let tail = tail.trim();
+        if tail.is_empty() {
+            return Ok(parts);
+        }
+        rest = tail
+            .strip_prefix('.')
+            .ok_or_else(|| format!("invalid key `{text}`"))?
+            .trim();
+    }
+}
+
+/// Remove a trailing `#` comment that is not inside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+/// Replace `${VAR}` and `${VAR:-default}` with environment variables.
+fn interpolate(text: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some(tail) = after.strip_prefix('$') {
+            out.push('$');
+            rest = tail;
+        } else if let Some(body) = after.strip_prefix('{') {
+            let end = body.find('}').ok_or("unterminated `${`")?;
+            let (name, default) = match body[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&body[..end], None),
+            };
+            match (env::var(name), default) {
+                (Ok(value), _) => out.push_str(&value),
+                (Err(_), Some(default)) => out.push_str(default),
+                (Err(_), None) => {
+                    return Err(format!("environment variable `{name}` is not set"));
+                }
+            }
+            rest = &body[end + 1..];
+        } else {
+            out.push('$');
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = r#"
+# shared settings
+default_profile = "dev"
+username = "loader"
+
+[profiles.dev]
+uri = "bolt://localhost:7687" # local
+password = 'p#ss'
+
+[profiles."prod-eu"]
+uri = "neo4j+s://db.example.com"
+password = "${CONFIG_TEST_UNSET_PASSWORD}"
+database = "${CONFIG_TEST_UNSET_DB:-graph}"
+"#;
+
+    #[test]
+    fn resolves_profiles_and_overrides() {
+        let file = ConfigFile::parse(FILE).unwrap();
+        assert_eq!(file.profiles().collect::<Vec<_>>(), ["dev", "prod-eu"]);
+
+        let cfg = Neo4jConfig::resolve(Some(&file), None, &ConfigOverrides::default()).unwrap();
+        assert_eq!(cfg.uri, "bolt://localhost:7687");
+        assert_eq!(cfg.auth, Auth::basic("loader", "p#ss"));
+
+        let overrides = ConfigOverrides {
+            password: Some(Secret::new("secret")),
+            ..ConfigOverrides::default()
+        };
+        let cfg = Neo4jConfig::resolve(Some(&file), Some("prod-eu"), &overrides).unwrap();
+        assert_eq!(cfg.uri, "neo4j+s://db.example.com");
+        assert_eq!(cfg.auth, Auth::basic("loader", "secret"));
+        assert_eq!(cfg.database.as_deref(), Some("graph"));
+
+        let err = Neo4jConfig::resolve(Some(&file), Some("prod-eu"), &ConfigOverrides::default())
+            .unwrap_err();
+        assert!(err.to_string().contains("CONFIG_TEST_UNSET_PASSWORD"));
+        assert!(
+            Neo4jConfig::resolve(Some(&file), Some("qa"), &ConfigOverrides::default()).is_err()
+        );
+    }
+
+    #[test]
+    fn redacts_and_reads_password_files() {
+        let cfg = Neo4jConfig {
+            uri: "bolt://localhost".into(),
+            auth: Auth::basic("neo4j", "hunter2"),
+            database: None,
+            tls: TlsConfig::default(),
+            max_connections: None,
+            fetch_size: None,
+            connect_timeout: None,
+            transaction_timeout: None,
+            dialect: None,
+        };
+        assert!(!format!("{cfg:?}").contains("hunter2"));
+        let Auth::Basic { password, .. } = &cfg.auth else {
+            unreachable!()
+        };
+        assert_eq!(password.to_string(), "[REDACTED]");
+
+        let path = std::env::temp_dir().join(format!("{}-password", std::process::id()));
+        std::fs::write(&path, "from-file\n").unwrap();
+        let file = ConfigFile::parse(&format!(
+            "uri = \"bolt://x\"\nusername = \"u\"\npassword_file = '{}'",
+            path.display()
+        ))
+        .unwrap();
+        let cfg = Neo4jConfig::resolve(Some(&file), None, &ConfigOverrides::default()).unwrap();
+        assert_eq!(cfg.auth, Auth::basic("u", "from-file"));
+
+        let both = ConfigOverrides {
+            password: Some(Secret::new("a")),
+            password_file: Some(path.clone()),
+            ..ConfigOverrides::default()
+        };
+        assert!(Neo4jConfig::resolve(Some(&file), None, &both).is_err());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn selects_auth_scheme() {
+        let file = ConfigFile::parse(
+            "uri = \"bolt://x\"\n\
+             [profiles.dev]\nauth_scheme = \"empty-basic\"\n\
+             [profiles.broken]\nauth_scheme = \"bearer\"\n\
+             [profiles.basic]\nauth_scheme = \"basic\"\nusername = \"u\"",
+        )
+        .unwrap();
+        let resolve =
+            |profile| Neo4jConfig::resolve(Some(&file), Some(profile), &ConfigOverrides::default());
+        assert_eq!(resolve("dev").unwrap().auth, Auth::EmptyBasic);
+        // The driver cannot send bearer tokens, Bolt's `none` scheme or
+        // impersonate.
+        let err = resolve("broken").unwrap_err().to_string();
+        assert!(
+            err.contains("auth scheme `bearer` is not supported"),
+            "{err}"
+        );
+        assert!("none".parse::<AuthScheme>().is_err());
+        assert!(ConfigFile::parse("bearer_token = \"tok\"").is_err());
+        let err = ConfigFile::parse("impersonate = \"tenant-a\"")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("cannot impersonate"), "{err}");
+
+        let overrides = ConfigOverrides {
+            password: Some(Secret::new("p")),
+            ..ConfigOverrides::default()
+        };
+        let cfg = Neo4jConfig::resolve(Some(&file), Some("basic"), &overrides).unwrap();
+        assert_eq!(cfg.auth.scheme(), AuthScheme::Basic);
+        let overrides = ConfigOverrides {
+            auth_scheme: Some(AuthScheme::EmptyBasic),
+            ..ConfigOverrides::default()
+        };
+        let cfg = Neo4jConfig::resolve(Some(&file), Some("basic"), &overrides).unwrap();
+        assert_eq!(cfg.auth, Auth::EmptyBasic);
+        assert!("kerberos".parse::<AuthScheme>().is_err());
+    }
+
+    #[test]
+    fn reads_tls_settings() {
+        let base = "uri = \"bolt://x\"\nusername = \"u\"\npassword = \"p\"\n";
+        let file = ConfigFile::parse(&format!(
+            "{base}[profiles.dev]\ntls_trust = \"all\"\n[profiles.prod]\ntls_ca_file = \"ca.pem\""
+        ))
+        .unwrap();
+        let resolve = |profile| {
+            Neo4jConfig::resolve(Some(&file), Some(profile), &ConfigOverrides::default()).unwrap()
+        };
+        assert_eq!(resolve("dev").tls.trust, TrustMode::All);
+        assert_eq!(
+            resolve("prod").tls.ca_file.as_deref(),
+            Some(Path::new("ca.pem"))
+        );
+
+        // The driver cannot present client certificates or override SNI.
+        let err = ConfigFile::parse(&format!("{base}tls_client_cert = \"c.pem\""))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("cannot present a client certificate"), "{err}");
+        assert!(ConfigFile::parse(&format!("{base}tls_server_name = \"db\"")).is_err());
+        let file = ConfigFile::parse(&format!("{base}tls_trust = \"none\"")).unwrap();
+        assert!(Neo4jConfig::resolve(Some(&file), None, &ConfigOverrides::default()).is_err());
+    }
+
+    #[test]
+    fn reads_pool_settings() {
+        let file = ConfigFile::parse(
+            "uri = \"bolt://x\"\nusername = \"u\"\npassword = \"p\"\n\
+             fetch_size = 500\nconnect_timeout = 5\ntransaction_timeout = \"2m\"",
+        )
+        .unwrap();
+        let cfg = Neo4jConfig::resolve(Some(&file), None, &ConfigOverrides::default()).unwrap();
+        assert_eq!(cfg.fetch_size, Some(500));
+        assert_eq!(cfg.connect_timeout, Some(Duration::from_secs(5)));
+        assert_eq!(cfg.transaction_timeout, Some(Duration::from_secs(120)));
+
+        let cfg = cfg.with_concurrency(32).unwrap();
+        assert_eq!(cfg.max_connections, Some(32));
+        let cfg = cfg.with_concurrency(8).unwrap();
+        assert_eq!(cfg.max_connections, Some(32));
+        assert!(cfg.with_concurrency(64).is_err());
+
+        let overrides = ConfigOverrides {
+            max_connections: Some(4),
+            connect_timeout: Some(Duration::from_millis(250)),
+            ..ConfigOverrides::default()
+        };
+        let cfg = Neo4jConfig::resolve(Some(&file), None, &overrides).unwrap();
+        assert_eq!(cfg.connect_timeout, Some(Duration::from_millis(250)));
+        assert_eq!(cfg.with_concurrency(1).unwrap().max_connections, Some(4));
+
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert!(parse_duration("0s").is_err());
+        assert!(parse_duration("5 days").is_err());
+        let file = ConfigFile::parse(
+            "uri = \"bolt://x\"\nusername = \"u\"\npassword = \"p\"\nfetch_size = 0",
+        )
+        .unwrap();
+        assert!(Neo4jConfig::resolve(Some(&file), None, &ConfigOverrides::default()).is_err());
+    }
+
+    #[test]
+    fn reads_dialect() {
+        let base = "uri = \"bolt://x\"\nusername = \"u\"\npassword = \"p\"\n";
+        let resolve = |extra: &str, overrides: &ConfigOverrides| {
+            let file = ConfigFile::parse(&format!("{base}{extra}")).unwrap();
+            Neo4jConfig::resolve(Some(&file), None, overrides)
+        };
+        let defaults = ConfigOverrides::default();
+        assert_eq!(resolve("", &defaults).unwrap().dialect, None);
+        assert_eq!(
+            resolve("dialect = \"auto\"", &defaults).unwrap().dialect,
+            None
+        );
+        assert_eq!(
+            resolve("dialect = \"memgraph\"", &defaults)
+                .unwrap()
+                .dialect,
+            Some(Dialect::Memgraph)
+        );
+        assert!(resolve("dialect = \"neo4j\"", &defaults).is_err());
+
+        let overrides = ConfigOverrides {
+            dialect: Some(Dialect::Neo4j4),
+            ..ConfigOverrides::default()
+        };
+        assert_eq!(
+            resolve("dialect = \"memgraph\"", &overrides)
+                .unwrap()
+                .dialect,
+            Some(Dialect::Neo4j4)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        assert!(ConfigFile::parse("uri = bolt://x").is_err());
+        assert!(ConfigFile::parse("[servers.dev]").is_err());
+        assert!(ConfigFile::parse("[profiles.dev]\nurl = \"x\"").is_err());
+        assert!(ConfigFile::parse("default_profile = \"qa\"").is_err());
+        assert!(ConfigFile::parse("[profiles.dev]\nuri = \"a\"\nuri = \"b\"").is_err());
+    }
+
+    #[test]
+    fn interpolates_environment() {
+        assert_eq!(interpolate("a$$b").unwrap(), "a$b");
+        assert_eq!(
+            interpolate("${CONFIG_TEST_UNSET_X:-x}/${CONFIG_TEST_UNSET_Y:-}").unwrap(),
+            "x/"
+        );
+        assert!(interpolate("${CONFIG_TEST_UNSET_X}").is_err());
+    }
 }
@@ -0,0 +1,547 @@
+//! Conversion of Arrow record batches into Bolt values.
+//!
+//! Arrow sources map values to their native Bolt types. Parquet files keep
+//! the values of the Parquet record API's JSON form, which they were loaded
+//! with before decoding went through Arrow; see [`Mapping::ParquetJson`].
+
+use std::sync::Arc;
+
+use arrow::array::temporal_conversions::{date32_to_datetime, timestamp_s_to_datetime};
+use arrow::array::{Array, ArrayRef, AsArray, make_array};
+use arrow::buffer::NullBuffer;
+use arrow::compute::cast;
+use arrow::datatypes::{
+    DataType, Date32Type, Date64Type, Decimal128Type, Decimal256Type, Float16Type, Float32Type,
+    Float64Type, Int8Type, Int16Type, Int32Type, Int64Type, Time32MillisecondType,
+    Time32SecondType, Time64MicrosecondType, Time64NanosecondType, TimeUnit,
+    TimestampMicrosecondType, TimestampMillisecondType, TimestampNanosecondType,
+    TimestampSecondType, UInt8Type, UInt16Type, UInt32Type, UInt64Type,
+};
+use arrow::datatypes::{Field, Schema};
+use arrow::record_batch::RecordBatch;
+use neo4rs::{BoltList, BoltMap, BoltNull, BoltString, BoltType};
+
+use crate::loader::Record;
+
+/// How Arrow values are mapped to Bolt values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mapping {
+    /// Native Bolt values: dates and times become temporal values, decimals
+    /// become floats and binary columns become bytes.
+    Native,
+    /// The values of the Parquet record API's JSON form. Dates, timestamps
+    /// and decimals become strings, bytes become base64 strings and
+    /// non-finite floats become null. Timestamps are written in UTC to the
+    /// second, as `2024-01-01 12:00:00 +00:00`. As in the record API,
+    /// millisecond times are written as timestamps and nanosecond
+    /// timestamps and times are sent as plain integers.
+    ParquetJson,
+}
+
+/// Convert every row of `batch` into a [`Record`] with native Bolt values.
+pub(crate) fn batch_to_records(
+    batch: &RecordBatch,
+) -> Result<Vec<Record>, Box<dyn std::error::Error + Send + Sync>> {
+    batch_to_records_as(batch, Mapping::Native)
+}
+
+/// Convert every row of `batch` into a [`Record`] using `mapping`.
+pub(crate) fn batch_to_records_as(
+    batch: &RecordBatch,
+    mapping: Mapping,
+) -> Result<Vec<Record>, Box<dyn std::error::Error + Send + Sync>> {
+    let schema = batch.schema();
+    let mut columns = Vec::with_capacity(batch.num_columns());
+    for column in batch.columns() {
+        columns.push(array_to_bolt_as(column, mapping)?.into_iter());
+    }
+    let mut records = Vec::with_capacity(batch.num_rows());
+    for _ in 0..batch.num_rows() {
+        let mut record = Vec::with_capacity(columns.len());
+        for (field, column) in schema.fields().iter().zip(columns.iter_mut()) {
+            // Every column yields exactly `num_rows` values.
+            let value = column.next().unwrap_or(BoltType::Null(BoltNull));
+            record.push((field.name().clone(), value));
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Replace struct columns by one column per leaf field.
+///
+/// A field `b` nested in column `a` becomes the column `a{separator}b`. A leaf
+/// is null wherever any of its enclosing structs is null.
+pub(crate) fn flatten_structs(
+    batch: &RecordBatch,
+    separator: &str,
+) -> Result<RecordBatch, Box<dyn std::error::Error + Send + Sync>> {
+    let mut fields = Vec::new();
+    let mut columns = Vec::new();
+    for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+        flatten_column(
+            field.name(),
+            field,
+            column.clone(),
+            separator,
+            &mut fields,
+            &mut columns,
+        )?;
+    }
+    Ok(RecordBatch::try_new(
+        Arc::new(Schema::new(fields)),
+        columns,
+    )?)
+}
+
+fn flatten_column(
+    name: &str,
+    field: &Field,
+    column: ArrayRef,
+    separator: &str,
+    fields: &mut Vec<Field>,
+    columns: &mut Vec<ArrayRef>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let DataType::Struct(children) = field.data_type() else {
+        fields.push(field.clone().with_name(name));
+        columns.push(column);
+        return Ok(());
+    };
+    let parent = column.as_struct();
+    for (child, child_column) in children.iter().zip(parent.columns()) {
+        let nulls = NullBuffer::union(parent.nulls(), child_column.nulls());
+        let data = child_column.to_data().into_builder().nulls(nulls).build()?;
+        flatten_column(
+            &format!("{name}{separator}{}", child.name()),
+            &child.as_ref().clone().with_nullable(true),
+            make_array(data),
+            separator,
+            fields,
+            columns,
+        )?;
+    }
+    Ok(())
+}
+
+macro_rules! primitive {
+    ($array:expr, $ty:ty, $f:expr) => {{
+        let array = $array.as_primitive::<$ty>();
+        (0..array.len())
+            .map(|i| {
+                if array.is_null(i) {
+                    Ok(BoltType::Null(BoltNull))
+                } else {
+                    $f(array.value(i))
+                }
+            })
+            .collect()
+    }};
+}
+
+macro_rules! temporal {
+    ($array:expr, $ty:ty, $convert:ident) => {{
+        let array = $array.as_primitive::<$ty>();
+        (0..array.len())
+            .map(|i| {
+                if array.is_null(i) {
+                    return Ok(BoltType::Null(BoltNull));
+                }
+                array
+                    .$convert(i)
+                    .map(BoltType::from)
+                    .ok_or_else(|| out_of_range(array.data_type()))
+            })
+            .collect()
+    }};
+}
+
+macro_rules! timestamp {
+    ($array:expr, $ty:ty, $tz:expr) => {{
+        match $tz {
+            None => temporal!($array, $ty, value_as_datetime),
+            Some(tz) => {
+                let array = $array.as_primitive::<$ty>();
+                let tz: arrow::array::timezone::Tz = tz.parse()?;
+                (0..array.len())
+                    .map(|i| {
+                        if array.is_null(i) {
+                            return Ok(BoltType::Null(BoltNull));
+                        }
+                        array
+                            .value_as_datetime_with_tz(i, tz)
+                            .map(|dt| BoltType::from(dt.fixed_offset()))
+                            .ok_or_else(|| out_of_range(array.data_type()))
+                    })
+                    .collect()
+            }
+        }
+    }};
+}
+
+/// Convert an Arrow array into one native Bolt value per slot.
+pub(crate) fn array_to_bolt(
+    array: &ArrayRef,
+) -> Result<Vec<BoltType>, Box<dyn std::error::Error + Send + Sync>> {
+    array_to_bolt_as(array, Mapping::Native)
+}
+
+/// Convert an Arrow array into one Bolt value per slot using `mapping`.
+fn array_to_bolt_as(
+    array: &ArrayRef,
+    mapping: Mapping,
+) -> Result<Vec<BoltType>, Box<dyn std::error::Error + Send + Sync>> {
+    if mapping == Mapping::ParquetJson
+        && let Some(values) = parquet_json(array)?
+    {
+        return Ok(values);
+    }
+    let int = |v: i64| Ok(BoltType::from(v));
+    let float = |v: f64| Ok(BoltType::from(v));
+    match array.data_type() {
+        DataType::Null => Ok(vec![BoltType::Null(BoltNull); array.len()]),
+        DataType::Boolean => {
+            let array = array.as_boolean();
+            Ok(array.iter().map(BoltType::from).collect())
+        }
+        DataType::Int8 => primitive!(array, Int8Type, |v: i8| int(v.into())),
+        DataType::Int16 => primitive!(array, Int16Type, |v: i16| int(v.into())),
+        DataType::Int32 => primitive!(array, Int32Type, |v: i32| int(v.into())),
+        DataType::Int64 => primitive!(array, Int64Type, int),
+        DataType::UInt8 => primitive!(array, UInt8Type, |v: u8| int(v.into())),
+        DataType::UInt16 => primitive!(array, UInt16Type, |v: u16| int(v.into())),
+        DataType::UInt32 => primitive!(array, UInt32Type, |v: u32| int(v.into())),
+        DataType::UInt64 => primitive!(array, UInt64Type, |v: u64| {
+            i64::try_from(v)
+                .map(BoltType::from)
+                .map_err(|_| out_of_range(&DataType::UInt64))
+        }),
+        DataType::Float16 => primitive!(array, Float16Type, |v| float(f64::from(v))),
+        DataType::Float32 => primitive!(array, Float32Type, |v: f32| float(v.into())),
+        DataType::Float64 => primitive!(array, Float64Type, float),
+        DataType::Decimal128(_, scale) => {
+            let divisor = 10f64.powi(i32::from(*scale));
+            primitive!(array, Decimal128Type, |v: i128| float(v as f64 / divisor))
+        }
+        DataType::Utf8 => Ok(array
+            .as_string::<i32>()
+            .iter()
+            .map(BoltType::from)
+            .collect()),
+        DataType::LargeUtf8 => Ok(array
+            .as_string::<i64>()
+            .iter()
+            .map(BoltType::from)
+            .collect()),
+        DataType::Utf8View => Ok(array.as_string_view().iter().map(BoltType::from).collect()),
+        DataType::Binary => Ok(array
+            .as_binary::<i32>()
+            .iter()
+            .map(BoltType::from)
+            .collect()),
+        DataType::LargeBinary => Ok(array
+            .as_binary::<i64>()
+            .iter()
+            .map(BoltType::from)
+            .collect()),
+        DataType::BinaryView => Ok(array.as_binary_view().iter().map(BoltType::from).collect()),
+        DataType::FixedSizeBinary(_) => Ok(array
+            .as_fixed_size_binary()
+            .iter()
+            .map(BoltType::from)
+            .collect()),
+        DataType::Date32 => temporal!(array, Date32Type, value_as_date),
+        DataType::Date64 => temporal!(array, Date64Type, value_as_date),
+        DataType::Time32(TimeUnit::Second) => temporal!(array, Time32SecondType, value_as_time),
+        DataType::Time32(TimeUnit::Millisecond) => {
+            temporal!(array, Time32MillisecondType, value_as_time)
+        }
+        DataType::Time64(TimeUnit::Microsecond) => {
+            temporal!(array, Time64MicrosecondType, value_as_time)
+        }
+        DataType::Time64(TimeUnit::Nanosecond) => {
+            temporal!(array, Time64NanosecondType, value_as_time)
+        }
+        DataType::Timestamp(TimeUnit::Second, tz) => timestamp!(array, TimestampSecondType, tz),
+        DataType::Timestamp(TimeUnit::Millisecond, tz) => {
+            timestamp!(array, TimestampMillisecondType, tz)
+        }
+        DataType::Timestamp(TimeUnit::Microsecond, tz) => {
+            timestamp!(array, TimestampMicrosecondType, tz)
+        }
+        DataType::Timestamp(TimeUnit::Nanosecond, tz) => {
+            timestamp!(array, TimestampNanosecondType, tz)
+        }
+        DataType::List(_) => {
+            let array = array.as_list::<i32>();
+            list_to_bolt(array, array.values(), mapping, |i| {
+                let offsets = array.value_offsets();
+                (offsets[i] as usize, offsets[i + 1] as usize)
+            })
+        }
+        DataType::LargeList(_) => {
+            let array = array.as_list::<i64>();
+            list_to_bolt(array, array.values(), mapping, |i| {
+                let offsets = array.value_offsets();
+                (offsets[i] as usize, offsets[i + 1] as usize)
+            })
+        }
+        DataType::FixedSizeList(_, size) => {
+            let array = array.as_fixed_size_list();
+            let size = *size as usize;
+            list_to_bolt(array, array.values(), mapping, |i| {
+                (i * size, (i + 1) * size)
+            })
+        }
+        DataType::Struct(fields) => {
+            let array = array.as_struct();
+            let mut children = Vec::with_capacity(fields.len());
+            for column in array.columns() {
+                children.push(array_to_bolt_as(column, mapping)?);
+            }
+            Ok((0..array.len())
+                .map(|i| {
+                    if array.is_null(i) {
+                        return BoltType::Null(BoltNull);
+                    }
+                    let map: BoltMap = fields
+                        .iter()
+                        .zip(children.iter())
+                        .map(|(f, c)| (BoltString::from(f.name().as_str()), c[i].clone()))
+                        .collect();
+                    BoltType::Map(map)
+                })
+                .collect())
+        }
+        DataType::Map(..) => {
+            let array = array.as_map();
+            let keys = array_to_bolt_as(array.keys(), mapping)?;
+            let values = array_to_bolt_as(array.values(), mapping)?;
+            let offsets = array.value_offsets();
+            (0..array.len())
+                .map(|i| {
+                    if array.is_null(i) {
+                        return Ok(BoltType::Null(BoltNull));
+                    }
+                    let (start, end) = (offsets[i] as usize, offsets[i + 1] as usize);
+                    let mut map = BoltMap::new();
+                    for (key, value) in keys[start..end].iter().zip(&values[start..end]) {
+                        map.put(map_key(key)?, value.clone());
+                    }
+                    Ok(BoltType::Map(map))
+                })
+                .collect()
+        }
+        DataType::Dictionary(_, value_type) => array_to_bolt_as(&cast(array, value_type)?, mapping),
+        other => Err(format!("unsupported Arrow data type: {other}").into()),
+    }
+}
+
+fn list_to_bolt(
+    array: &dyn Array,
+    values: &ArrayRef,
+    mapping: Mapping,
+    bounds: impl Fn(usize) -> (usize, usize),
+) -> Result<Vec<BoltType>, Box<dyn std::error::Error + Send + Sync>> {
+    let values = array_to_bolt_as(values, mapping)?;
+    Ok((0..array.len())
+        .map(|i| {
+            if array.is_null(i) {
+                return BoltType::Null(BoltNull);
+            }
+            let (start, end) = bounds(i);
+            BoltType::List(BoltList::from(values[start..end].to_vec()))
+        })
+        .collect())
+}
+
+/// Bolt map keys are strings; other scalar keys are written out.
+fn map_key(key: &BoltType) -> Result<BoltString, Box<dyn std::error::Error + Send + Sync>> {
+    let key = match key {
+        BoltType::String(s) => return Ok(s.clone()),
+        BoltType::Integer(i) => i.value.to_string(),
+        BoltType::Float(f) => f.value.to_string(),
+        BoltType::Boolean(b) => b.value.to_string(),
+        other => return Err(format!("unsupported map key: {other:?}").into()),
+    };
+    Ok(BoltString::from(key))
+}
+
+/// Values of `array` in [`Mapping::ParquetJson`], or `None` for types it
+/// maps like [`Mapping::Native`].
+fn parquet_json(
+    array: &ArrayRef,
+) -> Result<Option<Vec<BoltType>>, Box<dyn std::error::Error + Send + Sync>> {
+    let string = |value: Option<String>| value.map_or(BoltType::Null(BoltNull), BoltType::from);
+    let values = match array.data_type() {
+        DataType::Float16 | DataType::Float32 | DataType::Float64 => array_to_bolt(array)?
+            .into_iter()
+            .map(|value| match value {
+                BoltType::Float(f) if !f.value.is_finite() => BoltType::Null(BoltNull),
+                value => value,
+            })
+            .collect(),
+        DataType::Decimal128(..) => {
+            let array = array.as_primitive::<Decimal128Type>();
+            (0..array.len())
+                .map(|i| string(array.is_valid(i).then(|| array.value_as_string(i))))
+                .collect()
+        }
+        DataType::Decimal256(..) => {
+            let array = array.as_primitive::<Decimal256Type>();
+            (0..array.len())
+                .map(|i| string(array.is_valid(i).then(|| array.value_as_string(i))))
+                .collect()
+        }
+        DataType::Date32 | DataType::Date64 => {
+            let days = cast(array, &DataType::Date32)?;
+            let dates: Result<Vec<BoltType>, Box<dyn std::error::Error + Send + Sync>> =
+                primitive!(days, Date32Type, |v: i32| {
+                    date32_to_datetime(v)
+                        .map(|t| BoltType::from(t.format("%Y-%m-%d").to_string()))
+                        .ok_or_else(|| out_of_range(&DataType::Date32))
+                });
+            dates?
+        }
+        DataType::Timestamp(TimeUnit::Nanosecond, _) | DataType::Time64(TimeUnit::Nanosecond) => {
+            array_to_bolt(&cast(array, &DataType::Int64)?)?
+        }
+        DataType::Timestamp(unit, _) => timestamp_strings(&cast(array, &DataType::Int64)?, unit)?,
+        DataType::Time32(unit) => {
+            let raw = cast(&cast(array, &DataType::Int32)?, &DataType::Int64)?;
+            timestamp_strings(&raw, unit)?
+        }
+        DataType::Binary => base64_strings(array.as_binary::<i32>().iter()),
+        DataType::LargeBinary => base64_strings(array.as_binary::<i64>().iter()),
+        DataType::BinaryView => base64_strings(array.as_binary_view().iter()),
+        DataType::FixedSizeBinary(_) => base64_strings(array.as_fixed_size_binary().iter()),
+        _ => return Ok(None),
+    };
+    Ok(Some(values))
+}
+
+/// Write times since the epoch in `unit`, held in an Int64 array, as UTC
+/// timestamps to the second.
+fn timestamp_strings(
+    raw: &ArrayRef,
+    unit: &TimeUnit,
+) -> Result<Vec<BoltType>, Box<dyn std::error::Error + Send + Sync>> {
+    let per_second = match unit {
+        TimeUnit::Second => 1,
+        TimeUnit::Millisecond => 1_000,
+        TimeUnit::Microsecond => 1_000_000,
+        TimeUnit::Nanosecond => 1_000_000_000,
+    };
+    primitive!(raw, Int64Type, |v: i64| {
+        timestamp_s_to_datetime(v / per_second)
+            .map(|t| BoltType::from(t.format("%Y-%m-%d %H:%M:%S +00:00").to_string()))
+            .ok_or_else(|| out_of_range(&DataType::Timestamp(*unit, None)))
+    })
+}
+
+fn base64_strings<'a>(values: impl Iterator<Item = Option<&'a [u8]>>) -> Vec<BoltType> {
+    values
+        .map(|value| value.map_or(BoltType::Null(BoltNull), |v| BoltType::from(base64(v))))
+        .collect()
+}
+
+/// Standard base64 with padding.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn out_of_range(data_type: &DataType) -> Box<dyn std::error::Error + Send + Sync> {
+    format!("value out of range for Arrow data type {data_type}").into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::StructArray;
+    use arrow::array::{Int64Array, ListArray, StringArray, TimestampMillisecondArray};
+    use arrow::datatypes::Int32Type;
+
+    #[test]
+    fn converts_rows_with_nulls() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec![Some("a"), None])),
+            ],
+        )
+        .unwrap();
+        let records = batch_to_records(&batch).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0][0], ("id".to_string(), BoltType::from(1i64)));
+        assert_eq!(records[0][1], ("name".to_string(), BoltType::from("a")));
+        assert_eq!(
+            records[1][1],
+            ("name".to_string(), BoltType::Null(BoltNull))
+        );
+    }
+
+    #[test]
+    fn converts_nested_and_temporal_values() {
+        let list: ArrayRef = Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+            Some(vec![Some(1), Some(2)]),
+            None,
+        ]));
+        let values = array_to_bolt(&list).unwrap();
+        assert_eq!(
+            values[0],
+            BoltType::List(BoltList::from(vec![
+                BoltType::from(1i64),
+                BoltType::from(2i64)
+            ]))
+        );
+        assert_eq!(values[1], BoltType::Null(BoltNull));
+
+        let ts: ArrayRef =
+            Arc::new(TimestampMillisecondArray::from(vec![0]).with_timezone("+01:00"));
+        assert!(matches!(
+            array_to_bolt(&ts).unwrap()[0],
+            BoltType::DateTime(_)
+        ));
+    }
+
+    #[test]
+    fn flattens_nested_structs() {
+        let inner = StructArray::from(vec![(
+            Arc::new(Field::new("city", DataType::Utf8, true)),
+            Arc::new(StringArray::from(vec![Some("Oslo"), Some("x")])) as ArrayRef,
+        )]);
+        let outer = StructArray::try_new(
+            vec![Field::new("address", inner.data_type().clone(), true)].into(),
+            vec![Arc::new(inner) as ArrayRef],
+            Some(NullBuffer::from(vec![true, false])),
+        )
+        .unwrap();
+        let schema = Schema::new(vec![Field::new("person", outer.data_type().clone(), true)]);
+        let batch = RecordBatch::try_new(Arc::new(schema), vec![Arc::new(outer)]).unwrap();
+
+        let flat = flatten_structs(&batch, ".").unwrap();
+        assert_eq!(flat.schema().field(0).name(), "person.address.city");
+        let records = batch_to_records(&flat).unwrap();
+        assert_eq!(records[0][0].1, BoltType::from("Oslo"));
+        assert_eq!(records[1][0].1, BoltType::Null(BoltNull));
+    }
+}
@@ -0,0 +1,206 @@
+//! CSV sources.
+//!
+//! Files are decoded with [`arrow::csv`] and written through the same path as
+//! the Parquet loaders. Gzip and zstd compressed files are decompressed
+//! transparently.
+
+use std::path::{Path, PathBuf};
+
+use arrow::csv::ReaderBuilder;
+use arrow::csv::reader::Format;
+use arrow::datatypes::SchemaRef;
+use neo4rs::Graph;
+
+use crate::convert::batch_to_records;
+use crate::job::{self, NodeLoad, RelationshipLoad};
+use crate::loader::Record;
+use crate::source::{Compression, open_decompressed};
+
+/// Options controlling how a CSV file is parsed.
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// Whether the first line holds column names. Defaults to `true`.
+    pub has_header: bool,
+    pub delimiter: u8,
+    pub quote: u8,
+    pub escape: Option<u8>,
+    /// Lines starting with this byte are skipped.
+    pub comment: Option<u8>,
+    /// Explicit schema. When `None` the schema is inferred from the file.
+    pub schema: Option<SchemaRef>,
+    /// Number of records sampled for schema inference; `None` reads them all.
+    pub infer_max_records: Option<usize>,
+    pub compression: Compression,
+    /// Number of rows decoded per Arrow batch.
+    pub batch_size: usize,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            has_header: true,
+            delimiter: b',',
+            quote: b'"',
+            escape: None,
+            comment: None,
+            schema: None,
+            infer_max_records: Some(1000),
+            compression: Compression::Auto,
+            batch_size: 8192,
+        }
+    }
+}
+
+impl CsvOptions {
+    fn format(&self) -> Format {
+        let mut format = Format::default()
+            .with_header(self.has_header)
+            .with_delimiter(self.delimiter)
+            .with_quote(self.quote);
+        if let Some(escape) = self.escape {
+            format = format.with_escape(escape);
+        }
+        if let Some(comment) = self.comment {
+            format = format.with_comment(comment);
+        }
+        format
+    }
+}
+
+/// Load a CSV file into Neo4j in parallel.
+/// Each row is mapped to properties of a node with the given label.
+pub async fn load_csv_nodes_parallel<P: AsRef<Path>>(
+    graph: Graph,
+    path: P,
+    label: &str,
+    options: &CsvOptions,
+    concurrency: usize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    NodeLoad::new(path.as_ref(), label)
+        .format(job::Format::Csv(options.clone()))
+        .concurrency(concurrency)
+        .run(&graph)
+        .await?;
+    Ok(())
+}
+
+/// Load relationships from a CSV file in parallel.
+///
+/// Columns are mapped exactly as in
+/// [`load_parquet_relationships_parallel`](crate::load_parquet_relationships_parallel).
+#[allow(clippy::too_many_arguments)]
+pub async fn load_csv_relationships_parallel<P: AsRef<Path>>(
+    graph: Graph,
+    path: P,
+    rel_type: &str,
+    start_label: &str,
+    start_id_col: &str,
+    start_id_prop: &str,
+    end_label: &str,
+    end_id_col: &str,
+    end_id_prop: &str,
+    options: &CsvOptions,
+    concurrency: usize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    RelationshipLoad::new(path.as_ref(), rel_type)
+        .format(job::Format::Csv(options.clone()))
+        .start(start_label, start_id_col)
+        .start_property(start_id_prop)
+        .end(end_label, end_id_col)
+        .end_property(end_id_prop)
+        .concurrency(concurrency)
+        .run(&graph)
+        .await?;
+    Ok(())
+}
+
+/// Decode a CSV file in a blocking task.
+pub(crate) async fn read_csv_records(
+    path: PathBuf,
+    options: CsvOptions,
+) -> Result<Vec<Record>, Box<dyn std::error::Error + Send + Sync>> {
+    tokio::task::spawn_blocking(move || read_csv_records_blocking(&path, &options)).await?
+}
+
+fn read_csv_records_blocking(
+    path: &Path,
+    options: &CsvOptions,
+) -> Result<Vec<Record>, Box<dyn std::error::Error + Send + Sync>> {
+    let format = options.format();
+    let schema = match &options.schema {
+        Some(schema) => schema.clone(),
+        None => {
+            let reader = open_decompressed(path, options.compression)?;
+            let (schema, _) = format.infer_schema(reader, options.infer_max_records)?;
+            std::sync::Arc::new(schema)
+        }
+    };
+    let reader = ReaderBuilder::new(schema)
+        .with_format(format)
+        .with_batch_size(options.batch_size)
+        .build_buffered(open_decompressed(path, options.compression)?)?;
+
+    let mut records = Vec::new();
+    for batch in reader {
+        records.extend(batch_to_records(&batch?)?);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neo4rs::BoltType;
+    use std::io::Write;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn infers_schema_and_reads_gzip() {
+        let path = temp_path("people.csv.gz");
+        let file = std::fs::File::create(&path).unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+        encoder
+            .write_all(b"id;name\n1;'Ann'\n2;'Bob; Jr'\n")
+            .unwrap();
+        encoder.finish().unwrap();
+
+        let options = CsvOptions {
+            delimiter: b';',
+            quote: b'\'',
+            ..CsvOptions::default()
+        };
+        let records = read_csv_records_blocking(&path, &options).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0][0], ("id".to_string(), BoltType::from(1i64)));
+        assert_eq!(
+            records[1][1],
+            ("name".to_string(), BoltType::from("Bob; Jr"))
+        );
+    }
+
+    #[test]
+    fn reads_headerless_file_with_schema() {
+        use arrow::datatypes::{DataType, Field, Schema};
+
+        let path = temp_path("ids.csv");
+        std::fs::write(&path, "7\n8\n").unwrap();
+        let options = CsvOptions {
+            has_header: false,
+            schema: Some(std::sync::Arc::new(Schema::new(vec![Field::new(
+                "id",
+                DataType::Utf8,
+                false,
+            )]))),
+            ..CsvOptions::default()
+        };
+        let records = read_csv_records_blocking(&path, &options).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(records[1][0], ("id".to_string(), BoltType::from("8")));
+    }
+}
@@ -0,0 +1,620 @@
+//! Multi-file Parquet datasets.
+//!
+//! A dataset path may be a single file, a directory searched recursively for
+//! `.parquet` files, or a glob pattern such as `data/*/part-*.parquet`.
+//! Directories named `key=value` between the dataset root and a file are read
+//! as Hive partition keys and can be attached to the loaded rows.
+//!
+//! Row groups are decoded independently on a bounded pool of blocking threads,
+//! so large files use every core rather than a single reader thread.
+
+use std::fs::File;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use arrow::compute::cast;
+use arrow::datatypes::{DataType, Schema, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use futures::{StreamExt, TryStreamExt};
+use neo4rs::{BoltNull, BoltType};
+use parquet::arrow::arrow_reader::{
+    ArrowReaderMetadata, ArrowReaderOptions, ParquetRecordBatchReaderBuilder, RowSelection,
+};
+use parquet::arrow::parquet_to_arrow_schema;
+use parquet::basic::Type as PhysicalType;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::schema::types::SchemaDescriptor;
+use tokio::sync::Semaphore;
+
+use crate::convert::{Mapping, batch_to_records_as};
+use crate::filter::Filter;
+use crate::loader::Record;
+
+/// Options for reading Parquet files and multi-file datasets.
+#[derive(Debug, Clone)]
+pub struct DatasetOptions {
+    /// Add every Hive partition key as a property of the created entity.
+    pub partition_properties: bool,
+    /// Partition keys whose values become additional node labels.
+    pub partition_labels: Vec<String>,
+    /// Maximum number of row groups decoded at the same time across all
+    /// files. Defaults to the number of available CPU cores.
+    pub decode_parallelism: usize,
+    /// Keep rows in file and row group order. When `false`, row groups are
+    /// emitted as soon as they are decoded.
+    pub preserve_order: bool,
+    /// Number of rows decoded per Arrow batch.
+    pub batch_size: usize,
+    /// Only load rows matching this filter. Row groups and pages whose
+    /// statistics rule out a match are not decoded. Partition keys taken from
+    /// the path cannot be filtered on.
+    pub filter: Option<Filter>,
+}
+
+impl Default for DatasetOptions {
+    fn default() -> Self {
+        Self {
+            partition_properties: false,
+            partition_labels: Vec::new(),
+            decode_parallelism: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            preserve_order: true,
+            batch_size: 8192,
+            filter: None,
+        }
+    }
+}
+
+/// Rows of a dataset sharing the same extra labels.
+pub(crate) struct LabelGroup {
+    pub labels: Vec<String>,
+    pub records: Vec<Record>,
+}
+
+struct DatasetFile {
+    path: PathBuf,
+    partitions: Vec<(String, String)>,
+}
+
+/// Read every file of the dataset at `path`, decoding up to `concurrency`
+/// files at a time, and group the rows by their partition labels.
+pub(crate) async fn read_parquet_dataset(
+    path: &Path,
+    options: &DatasetOptions,
+    concurrency: usize,
+) -> Result<Vec<LabelGroup>, Box<dyn std::error::Error + Send + Sync>> {
+    let root = path.to_path_buf();
+    let files = tokio::task::spawn_blocking(
+        move || -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
+            let files = resolve_files(&root)?;
+            check_schemas(&files)?;
+            Ok(files)
+        },
+    )
+    .await??;
+
+    let decoders = Arc::new(Semaphore::new(options.decode_parallelism.max(1)));
+    let decoded = futures::stream::iter(files).map(|file| {
+        let decoders = decoders.clone();
+        async move {
+            let records = read_parquet_file_records(file.path.clone(), decoders, options).await?;
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>((file, records))
+        }
+    });
+    let decoded: Vec<(DatasetFile, Vec<Record>)> = if options.preserve_order {
+        decoded.buffered(concurrency.max(1)).try_collect().await?
+    } else {
+        decoded
+            .buffer_unordered(concurrency.max(1))
+            .try_collect()
+            .await?
+    };
+
+    let mut groups: Vec<LabelGroup> = Vec::new();
+    for (DatasetFile { partitions, .. }, mut records) in decoded {
+        let mut labels = Vec::with_capacity(options.partition_labels.len());
+        for key in &options.partition_labels {
+            let (_, value) = partitions.iter().find(|(k, _)| k == key).ok_or_else(|| {
+                std::io::Error::other(format!("partition key `{key}` missing from file path"))
+            })?;
+            labels.push(escape_label(value));
+        }
+        if options.partition_properties {
+            for record in &mut records {
+                for (key, value) in &partitions {
+                    record.push((key.clone(), partition_value(value)));
+                }
+            }
+        }
+        match groups.iter_mut().find(|g| g.labels == labels) {
+            Some(group) => group.records.extend(records),
+            None => groups.push(LabelGroup { labels, records }),
+        }
+    }
+    Ok(groups)
+}
+
+/// Decode a single Parquet file, one blocking task per row group.
+///
+/// Each task holds a permit of `decoders` while it runs, which bounds the
+/// number of row groups decoded at once across all files sharing it. Row
+/// groups pruned by `options.filter` are never scheduled.
+async fn read_parquet_file_records(
+    path: PathBuf,
+    decoders: Arc<Semaphore>,
+    options: &DatasetOptions,
+) -> Result<Vec<Record>, Box<dyn std::error::Error + Send + Sync>> {
+    let meta_path = path.clone();
+    let page_index = options.filter.is_some();
+    let metadata = tokio::task::spawn_blocking(move || {
+        let reader_options = ArrowReaderOptions::new().with_page_index(page_index);
+        ArrowReaderMetadata::load(&File::open(meta_path)?, reader_options)
+            .map_err(Box::<dyn std::error::Error + Send + Sync>::from)
+    })
+    .await??;
+
+    let batch_size = options.batch_size;
+    let row_groups: Vec<usize> = match &options.filter {
+        Some(filter) => filter.prune_row_groups(metadata.schema(), metadata.metadata()),
+        None => (0..metadata.metadata().num_row_groups()).collect(),
+    };
+    let decoded = futures::stream::iter(row_groups).map(|row_group| {
+        let path = path.clone();
+        let metadata = metadata.clone();
+        let decoders = decoders.clone();
+        let filter = options.filter.clone();
+        async move {
+            let permit = decoders.acquire_owned().await?;
+            tokio::task::spawn_blocking(move || {
+                let _permit = permit;
+                decode_row_group(&path, metadata, row_group, batch_size, filter.as_ref())
+            })
+            .await?
+        }
+    });
+    let parallelism = options.decode_parallelism.max(1);
+    let groups: Vec<Vec<Record>> = if options.preserve_order {
+        decoded.buffered(parallelism).try_collect().await?
+    } else {
+        decoded.buffer_unordered(parallelism).try_collect().await?
+    };
+    Ok(groups.into_iter().flatten().collect())
+}
+
+fn decode_row_group(
+    path: &Path,
+    metadata: ArrowReaderMetadata,
+    row_group: usize,
+    batch_size: usize,
+    filter: Option<&Filter>,
+) -> Result<Vec<Record>, Box<dyn std::error::Error + Send + Sync>> {
+    let selection: Option<RowSelection> =
+        filter.and_then(|f| f.page_selection(metadata.schema(), metadata.metadata(), row_group));
+    if selection.as_ref().is_some_and(|s| !s.selects_any()) {
+        return Ok(Vec::new());
+    }
+    let mut builder =
+        ParquetRecordBatchReaderBuilder::new_with_metadata(File::open(path)?, metadata)
+            .with_row_groups(vec![row_group])
+            .with_batch_size(batch_size);
+    if let Some(selection) = selection {
+        builder = builder.with_row_selection(selection);
+    }
+    let int96 = int96_columns(builder.parquet_schema());
+    let mut records = Vec::new();
+    for batch in builder.build()? {
+        let batch = match filter {
+            Some(filter) => filter.apply(&batch?)?,
+            None => batch?,
+        };
+        let batch = millisecond_int96(batch, &int96)?;
+        records.extend(batch_to_records_as(&batch, Mapping::ParquetJson)?);
+    }
+    Ok(records)
+}
+
+/// Names of the top-level INT96 columns of a Parquet schema.
+fn int96_columns(schema: &SchemaDescriptor) -> Vec<String> {
+    schema
+        .root_schema()
+        .get_fields()
+        .iter()
+        .filter(|field| field.is_primitive() && field.get_physical_type() == PhysicalType::INT96)
+        .map(|field| field.name().to_string())
+        .collect()
+}
+
+/// Arrow reads INT96 timestamps with nanoseconds, but the record API reads
+/// them with milliseconds and writes them as timestamps rather than the
+/// integers of other nanosecond columns.
+fn millisecond_int96(
+    batch: RecordBatch,
+    columns: &[String],
+) -> Result<RecordBatch, Box<dyn std::error::Error + Send + Sync>> {
+    if columns.is_empty() {
+        return Ok(batch);
+    }
+    let schema = batch.schema();
+    let mut fields = Vec::with_capacity(schema.fields().len());
+    let mut arrays = Vec::with_capacity(batch.num_columns());
+    for (field, array) in schema.fields().iter().zip(batch.columns()) {
+        match field.data_type() {
+            DataType::Timestamp(TimeUnit::Nanosecond, tz) if columns.contains(field.name()) => {
+                let data_type = DataType::Timestamp(TimeUnit::Millisecond, tz.clone());
+                arrays.push(cast(array, &data_type)?);
+                fields.push(field.as_ref().clone().with_data_type(data_type));
+            }
+            _ => {
+                arrays.push(array.clone());
+                fields.push(field.as_ref().clone());
+            }
+        }
+    }
+    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)?)
+}
+
+/// Whether `path` contains glob meta characters.
+fn is_glob(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?', '['])
+}
+
+/// Expand `path` into the sorted list of data files it refers to.
+fn resolve_files(
+    path: &Path,
+) -> Result<Vec<DatasetFile>, Box<dyn std::error::Error + Send + Sync>> {
+    let (root, mut paths) = if is_glob(path) {
+        let root: PathBuf = path
+            .components()
+            .take_while(|c| !is_glob(Path::new(c.as_os_str())))
+            .collect();
+        let mut paths = Vec::new();
+        for entry in glob::glob(&path.to_string_lossy())? {
+            let entry = entry?;
+            if entry.is_file() {
+                paths.push(entry);
+            }
+        }
+        (root, paths)
+    } else if path.is_dir() {
+        let mut paths = Vec::new();
+        walk_dir(path, &mut paths)?;
+        (path.to_path_buf(), paths)
+    } else {
+        let root = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        (root, vec![path.to_path_buf()])
+    };
+    if paths.is_empty() {
+        return Err(format!("no Parquet files found at {}", path.display()).into());
+    }
+    paths.sort();
+    Ok(paths
+        .into_iter()
+        .map(|path| DatasetFile {
+            partitions: parse_partitions(&root, &path),
+            path,
+        })
+        .collect())
+}
+
+/// Recursively collect `.parquet` files, skipping hidden and `_`-prefixed
+/// entries such as `_SUCCESS` markers.
+fn walk_dir(dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        if name.starts_with('.') || name.starts_with('_') {
+            continue;
+        }
+        if path.is_dir() {
+            walk_dir(&path, out)?;
+        } else if path.extension().and_then(|e| e.to_str()) == Some("parquet") {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Parse the `key=value` directories between `root` and `file`.
+fn parse_partitions(root: &Path, file: &Path) -> Vec<(String, String)> {
+    let relative = file.strip_prefix(root).unwrap_or(file);
+    let Some(parent) = relative.parent() else {
+        return Vec::new();
+    };
+    parent
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .filter_map(|name| name.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+/// Ensure every file has the same columns with the same types.
+fn check_schemas(files: &[DatasetFile]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut expected: Option<(&Path, Vec<(String, arrow::datatypes::DataType)>)> = None;
+    for file in files {
+        let reader = SerializedFileReader::new(File::open(&file.path)?)?;
+        let meta = reader.metadata().file_metadata();
+        let schema = parquet_to_arrow_schema(meta.schema_descr(), meta.key_value_metadata())?;
+        let mut columns: Vec<_> = schema
+            .fields()
+            .iter()
+            .map(|f| (f.name().clone(), f.data_type().clone()))
+            .collect();
+        columns.sort_by(|a, b| a.0.cmp(&b.0));
+        match &expected {
+            None => expected = Some((&file.path, columns)),
+            Some((first, cols)) if *cols != columns => {
+                return Err(format!(
+                    "schema of {} is incompatible with {}",
+                    file.path.display(),
+                    first.display()
+                )
+                .into());
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Convert a partition directory value into a property value. Values that
+/// are integers written in canonical form become integers; others, such as
+/// the zero-padded `month=007` or `zip=02134`, stay strings.
+fn partition_value(value: &str) -> BoltType {
+    if value == "__HIVE_DEFAULT_PARTITION__" {
+        return BoltType::Null(BoltNull);
+    }
+    match value.parse::<i64>() {
+        Ok(int) if int.to_string() == value => BoltType::from(int),
+        _ => BoltType::from(value),
+    }
+}
+
+/// Quote a label so that arbitrary partition values are valid Cypher.
+fn escape_label(value: &str) -> String {
+    format!("`{}`", value.replace('`', "``"))
+}
+
+/// Append the partition labels of `group` to `label`.
+pub(crate) fn group_label(label: &str, group: &LabelGroup) -> String {
+    let mut out = label.to_string();
+    for extra in &group.labels {
+        out.push(':');
+        out.push_str(extra);
+    }
+    out
+}
+
+/// Concatenate the rows of every group; used where labels do not apply.
+pub(crate) fn flatten_groups(groups: Vec<LabelGroup>) -> Vec<Record> {
+    groups.into_iter().flat_map(|g| g.records).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::Int64Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use parquet::arrow::ArrowWriter;
+    use parquet::file::properties::WriterProperties;
+
+    fn write_parquet(path: &Path, column: &str, ids: Vec<i64>) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            column,
+            DataType::Int64,
+            false,
+        )]));
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(2)
+            .build();
+        let mut writer =
+            ArrowWriter::try_new(File::create(path).unwrap(), schema.clone(), Some(props)).unwrap();
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(Int64Array::from(ids))]).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}-{}", std::process::id(), name));
+        std::fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    #[test]
+    fn parses_hive_partitions() {
+        let parts = parse_partitions(
+            Path::new("/data"),
+            Path::new("/data/country=US/date=2024-01-01/part-0.parquet"),
+        );
+        assert_eq!(
+            parts,
+            vec![
+                ("country".to_string(), "US".to_string()),
+                ("date".to_string(), "2024-01-01".to_string())
+            ]
+        );
+        assert_eq!(partition_value("42"), BoltType::from(42i64));
+        assert_eq!(partition_value("-7"), BoltType::from(-7i64));
+        assert_eq!(partition_value("007"), BoltType::from("007"));
+        assert_eq!(partition_value("02134"), BoltType::from("02134"));
+        assert_eq!(partition_value("+5"), BoltType::from("+5"));
+        assert_eq!(escape_label("a`b"), "`a``b`");
+    }
+
+    #[tokio::test]
+    async fn reads_partitioned_directory() {
+        let dir = temp_dir("dataset");
+        write_parquet(&dir.join("country=US/part-0.parquet"), "id", vec![1, 2]);
+        write_parquet(&dir.join("country=NO/part-0.parquet"), "id", vec![3]);
+        std::fs::write(dir.join("_SUCCESS"), "").unwrap();
+
+        let options = DatasetOptions {
+            partition_properties: true,
+            partition_labels: vec!["country".to_string()],
+            ..DatasetOptions::default()
+        };
+        let groups = read_parquet_dataset(&dir, &options, 2).await.unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].labels, vec!["`NO`".to_string()]);
+        assert_eq!(group_label("City", &groups[1]), "City:`US`");
+        assert_eq!(
+            groups[1].records[0][1],
+            ("country".to_string(), BoltType::from("US"))
+        );
+
+        let glob = dir.join("country=*").join("*.parquet");
+        let groups = read_parquet_dataset(&glob, &DatasetOptions::default(), 2)
+            .await
+            .unwrap();
+        assert_eq!(flatten_groups(groups).len(), 3);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn rejects_incompatible_schemas() {
+        let dir = temp_dir("mismatch");
+        write_parquet(&dir.join("a.parquet"), "id", vec![1]);
+        write_parquet(&dir.join("b.parquet"), "key", vec![2]);
+        let err = read_parquet_dataset(&dir, &DatasetOptions::default(), 2).await;
+        assert!(err.is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn decodes_row_groups_in_order() {
+        let dir = temp_dir("row-groups");
+        let path = dir.join("ids.parquet");
+        write_parquet(&path, "id", (0..9).collect());
+        let options = DatasetOptions {
+            decode_parallelism: 3,
+            ..DatasetOptions::default()
+        };
+        let records = read_parquet_file_records(path, Arc::new(Semaphore::new(3)), &options)
+            .await
+            .unwrap();
+        let ids: Vec<BoltType> = records.into_iter().map(|r| r[0].1.clone()).collect();
+        assert_eq!(ids, (0..9i64).map(BoltType::from).collect::<Vec<_>>());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn prunes_row_groups_by_filter() {
+        let dir = temp_dir("filter");
+        let path = dir.join("ids.parquet");
+        write_parquet(&path, "id", (0..10).collect());
+        let filter = Filter::gt("id", 6).or(Filter::eq("id", 1));
+
+        let file = File::open(&path).unwrap();
+        let metadata = ArrowReaderMetadata::load(&file, ArrowReaderOptions::new()).unwrap();
+        let kept = filter.prune_row_groups(metadata.schema(), metadata.metadata());
+        assert_eq!(kept, vec![0, 3, 4]);
+
+        let options = DatasetOptions {
+            filter: Some(filter),
+            ..DatasetOptions::default()
+        };
+        let records = read_parquet_file_records(path, Arc::new(Semaphore::new(2)), &options)
+            .await
+            .unwrap();
+        let ids: Vec<BoltType> = records.into_iter().map(|r| r[0].1.clone()).collect();
+        assert_eq!(ids, [1i64, 7, 8, 9].map(BoltType::from).to_vec());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn maps_values_like_the_record_api() {
+        use arrow::array::{
+            ArrayRef, BinaryArray, BooleanArray, Date32Array, Decimal128Array, Float64Array,
+            ListArray, StringArray, TimestampMicrosecondArray, TimestampMillisecondArray,
+            TimestampNanosecondArray,
+        };
+        use arrow::datatypes::Int32Type;
+
+        let dir = temp_dir("types");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("types.parquet");
+        let columns: Vec<(&str, ArrayRef)> = vec![
+            ("id", Arc::new(Int64Array::from(vec![Some(1), None]))),
+            ("name", Arc::new(StringArray::from(vec![Some("a"), None]))),
+            ("flag", Arc::new(BooleanArray::from(vec![true, false]))),
+            ("score", Arc::new(Float64Array::from(vec![1.5, f64::NAN]))),
+            ("day", Arc::new(Date32Array::from(vec![19723, -1]))),
+            (
+                "at",
+                Arc::new(
+                    TimestampMillisecondArray::from(vec![1_704_110_400_123, 0])
+                        .with_timezone("+02:00"),
+                ),
+            ),
+            (
+                "at_us",
+                Arc::new(TimestampMicrosecondArray::from(vec![
+                    1_704_110_400_123_456,
+                    0,
+                ])),
+            ),
+            (
+                "at_ns",
+                Arc::new(TimestampNanosecondArray::from(vec![1, 2])),
+            ),
+            (
+                "price",
+                Arc::new(
+                    Decimal128Array::from(vec![12_345_678_901_234_567, -5])
+                        .with_precision_and_scale(20, 2)
+                        .unwrap(),
+                ),
+            ),
+            (
+                "blob",
+                Arc::new(BinaryArray::from(vec![b"hello".as_ref(), b"".as_ref()])),
+            ),
+            (
+                "tags",
+                Arc::new(ListArray::from_iter_primitive::<Int32Type, _, _>(vec![
+                    Some(vec![Some(1), Some(2)]),
+                    None,
+                ])),
+            ),
+        ];
+        let batch = RecordBatch::try_from_iter(columns).unwrap();
+        let mut writer =
+            ArrowWriter::try_new(File::create(&path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let expected: Vec<Record> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| {
+                row.unwrap()
+                    .get_column_iter()
+                    .map(|(key, field)| (key.clone(), field.to_json_value().try_into().unwrap()))
+                    .collect()
+            })
+            .collect();
+        let groups = read_parquet_dataset(&path, &DatasetOptions::default(), 1)
+            .await
+            .unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        let records = flatten_groups(groups);
+        assert_eq!(records, expected);
+        let row = &records[0];
+        assert_eq!(row[4].1, BoltType::from("2024-01-01"));
+        assert_eq!(row[5].1, BoltType::from("2024-01-01 12:00:00 +00:00"));
+        assert_eq!(row[8].1, BoltType::from("123456789012345.67"));
+        assert_eq!(row[9].1, BoltType::from("aGVsbG8="));
+        assert_eq!(records[1][3].1, BoltType::Null(BoltNull));
+    }
+}
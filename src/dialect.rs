@@ -0,0 +1,647 @@
+//! Cypher that differs between the servers the loader supports.
+//!
+//! Neo4j 4.4, Neo4j 5 (including the calendar-versioned 2025 releases) and
+//! Memgraph all accept the `UNWIND` writes of a load, but differ in their
+//! schema commands, in the procedures describing the server and in how a
+//! cluster is routed. Every query the loader sends is built by a
+//! [`Dialect`], which is detected from the server unless configured with
+//! `dialect` (`neo4j-4`, `neo4j-5`, `neo4j-5.<minor>` or `memgraph`).
+//!
+//! Neo4j 5 gained features along its minor releases, so the dialect keeps
+//! the minor version: `CALL { } IN TRANSACTIONS` reports statuses and
+//! continues past failures from 5.7 and runs transactions concurrently
+//! from 5.21. A plain `neo4j-5`, like the calendar-versioned releases, has
+//! all of them.
+
+use neo4rs::{Graph, query};
+
+use crate::doctor::{Entity, Lookup};
+use crate::loader::{NodeSpec, RelationshipSpec};
+use crate::run::RUN_PROPERTY;
+
+/// The Cypher variant spoken by a server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// Neo4j 4.x.
+    Neo4j4,
+    /// Neo4j 5 and the calendar-versioned releases that followed it.
+    /// `minor` is `None` for calendar versions and when only the major
+    /// version is known, which are assumed to support every 5.x feature.
+    Neo4j5 {
+        minor: Option<u32>,
+    },
+    Memgraph,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Dialect::Neo4j5 { minor: None }
+    }
+}
+
+impl std::str::FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "neo4j-4" => Ok(Dialect::Neo4j4),
+            "neo4j-5" => Ok(Dialect::Neo4j5 { minor: None }),
+            "memgraph" => Ok(Dialect::Memgraph),
+            _ => match s.strip_prefix("neo4j-5.").map(str::parse) {
+                Some(Ok(minor)) => Ok(Dialect::Neo4j5 { minor: Some(minor) }),
+                _ => Err(format!(
+                    "unknown dialect `{s}`; expected `neo4j-4`, `neo4j-5`, \
+                     `neo4j-5.<minor>` or `memgraph`"
+                )),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Dialect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dialect::Neo4j4 => f.write_str("neo4j-4"),
+            Dialect::Neo4j5 { minor: None } => f.write_str("neo4j-5"),
+            Dialect::Neo4j5 { minor: Some(minor) } => write!(f, "neo4j-5.{minor}"),
+            Dialect::Memgraph => f.write_str("memgraph"),
+        }
+    }
+}
+
+impl Dialect {
+    /// Ask the server behind `graph` which dialect it speaks: Neo4j reports
+    /// its version through `dbms.components()`, and Memgraph, which lacks
+    /// that procedure, through `SHOW VERSION`.
+    pub async fn detect(graph: &Graph) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let neo4j = match first_row(graph, Dialect::default().server_query()).await {
+            Ok(Some(row)) => return Self::from_neo4j_version(&row.get::<String>("version")?),
+            Ok(None) => "dbms.components() returned nothing".to_string(),
+            Err(e) => e.to_string(),
+        };
+        match first_row(graph, Dialect::Memgraph.server_query()).await {
+            Ok(Some(_)) => Ok(Dialect::Memgraph),
+            _ => Err(format!(
+                "cannot detect the server's Cypher dialect ({neo4j}); set `dialect` to \
+                 `neo4j-4`, `neo4j-5` or `memgraph`"
+            )
+            .into()),
+        }
+    }
+
+    /// The dialect of a Neo4j server reporting `version`, such as `4.4.12`,
+    /// `5.20.0` or `2025.01.0`.
+    fn from_neo4j_version(version: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let mut parts = version.split('.').map(|part| part.parse::<u32>().ok());
+        let major = parts
+            .next()
+            .flatten()
+            .ok_or_else(|| format!("cannot parse Neo4j version `{version}`"))?;
+        match major {
+            4 => Ok(Dialect::Neo4j4),
+            5 => Ok(Dialect::Neo4j5 {
+                minor: Some(parts.next().flatten().ok_or_else(|| {
+                    format!("cannot parse the minor version of Neo4j `{version}`")
+                })?),
+            }),
+            6.. => Ok(Dialect::Neo4j5 { minor: None }),
+            _ => Err(format!("Neo4j {version} is not supported; use 4.4 or later").into()),
+        }
+    }
+
+    /// Whether `CALL { } IN TRANSACTIONS` accepts `ON ERROR CONTINUE` and
+    /// `REPORT STATUS`, which came with Neo4j 5.7.
+    fn reports_status(&self) -> bool {
+        matches!(self, Dialect::Neo4j5 { minor } if minor.is_none_or(|m| m >= 7))
+    }
+
+    /// Whether `CALL { } IN CONCURRENT TRANSACTIONS` is available, which
+    /// came with Neo4j 5.21.
+    fn concurrent_transactions(&self) -> bool {
+        matches!(self, Dialect::Neo4j5 { minor } if minor.is_none_or(|m| m >= 21))
+    }
+
+    /// `UNWIND` query creating, or merging on `spec.keys`, one node per row.
+    /// With `tag_run`, created nodes get the run id `$run`; see
+    /// [`crate::run`].
+    pub(crate) fn write_nodes(&self, spec: &NodeSpec<'_>, tag_run: bool) -> String {
+        format!("UNWIND $rows AS row {}", node_body(spec, tag_run))
+    }
+
+    /// `UNWIND` query matching both endpoints and creating, or merging on
+    /// `spec.keys`, the relationship of each row.
+    pub(crate) fn write_relationships(&self, spec: &RelationshipSpec<'_>, tag_run: bool) -> String {
+        format!("UNWIND $rows AS row {}", relationship_body(spec, tag_run))
+    }
+
+    /// Like [`write_nodes`](Self::write_nodes), but committed by the server
+    /// in transactions of `rows` rows; see
+    /// [`in_transactions`](Self::in_transactions).
+    pub(crate) fn write_nodes_in_transactions(
+        &self,
+        spec: &NodeSpec<'_>,
+        tag_run: bool,
+        rows: usize,
+        concurrency: usize,
+    ) -> Result<String, String> {
+        self.in_transactions(&node_body(spec, tag_run), rows, concurrency)
+    }
+
+    /// Like [`write_relationships`](Self::write_relationships), but
+    /// committed by the server in transactions of `rows` rows.
+    pub(crate) fn write_relationships_in_transactions(
+        &self,
+        spec: &RelationshipSpec<'_>,
+        tag_run: bool,
+        rows: usize,
+        concurrency: usize,
+    ) -> Result<String, String> {
+        self.in_transactions(&relationship_body(spec, tag_run), rows, concurrency)
+    }
+
+    /// Run `body` for every row of `$rows` in server-side transactions of
+    /// `rows` rows, `concurrency` at a time.
+    ///
+    /// Neo4j 5.7 and later continue past failed transactions and return
+    /// one row per outcome with the columns `committed`, `error`, `rows`
+    /// and `transactions`. Older Neo4j releases (`IN TRANSACTIONS`) and
+    /// Memgraph (`USING PERIODIC COMMIT`) run the transactions one at a
+    /// time, stop at the first failure and return nothing. Concurrency
+    /// above 1 needs Neo4j 5.21.
+    fn in_transactions(
+        &self,
+        body: &str,
+        rows: usize,
+        concurrency: usize,
+    ) -> Result<String, String> {
+        if concurrency > 1 && !self.concurrent_transactions() {
+            return Err(format!(
+                "concurrent server-side transactions need Neo4j 5.21 or later; use \
+                 concurrency 1 with {self}"
+            ));
+        }
+        Ok(match self {
+            Dialect::Neo4j5 { .. } if self.reports_status() => {
+                let concurrent = if concurrency > 1 {
+                    format!("{concurrency} CONCURRENT ")
+                } else {
+                    String::new()
+                };
+                format!(
+                    "UNWIND $rows AS row \
+                     CALL {{ WITH row {body} }} IN {concurrent}TRANSACTIONS OF {rows} ROWS \
+                     ON ERROR CONTINUE REPORT STATUS AS status \
+                     RETURN status.committed AS committed, status.errorMessage AS error, \
+                     count(*) AS rows, count(DISTINCT status.transactionId) AS transactions"
+                )
+            }
+            Dialect::Neo4j4 | Dialect::Neo4j5 { .. } => format!(
+                "UNWIND $rows AS row CALL {{ WITH row {body} }} IN TRANSACTIONS OF {rows} ROWS"
+            ),
+            Dialect::Memgraph => {
+                format!("USING PERIODIC COMMIT {rows} UNWIND $rows AS row {body}")
+            }
+        })
+    }
+
+    /// Procedure call returning the routing table of a Neo4j cluster.
+    /// Memgraph clusters are routed by the Bolt `ROUTE` message instead,
+    /// which the driver does not send.
+    pub(crate) fn routing_query(&self) -> Result<&'static str, String> {
+        match self {
+            Dialect::Neo4j4 | Dialect::Neo4j5 { .. } => {
+                Ok("CALL dbms.routing.getRoutingTable($context, $database) \
+                 YIELD ttl, servers \
+                 UNWIND servers AS server \
+                 RETURN ttl, server.role AS role, server.addresses AS addresses")
+            }
+            Dialect::Memgraph => Err(
+                "Memgraph clusters cannot be routed by the loader; connect to the main \
+                 instance with a bolt:// URI"
+                    .to_string(),
+            ),
+        }
+    }
+
+    /// Query describing the server. Neo4j returns `name`, `version` and
+    /// `edition`, Memgraph only `version`.
+    pub(crate) fn server_query(&self) -> &'static str {
+        match self {
+            Dialect::Neo4j4 | Dialect::Neo4j5 { .. } => {
+                "CALL dbms.components() YIELD name, versions, edition \
+                 RETURN name, versions[0] AS version, edition"
+            }
+            Dialect::Memgraph => "SHOW VERSION",
+        }
+    }
+
+    /// Query returning the `name` of the database in use, if the server
+    /// has one.
+    pub(crate) fn database_query(&self) -> Option<&'static str> {
+        match self {
+            Dialect::Neo4j4 | Dialect::Neo4j5 { .. } => {
+                Some("CALL db.info() YIELD name RETURN name")
+            }
+            Dialect::Memgraph => None,
+        }
+    }
+
+    /// Query returning as `transaction` the id of the last transaction
+    /// committed on the writer of `$database`, run on the `system`
+    /// database. Memgraph has no such id.
+    pub(crate) fn last_transaction_query(&self) -> Option<&'static str> {
+        match self {
+            Dialect::Neo4j4 => Some(
+                "SHOW DATABASE $database YIELD role, lastCommittedTxn \
+                 WHERE role IN ['leader', 'standalone'] \
+                 RETURN lastCommittedTxn AS transaction",
+            ),
+            Dialect::Neo4j5 { .. } => Some(
+                "SHOW DATABASE $database YIELD writer, lastCommittedTxn WHERE writer \
+                 RETURN lastCommittedTxn AS transaction",
+            ),
+            Dialect::Memgraph => None,
+        }
+    }
+
+    /// Query returning the current `user` and, on Neo4j, their `roles`.
+    pub(crate) fn current_user_query(&self) -> &'static str {
+        match self {
+            Dialect::Neo4j4 | Dialect::Neo4j5 { .. } => {
+                "SHOW CURRENT USER YIELD user, roles RETURN user, roles"
+            }
+            Dialect::Memgraph => "SHOW CURRENT USER",
+        }
+    }
+
+    /// Queries listing indexes and then constraints. Neo4j returns `name`,
+    /// `type`, `entityType`, `labelsOrTypes` and `properties`; Memgraph
+    /// returns `index type` or `constraint type`, `label` and `property` or
+    /// `properties`.
+    pub(crate) fn schema_queries(&self) -> [&'static str; 2] {
+        match self {
+            Dialect::Neo4j4 | Dialect::Neo4j5 { .. } => [
+                "SHOW INDEXES YIELD name, type, entityType, labelsOrTypes, properties \
+                 WHERE labelsOrTypes IS NOT NULL RETURN *",
+                "SHOW CONSTRAINTS YIELD name, type, entityType, labelsOrTypes, properties \
+                 WHERE properties IS NOT NULL RETURN *",
+            ],
+            Dialect::Memgraph => ["SHOW INDEX INFO", "SHOW CONSTRAINT INFO"],
+        }
+    }
+
+    /// Schema command that makes `lookup` fast: a uniqueness constraint for
+    /// merging nodes on a single property, an index otherwise.
+    pub(crate) fn create_index(&self, lookup: &Lookup) -> String {
+        let name = &lookup.name;
+        let on = |var: &str| {
+            lookup
+                .properties
+                .iter()
+                .map(|p| format!("{var}.{p}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let unique = lookup.entity == Entity::Node
+            && lookup.purpose == "merge"
+            && lookup.properties.len() == 1;
+        match (self, lookup.entity) {
+            (Dialect::Memgraph, Entity::Node) if unique => {
+                format!(
+                    "CREATE CONSTRAINT ON (n:{name}) ASSERT {} IS UNIQUE",
+                    on("n")
+                )
+            }
+            (Dialect::Memgraph, Entity::Node) => {
+                format!("CREATE INDEX ON :{name}({})", lookup.properties.join(", "))
+            }
+            (Dialect::Memgraph, Entity::Relationship) => {
+                format!(
+                    "CREATE EDGE INDEX ON :{name}({})",
+                    lookup.properties.join(", ")
+                )
+            }
+            (Dialect::Neo4j4, Entity::Node) if unique => format!(
+                "CREATE CONSTRAINT IF NOT EXISTS ON (n:{name}) ASSERT {} IS UNIQUE",
+                on("n")
+            ),
+            (Dialect::Neo4j5 { .. }, Entity::Node) if unique => format!(
+                "CREATE CONSTRAINT IF NOT EXISTS FOR (n:{name}) REQUIRE {} IS UNIQUE",
+                on("n")
+            ),
+            (_, Entity::Node) => {
+                format!("CREATE INDEX IF NOT EXISTS FOR (n:{name}) ON ({})", on("n"))
+            }
+            (_, Entity::Relationship) => format!(
+                "CREATE INDEX IF NOT EXISTS FOR ()-[r:{name}]-() ON ({})",
+                on("r")
+            ),
+        }
+    }
+}
+
+/// Clause creating, or merging on `spec.keys`, the node of `row`.
+fn node_body(spec: &NodeSpec<'_>, tag_run: bool) -> String {
+    if spec.keys.is_empty() {
+        format!(
+            "CREATE (n:{}) SET n = row{}",
+            spec.label,
+            run_tag("n", tag_run, ", ")
+        )
+    } else {
+        format!(
+            "MERGE (n:{} {{{}}}){} SET n += row",
+            spec.label,
+            key_pattern(spec.keys, "row"),
+            run_tag("n", tag_run, " ON CREATE SET ")
+        )
+    }
+}
+
+/// Clauses matching both endpoints and creating, or merging on
+/// `spec.keys`, the relationship of `row`.
+fn relationship_body(spec: &RelationshipSpec<'_>, tag_run: bool) -> String {
+    let matches = format!(
+        "MATCH (a:{} {{{}: row.start}}) MATCH (b:{} {{{}: row.end}})",
+        spec.start_label, spec.start_id_prop, spec.end_label, spec.end_id_prop
+    );
+    if spec.keys.is_empty() {
+        format!(
+            "{matches} CREATE (a)-[r:{}]->(b) SET r = row.props{}",
+            spec.rel_type,
+            run_tag("r", tag_run, ", ")
+        )
+    } else {
+        format!(
+            "{matches} MERGE (a)-[r:{} {{{}}}]->(b){} SET r += row.props",
+            spec.rel_type,
+            key_pattern(spec.keys, "row.props"),
+            run_tag("r", tag_run, " ON CREATE SET ")
+        )
+    }
+}
+
+/// `prefix` followed by the assignment of the run id to `variable`, or
+/// nothing without `tag_run`. Only created entities are tagged, so a
+/// rollback never deletes what a merge matched.
+fn run_tag(variable: &str, tag_run: bool, prefix: &str) -> String {
+    if tag_run {
+        format!("{prefix}{variable}.{RUN_PROPERTY} = $run")
+    } else {
+        String::new()
+    }
+}
+
+/// Property map pattern such as `id: row.id, tenant: row.tenant`.
+fn key_pattern(keys: &[String], map: &str) -> String {
+    keys.iter()
+        .map(|k| format!("{k}: {map}.{k}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+async fn first_row(graph: &Graph, cypher: &str) -> Result<Option<neo4rs::Row>, neo4rs::Error> {
+    let mut stream = graph.execute(query(cypher)).await?;
+    let row = stream.next().await?;
+    // Drain the stream so the connection returns to the pool clean.
+    while stream.next().await?.is_some() {}
+    Ok(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_unwind_queries() {
+        let keys = ["id".to_string(), "tenant".to_string()];
+        let create = NodeSpec {
+            label: "Person",
+            keys: &[],
+        };
+        let merge = NodeSpec {
+            label: "Person",
+            keys: &keys,
+        };
+        let spec = RelationshipSpec {
+            rel_type: "KNOWS",
+            start_label: "Person",
+            start_id_col: "src",
+            start_id_prop: "id",
+            end_label: "City",
+            end_id_col: "dst",
+            end_id_prop: "code",
+            keys: &keys[..1],
+        };
+        for dialect in [Dialect::Neo4j4, Dialect::default(), Dialect::Memgraph] {
+            assert_eq!(
+                dialect.write_nodes(&create, false),
+                "UNWIND $rows AS row CREATE (n:Person) SET n = row"
+            );
+            assert_eq!(
+                dialect.write_nodes(&merge, false),
+                "UNWIND $rows AS row MERGE (n:Person {id: row.id, tenant: row.tenant}) \
+                 SET n += row"
+            );
+            assert_eq!(
+                dialect.write_relationships(&spec, false),
+                "UNWIND $rows AS row MATCH (a:Person {id: row.start}) \
+                 MATCH (b:City {code: row.end}) \
+                 MERGE (a)-[r:KNOWS {id: row.props.id}]->(b) SET r += row.props"
+            );
+        }
+    }
+
+    #[test]
+    fn tags_created_entities_with_the_run() {
+        let keys = ["id".to_string()];
+        let create = NodeSpec {
+            label: "Person",
+            keys: &[],
+        };
+        let merge = NodeSpec {
+            label: "Person",
+            keys: &keys,
+        };
+        let dialect = Dialect::default();
+        assert_eq!(
+            dialect.write_nodes(&create, true),
+            "UNWIND $rows AS row CREATE (n:Person) SET n = row, n._loadRun = $run"
+        );
+        assert_eq!(
+            dialect.write_nodes(&merge, true),
+            "UNWIND $rows AS row MERGE (n:Person {id: row.id}) \
+             ON CREATE SET n._loadRun = $run SET n += row"
+        );
+        let spec = RelationshipSpec {
+            rel_type: "KNOWS",
+            start_label: "Person",
+            start_id_col: "src",
+            start_id_prop: "id",
+            end_label: "Person",
+            end_id_col: "dst",
+            end_id_prop: "id",
+            keys: &[],
+        };
+        assert!(
+            dialect
+                .write_relationships(&spec, true)
+                .ends_with("CREATE (a)-[r:KNOWS]->(b) SET r = row.props, r._loadRun = $run")
+        );
+    }
+
+    #[test]
+    fn batches_on_the_server() {
+        let spec = NodeSpec {
+            label: "Person",
+            keys: &[],
+        };
+        assert_eq!(
+            Dialect::default()
+                .write_nodes_in_transactions(&spec, false, 500, 4)
+                .unwrap(),
+            "UNWIND $rows AS row CALL { WITH row CREATE (n:Person) SET n = row } \
+             IN 4 CONCURRENT TRANSACTIONS OF 500 ROWS \
+             ON ERROR CONTINUE REPORT STATUS AS status \
+             RETURN status.committed AS committed, status.errorMessage AS error, \
+             count(*) AS rows, count(DISTINCT status.transactionId) AS transactions"
+        );
+        assert!(
+            Dialect::default()
+                .write_nodes_in_transactions(&spec, false, 500, 1)
+                .unwrap()
+                .contains("} IN TRANSACTIONS OF 500 ROWS ON ERROR CONTINUE")
+        );
+        assert_eq!(
+            Dialect::Neo4j4
+                .write_nodes_in_transactions(&spec, false, 500, 1)
+                .unwrap(),
+            "UNWIND $rows AS row CALL { WITH row CREATE (n:Person) SET n = row } \
+             IN TRANSACTIONS OF 500 ROWS"
+        );
+        assert_eq!(
+            Dialect::Memgraph
+                .write_nodes_in_transactions(&spec, false, 500, 1)
+                .unwrap(),
+            "USING PERIODIC COMMIT 500 UNWIND $rows AS row CREATE (n:Person) SET n = row"
+        );
+        assert!(
+            Dialect::Neo4j4
+                .write_nodes_in_transactions(&spec, false, 500, 4)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn gates_server_batching_on_the_minor_version() {
+        let spec = NodeSpec {
+            label: "Person",
+            keys: &[],
+        };
+        let dialect = |version| Dialect::from_neo4j_version(version).unwrap();
+        let batch = |dialect: Dialect, concurrency| {
+            dialect.write_nodes_in_transactions(&spec, false, 500, concurrency)
+        };
+
+        // 5.6 has neither statuses nor concurrency.
+        let old = dialect("5.6.0");
+        assert_eq!(
+            batch(old, 1).unwrap(),
+            "UNWIND $rows AS row CALL { WITH row CREATE (n:Person) SET n = row } \
+             IN TRANSACTIONS OF 500 ROWS"
+        );
+        assert!(batch(old, 2).is_err());
+
+        // 5.20 reports statuses, but runs one transaction at a time.
+        let statuses = dialect("5.20.0");
+        assert!(
+            batch(statuses, 1)
+                .unwrap()
+                .contains("} IN TRANSACTIONS OF 500 ROWS ON ERROR CONTINUE REPORT STATUS")
+        );
+        let err = batch(statuses, 4).unwrap_err();
+        assert!(err.contains("5.21") && err.contains("neo4j-5.20"), "{err}");
+
+        // 5.21 has both.
+        assert!(
+            batch(dialect("5.21.2"), 4)
+                .unwrap()
+                .contains("} IN 4 CONCURRENT TRANSACTIONS OF 500 ROWS ON ERROR CONTINUE")
+        );
+    }
+
+    #[test]
+    fn maps_versions_to_dialects() {
+        assert_eq!(
+            Dialect::from_neo4j_version("4.4.12").unwrap(),
+            Dialect::Neo4j4
+        );
+        assert_eq!(
+            Dialect::from_neo4j_version("5.20.0").unwrap(),
+            Dialect::Neo4j5 { minor: Some(20) }
+        );
+        assert_eq!(
+            Dialect::from_neo4j_version("2025.01.0").unwrap(),
+            Dialect::default()
+        );
+        assert!(Dialect::from_neo4j_version("3.5.35").is_err());
+        assert!(Dialect::from_neo4j_version("dev").is_err());
+        assert_eq!("memgraph".parse::<Dialect>().unwrap(), Dialect::Memgraph);
+        assert_eq!("neo4j-5".parse::<Dialect>().unwrap(), Dialect::default());
+        assert_eq!(
+            "neo4j-5.6".parse::<Dialect>().unwrap(),
+            Dialect::Neo4j5 { minor: Some(6) }
+        );
+        assert_eq!(Dialect::Neo4j4.to_string(), "neo4j-4");
+        assert_eq!(
+            Dialect::Neo4j5 { minor: Some(20) }.to_string(),
+            "neo4j-5.20"
+        );
+        assert!("neo4j".parse::<Dialect>().is_err());
+        assert!("neo4j-5.x".parse::<Dialect>().is_err());
+    }
+
+    #[test]
+    fn writes_schema_commands() {
+        let lookup = |entity, name: &str, properties: &[&str], purpose| Lookup {
+            entity,
+            name: name.into(),
+            properties: properties.iter().map(|p| p.to_string()).collect(),
+            purpose,
+        };
+        let merge = lookup(Entity::Node, "Person", &["id"], "merge");
+        assert_eq!(
+            Dialect::Neo4j4.create_index(&merge),
+            "CREATE CONSTRAINT IF NOT EXISTS ON (n:Person) ASSERT n.id IS UNIQUE"
+        );
+        assert_eq!(
+            Dialect::default().create_index(&merge),
+            "CREATE CONSTRAINT IF NOT EXISTS FOR (n:Person) REQUIRE n.id IS UNIQUE"
+        );
+        assert_eq!(
+            Dialect::Memgraph.create_index(&merge),
+            "CREATE CONSTRAINT ON (n:Person) ASSERT n.id IS UNIQUE"
+        );
+
+        let matched = lookup(Entity::Node, "City", &["code"], "match end nodes");
+        assert_eq!(
+            Dialect::default().create_index(&matched),
+            "CREATE INDEX IF NOT EXISTS FOR (n:City) ON (n.code)"
+        );
+        assert_eq!(
+            Dialect::Memgraph.create_index(&matched),
+            "CREATE INDEX ON :City(code)"
+        );
+
+        let rel = lookup(Entity::Relationship, "KNOWS", &["since"], "merge");
+        assert_eq!(
+            Dialect::Neo4j4.create_index(&rel),
+            "CREATE INDEX IF NOT EXISTS FOR ()-[r:KNOWS]-() ON (r.since)"
+        );
+        assert_eq!(
+            Dialect::Memgraph.create_index(&rel),
+            "CREATE EDGE INDEX ON :KNOWS(since)"
+        );
+        assert!(Dialect::Memgraph.routing_query().is_err());
+    }
+}
@@ -0,0 +1,468 @@
+//! Checks that a connection and its target database are ready for a load.
+//!
+//! [`Doctor`] connects with a [`Neo4jConfig`], reports the server version and
+//! the database in use, proves that the loads can write by running their
+//! writes in transactions that are rolled back, and lists the indexes and
+//! constraints backing the lookups each load makes:
+//!
+//! ```no_run
+//! # async fn example(cfg: neo4j_parallel_rust_loader::Neo4jConfig) {
+//! use neo4j_parallel_rust_loader::{Doctor, NodeLoad};
+//!
+//! let report = Doctor::new()
+//!     .node_load(&NodeLoad::new("people.parquet", "Person").key("id"))
+//!     .run(&cfg)
+//!     .await;
+//! print!("{report}");
+//! assert!(report.is_healthy());
+//! # }
+//! ```
+
+use neo4rs::{Graph, Row, query};
+
+use crate::config::Neo4jConfig;
+use crate::dialect::Dialect;
+use crate::job::{NodeLoad, RelationshipLoad};
+use crate::neo4j::connect;
+
+/// Outcome of a single check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    /// Something that will not stop a load but may slow it down.
+    Warning,
+    /// Something that will make a load fail.
+    Failed,
+}
+
+/// A named check and what it found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+}
+
+/// The checks run by [`Doctor::run`], in order.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Whether no check failed. Warnings do not count as failures.
+    pub fn is_healthy(&self) -> bool {
+        self.checks.iter().all(|c| c.status != Status::Failed)
+    }
+
+    fn push(&mut self, name: impl Into<String>, status: Status, detail: impl Into<String>) {
+        self.checks.push(Check {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+        });
+    }
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for check in &self.checks {
+            let status = match check.status {
+                Status::Ok => "ok",
+                Status::Warning => "warning",
+                Status::Failed => "FAILED",
+            };
+            writeln!(f, "{status:<8}{}: {}", check.name, check.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether a lookup matches nodes or relationships.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Entity {
+    Node,
+    Relationship,
+}
+
+/// Properties a load looks entities up by, which should be indexed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Lookup {
+    pub(crate) entity: Entity,
+    pub(crate) name: String,
+    pub(crate) properties: Vec<String>,
+    /// What the lookup is for, e.g. `merge` or `match start nodes`.
+    pub(crate) purpose: &'static str,
+}
+
+impl Lookup {
+    fn pattern(&self) -> String {
+        let properties = self.properties.join(", ");
+        match self.entity {
+            Entity::Node => format!(":{}({properties})", self.name),
+            Entity::Relationship => format!("[:{}]({properties})", self.name),
+        }
+    }
+}
+
+/// What [`Doctor`] checks for one load.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Target {
+    /// Short description of the load, such as `nodes :Person`.
+    pub(crate) description: String,
+    /// Cypher writing what the load writes, run and rolled back.
+    pub(crate) probe: String,
+    pub(crate) lookups: Vec<Lookup>,
+}
+
+/// An index or constraint as listed by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Schema {
+    name: String,
+    kind: String,
+    entity: Entity,
+    labels: Vec<String>,
+    properties: Vec<String>,
+}
+
+impl Schema {
+    fn from_row(row: &Row) -> Result<Self, neo4rs::DeError> {
+        Ok(Self {
+            name: row.get("name")?,
+            kind: row.get("type")?,
+            entity: match row.get::<String>("entityType")?.as_str() {
+                "RELATIONSHIP" => Entity::Relationship,
+                _ => Entity::Node,
+            },
+            labels: row.get("labelsOrTypes")?,
+            properties: row.get("properties")?,
+        })
+    }
+
+    /// A row of Memgraph's `SHOW INDEX INFO` or `SHOW CONSTRAINT INFO`.
+    /// Memgraph does not name them, so the pattern serves as the name.
+    fn from_memgraph_row(row: &Row) -> Result<Self, neo4rs::DeError> {
+        let (kind, column) = match row.get::<String>("index type") {
+            Ok(kind) => (kind, "property"),
+            Err(_) => (
+                format!("{} constraint", row.get::<String>("constraint type")?),
+                "properties",
+            ),
+        };
+        // A single property is listed as a string, several as a list.
+        let properties = row
+            .get::<Vec<String>>(column)
+            .or_else(|_| row.get::<String>(column).map(|p| vec![p]))
+            .unwrap_or_default();
+        let label: String = row.get("label")?;
+        Ok(Self {
+            name: format!(":{label}({})", properties.join(", ")),
+            entity: if kind.starts_with("edge") {
+                Entity::Relationship
+            } else {
+                Entity::Node
+            },
+            kind: if kind.ends_with("constraint") {
+                kind
+            } else {
+                "index".to_string()
+            },
+            labels: vec![label],
+            properties,
+        })
+    }
+
+    /// Whether an equality lookup on `lookup` can use this index, which is
+    /// the case when the index covers only properties the lookup sets.
+    fn serves(&self, lookup: &Lookup) -> bool {
+        self.entity == lookup.entity
+            && self.labels.contains(&lookup.name)
+            && !self.properties.is_empty()
+            && self
+                .properties
+                .iter()
+                .all(|p| lookup.properties.contains(p))
+    }
+}
+
+/// Diagnoses a connection and the loads that will run over it.
+#[derive(Debug, Clone, Default)]
+pub struct Doctor {
+    targets: Vec<Target>,
+}
+
+impl Doctor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also check the privileges and indexes `load` needs.
+    pub fn node_load(mut self, load: &NodeLoad) -> Self {
+        self.targets.push(load.target());
+        self
+    }
+
+    /// Also check the privileges and indexes `load` needs.
+    pub fn relationship_load(mut self, load: &RelationshipLoad) -> Self {
+        self.targets.push(load.target());
+        self
+    }
+
+    /// Run every check. Checks that need a connection are skipped when
+    /// connecting fails.
+    pub async fn run(&self, config: &Neo4jConfig) -> Report {
+        let mut report = Report::default();
+        let graph = match connect(config).await {
+            Ok(graph) => {
+                report.push(
+                    "connectivity",
+                    Status::Ok,
+                    format!("connected to {}", config.uri),
+                );
+                graph
+            }
+            Err(e) => {
+                report.push("connectivity", Status::Failed, e.to_string());
+                return report;
+            }
+        };
+
+        let dialect = match config.dialect {
+            Some(dialect) => dialect,
+            None => Dialect::detect(&graph).await.unwrap_or_else(|e| {
+                let assumed = Dialect::default();
+                report.push(
+                    "dialect",
+                    Status::Warning,
+                    format!("{e}; assuming {assumed}"),
+                );
+                assumed
+            }),
+        };
+        match server(&graph, dialect).await {
+            Ok(server) => report.push(
+                "server",
+                Status::Ok,
+                format!("{server}, Cypher dialect {dialect}"),
+            ),
+            Err(e) => report.push(
+                "server",
+                Status::Warning,
+                format!("cannot read version: {e}"),
+            ),
+        }
+        if let Some(cypher) = dialect.database_query() {
+            match rows(&graph, cypher).await {
+                Ok(rows) => {
+                    let name = rows
+                        .first()
+                        .and_then(|r| r.get::<String>("name").ok())
+                        .unwrap_or_default();
+                    report.push("database", Status::Ok, format!("`{name}` is online"));
+                }
+                Err(e) => report.push("database", Status::Failed, e.to_string()),
+            }
+        }
+        match rows(&graph, dialect.current_user_query()).await {
+            Ok(rows) => {
+                let detail = rows
+                    .first()
+                    .and_then(|r| r.get::<String>("user").ok())
+                    .map(|user| {
+                        let roles: Vec<String> = rows[0].get("roles").unwrap_or_default();
+                        if roles.is_empty() {
+                            format!("`{user}`")
+                        } else {
+                            format!("`{user}` with roles {}", roles.join(", "))
+                        }
+                    })
+                    .unwrap_or_else(|| "no current user".to_string());
+                report.push("user", Status::Ok, detail);
+            }
+            Err(e) => report.push("user", Status::Warning, format!("cannot list roles: {e}")),
+        }
+
+        if self.targets.is_empty() {
+            let probe = "CREATE (n:LoaderDoctorProbe) DELETE n";
+            match rolled_back(&graph, probe).await {
+                Ok(()) => report.push("write access", Status::Ok, "can write"),
+                Err(e) => report.push("write access", Status::Failed, e.to_string()),
+            }
+            return report;
+        }
+
+        let schema = match schema(&graph, dialect).await {
+            Ok(schema) => Some(schema),
+            Err(e) => {
+                report.push(
+                    "schema",
+                    Status::Warning,
+                    format!("cannot list indexes: {e}"),
+                );
+                None
+            }
+        };
+        for target in &self.targets {
+            let name = format!("write {}", target.description);
+            match rolled_back(&graph, &target.probe).await {
+                Ok(()) => report.push(name, Status::Ok, "allowed"),
+                Err(e) => report.push(name, Status::Failed, e.to_string()),
+            }
+            let Some(schema) = &schema else { continue };
+            for lookup in &target.lookups {
+                let (status, detail) = index_check(lookup, schema, dialect);
+                report.push(format!("index {}", lookup.pattern()), status, detail);
+            }
+        }
+        report
+    }
+}
+
+/// The indexes and constraints serving `lookup`, or a warning with the
+/// command creating one if none does.
+fn index_check(lookup: &Lookup, schema: &[Schema], dialect: Dialect) -> (Status, String) {
+    let serving: Vec<String> = schema
+        .iter()
+        .filter(|s| s.serves(lookup))
+        .map(|s| format!("{} `{}`", s.kind.to_lowercase().replace('_', " "), s.name))
+        .collect();
+    if serving.is_empty() {
+        let scanned = match lookup.entity {
+            Entity::Node => format!("every :{} node", lookup.name),
+            Entity::Relationship => format!("every :{} relationship", lookup.name),
+        };
+        (
+            Status::Warning,
+            format!(
+                "nothing indexes it, so each {} scans {scanned}; create one with `{}`",
+                lookup.purpose,
+                dialect.create_index(lookup)
+            ),
+        )
+    } else {
+        (
+            Status::Ok,
+            format!("{} uses {}", lookup.purpose, serving.join(", ")),
+        )
+    }
+}
+
+async fn server(
+    graph: &Graph,
+    dialect: Dialect,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let rows = rows(graph, dialect.server_query()).await?;
+    let row = rows.first().ok_or("no version reported")?;
+    let version: String = row.get("version")?;
+    if dialect == Dialect::Memgraph {
+        return Ok(format!("Memgraph {version}"));
+    }
+    let name: String = row.get("name")?;
+    let edition: String = row.get("edition")?;
+    Ok(format!("{name} {version} ({edition} edition)"))
+}
+
+async fn schema(
+    graph: &Graph,
+    dialect: Dialect,
+) -> Result<Vec<Schema>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut schema = Vec::new();
+    for cypher in dialect.schema_queries() {
+        for row in rows(graph, cypher).await? {
+            schema.push(match dialect {
+                Dialect::Memgraph => Schema::from_memgraph_row(&row)?,
+                Dialect::Neo4j4 | Dialect::Neo4j5 { .. } => Schema::from_row(&row)?,
+            });
+        }
+    }
+    Ok(schema)
+}
+
+async fn rows(graph: &Graph, cypher: &str) -> Result<Vec<Row>, neo4rs::Error> {
+    let mut stream = graph.execute(query(cypher)).await?;
+    let mut rows = Vec::new();
+    while let Some(row) = stream.next().await? {
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Run `cypher` in a transaction and roll it back, leaving no trace.
+async fn rolled_back(graph: &Graph, cypher: &str) -> Result<(), neo4rs::Error> {
+    let mut txn = graph.start_txn().await?;
+    let result = txn.run(query(cypher)).await;
+    let rollback = txn.rollback().await;
+    result?;
+    rollback
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema(name: &str, entity: Entity, label: &str, properties: &[&str]) -> Schema {
+        Schema {
+            name: name.into(),
+            kind: "RANGE".into(),
+            entity,
+            labels: vec![label.into()],
+            properties: properties.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn finds_indexes_serving_lookups() {
+        let lookup = Lookup {
+            entity: Entity::Node,
+            name: "Person".into(),
+            properties: vec!["tenant".into(), "id".into()],
+            purpose: "merge",
+        };
+        let schema = [
+            schema("person_id", Entity::Node, "Person", &["id"]),
+            schema("person_name", Entity::Node, "Person", &["id", "name"]),
+            schema("city_id", Entity::Node, "City", &["id"]),
+            schema("knows_id", Entity::Relationship, "Person", &["id"]),
+        ];
+        let (status, detail) = index_check(&lookup, &schema, Dialect::default());
+        assert_eq!(status, Status::Ok);
+        assert_eq!(detail, "merge uses range `person_id`");
+
+        let (status, detail) = index_check(&lookup, &schema[2..], Dialect::Memgraph);
+        assert_eq!(status, Status::Warning);
+        assert!(detail.contains("every :Person node"), "{detail}");
+        assert!(
+            detail.ends_with("create one with `CREATE INDEX ON :Person(tenant, id)`"),
+            "{detail}"
+        );
+    }
+
+    #[test]
+    fn describes_load_targets() {
+        let nodes = NodeLoad::new("p.csv", "Person").key("id").target();
+        assert_eq!(nodes.description, "nodes :Person");
+        assert_eq!(nodes.lookups.len(), 1);
+        assert_eq!(nodes.lookups[0].pattern(), ":Person(id)");
+        assert!(NodeLoad::new("p.csv", "Person").target().lookups.is_empty());
+
+        let rels = RelationshipLoad::new("k.csv", "KNOWS")
+            .start("Person", "src")
+            .start_property("id")
+            .end("City", "dst")
+            .key("since")
+            .target();
+        assert_eq!(rels.description, "relationships [:KNOWS]");
+        let patterns: Vec<_> = rels.lookups.iter().map(Lookup::pattern).collect();
+        assert_eq!(patterns, [":Person(id)", ":City(dst)", "[:KNOWS](since)"]);
+        assert!(rels.probe.contains("(a:Person)-[r:KNOWS]->(b:City)"));
+
+        let mut report = Report::default();
+        report.push("database", Status::Ok, "`neo4j` is online");
+        report.push("write nodes :Person", Status::Failed, "read only");
+        assert!(!report.is_healthy());
+        assert_eq!(
+            report.to_string(),
+            "ok      database: `neo4j` is online\nFAILED  write nodes :Person: read only\n"
+        );
+    }
+}
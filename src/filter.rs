@@ -0,0 +1,538 @@
+//! Row filters with Parquet predicate pushdown.
+//!
+//! A [`Filter`] is evaluated in three stages when reading Parquet: row groups
+//! whose statistics rule out a match are skipped entirely, pages are skipped
+//! using the page index when the file has one, and the remaining rows are
+//! filtered with Arrow compute kernels. Other sources only use the last stage.
+
+use std::sync::Arc;
+
+use arrow::array::{
+    Array, ArrayRef, BooleanArray, Datum, RecordBatch, Scalar, StringArray, UInt64Array,
+};
+use arrow::compute::kernels::boolean::{and_kleene, not, or_kleene};
+use arrow::compute::kernels::cmp;
+use arrow::compute::{cast, filter_record_batch, is_not_null, is_null};
+use arrow::datatypes::{DataType, Schema};
+use parquet::arrow::arrow_reader::statistics::StatisticsConverter;
+use parquet::arrow::arrow_reader::{RowSelection, RowSelector};
+use parquet::file::metadata::ParquetMetaData;
+
+/// A literal compared against a column. It is cast to the column type before
+/// comparison, so a string such as `"2024-01-01T00:00:00"` can be compared
+/// with a timestamp column.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl From<bool> for FilterValue {
+    fn from(v: bool) -> Self {
+        FilterValue::Bool(v)
+    }
+}
+
+impl From<i64> for FilterValue {
+    fn from(v: i64) -> Self {
+        FilterValue::Int(v)
+    }
+}
+
+impl From<f64> for FilterValue {
+    fn from(v: f64) -> Self {
+        FilterValue::Float(v)
+    }
+}
+
+impl From<&str> for FilterValue {
+    fn from(v: &str) -> Self {
+        FilterValue::String(v.to_owned())
+    }
+}
+
+impl From<String> for FilterValue {
+    fn from(v: String) -> Self {
+        FilterValue::String(v)
+    }
+}
+
+impl FilterValue {
+    fn to_array(&self, data_type: &DataType) -> Result<ArrayRef, arrow::error::ArrowError> {
+        let literal: ArrayRef = match self {
+            FilterValue::Bool(v) => Arc::new(BooleanArray::from(vec![*v])),
+            FilterValue::Int(v) => Arc::new(arrow::array::Int64Array::from(vec![*v])),
+            FilterValue::Float(v) => Arc::new(arrow::array::Float64Array::from(vec![*v])),
+            FilterValue::String(v) => Arc::new(StringArray::from(vec![v.as_str()])),
+        };
+        cast(&literal, data_type)
+    }
+}
+
+/// Comparison operators supported by [`Filter::Compare`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+/// A predicate over the columns of a source.
+///
+/// Comparisons follow SQL semantics: a null value never matches.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Compare {
+        column: String,
+        op: CompareOp,
+        value: FilterValue,
+    },
+    In {
+        column: String,
+        values: Vec<FilterValue>,
+    },
+    IsNull(String),
+    IsNotNull(String),
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    fn compare(column: &str, op: CompareOp, value: impl Into<FilterValue>) -> Self {
+        Filter::Compare {
+            column: column.to_owned(),
+            op,
+            value: value.into(),
+        }
+    }
+
+    pub fn eq(column: &str, value: impl Into<FilterValue>) -> Self {
+        Self::compare(column, CompareOp::Eq, value)
+    }
+
+    pub fn not_eq(column: &str, value: impl Into<FilterValue>) -> Self {
+        Self::compare(column, CompareOp::NotEq, value)
+    }
+
+    pub fn lt(column: &str, value: impl Into<FilterValue>) -> Self {
+        Self::compare(column, CompareOp::Lt, value)
+    }
+
+    pub fn lt_eq(column: &str, value: impl Into<FilterValue>) -> Self {
+        Self::compare(column, CompareOp::LtEq, value)
+    }
+
+    pub fn gt(column: &str, value: impl Into<FilterValue>) -> Self {
+        Self::compare(column, CompareOp::Gt, value)
+    }
+
+    pub fn gt_eq(column: &str, value: impl Into<FilterValue>) -> Self {
+        Self::compare(column, CompareOp::GtEq, value)
+    }
+
+    pub fn is_in<V: Into<FilterValue>>(column: &str, values: impl IntoIterator<Item = V>) -> Self {
+        Filter::In {
+            column: column.to_owned(),
+            values: values.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn is_null(column: &str) -> Self {
+        Filter::IsNull(column.to_owned())
+    }
+
+    pub fn is_not_null(column: &str) -> Self {
+        Filter::IsNotNull(column.to_owned())
+    }
+
+    pub fn and(self, other: Filter) -> Self {
+        Filter::And(vec![self, other])
+    }
+
+    pub fn or(self, other: Filter) -> Self {
+        Filter::Or(vec![self, other])
+    }
+
+    /// Evaluate the filter against every row of `batch`.
+    pub fn evaluate(&self, batch: &RecordBatch) -> Result<BooleanArray, arrow::error::ArrowError> {
+        let column = |name: &str| {
+            batch.column_by_name(name).cloned().ok_or_else(|| {
+                arrow::error::ArrowError::SchemaError(format!("unknown filter column `{name}`"))
+            })
+        };
+        match self {
+            Filter::Compare {
+                column: c,
+                op,
+                value,
+            } => {
+                let array = column(c)?;
+                let literal = value.to_array(array.data_type())?;
+                compare(&array, *op, &Scalar::new(literal))
+            }
+            Filter::In { column: c, values } => {
+                let array = column(c)?;
+                let mut result = BooleanArray::from(vec![false; array.len()]);
+                for value in values {
+                    let literal = value.to_array(array.data_type())?;
+                    result = or_kleene(&result, &cmp::eq(&array, &Scalar::new(literal))?)?;
+                }
+                Ok(result)
+            }
+            Filter::IsNull(c) => is_null(&column(c)?),
+            Filter::IsNotNull(c) => is_not_null(&column(c)?),
+            Filter::And(filters) => {
+                let mut result = BooleanArray::from(vec![true; batch.num_rows()]);
+                for filter in filters {
+                    result = and_kleene(&result, &filter.evaluate(batch)?)?;
+                }
+                Ok(result)
+            }
+            Filter::Or(filters) => {
+                let mut result = BooleanArray::from(vec![false; batch.num_rows()]);
+                for filter in filters {
+                    result = or_kleene(&result, &filter.evaluate(batch)?)?;
+                }
+                Ok(result)
+            }
+            Filter::Not(filter) => not(&filter.evaluate(batch)?),
+        }
+    }
+
+    /// Keep only the rows of `batch` matching the filter.
+    pub(crate) fn apply(
+        &self,
+        batch: &RecordBatch,
+    ) -> Result<RecordBatch, arrow::error::ArrowError> {
+        filter_record_batch(batch, &self.evaluate(batch)?)
+    }
+
+    /// Indices of the row groups whose statistics do not rule out a match.
+    pub(crate) fn prune_row_groups(
+        &self,
+        schema: &Schema,
+        metadata: &ParquetMetaData,
+    ) -> Vec<usize> {
+        let row_groups = metadata.row_groups();
+        let stats = |column: &str| -> Option<Stats> {
+            let converter = StatisticsConverter::try_new(
+                column,
+                schema,
+                metadata.file_metadata().schema_descr(),
+            )
+            .ok()?;
+            Some(Stats {
+                mins: converter.row_group_mins(row_groups).ok()?,
+                maxes: converter.row_group_maxes(row_groups).ok()?,
+                null_counts: converter.row_group_null_counts(row_groups).ok()?,
+                row_counts: converter.row_group_row_counts(row_groups).ok()?,
+            })
+        };
+        let keep = self.may_match(row_groups.len(), &stats);
+        (0..row_groups.len()).filter(|i| keep[*i]).collect()
+    }
+
+    /// Rows of `row_group` whose page statistics do not rule out a match, or
+    /// `None` when the file has no page index.
+    pub(crate) fn page_selection(
+        &self,
+        schema: &Schema,
+        metadata: &ParquetMetaData,
+        row_group: usize,
+    ) -> Option<RowSelection> {
+        let column_index = metadata.column_index()?;
+        let offset_index = metadata.offset_index()?;
+        let num_rows = metadata.row_group(row_group).num_rows() as usize;
+        Some(self.select_pages(num_rows, &|column: &str| {
+            let converter = StatisticsConverter::try_new(
+                column,
+                schema,
+                metadata.file_metadata().schema_descr(),
+            )
+            .ok()?;
+            let groups = [row_group];
+            let row_counts = converter
+                .data_page_row_counts(offset_index, metadata.row_groups(), &groups)
+                .ok()??;
+            let stats = Stats {
+                mins: converter
+                    .data_page_mins(column_index, offset_index, &groups)
+                    .ok()?,
+                maxes: converter
+                    .data_page_maxes(column_index, offset_index, &groups)
+                    .ok()?,
+                null_counts: converter
+                    .data_page_null_counts(column_index, offset_index, &groups)
+                    .ok()?,
+                row_counts: Some(row_counts),
+            };
+            let pages = stats.mins.len();
+            Some((stats, pages))
+        }))
+    }
+
+    /// Evaluate the filter over entries that all share the same layout, such
+    /// as the row groups of a file. Unknown statistics never rule out a match.
+    fn may_match(&self, len: usize, stats: &dyn Fn(&str) -> Option<Stats>) -> Vec<bool> {
+        match self {
+            Filter::And(filters) => filters.iter().fold(vec![true; len], |acc, f| {
+                let keep = f.may_match(len, stats);
+                acc.iter().zip(keep).map(|(a, b)| *a && b).collect()
+            }),
+            Filter::Or(filters) => filters.iter().fold(vec![false; len], |acc, f| {
+                let keep = f.may_match(len, stats);
+                acc.iter().zip(keep).map(|(a, b)| *a || b).collect()
+            }),
+            Filter::Not(_) => vec![true; len],
+            leaf => {
+                let column = leaf.column().unwrap_or_default();
+                match stats(column).and_then(|s| leaf.leaf_may_match(&s)) {
+                    Some(keep) if keep.len() == len => keep,
+                    _ => vec![true; len],
+                }
+            }
+        }
+    }
+
+    /// Build a row selection from per-column page statistics. Pages of
+    /// different columns do not line up, so each leaf is turned into a row
+    /// selection before the selections are combined.
+    fn select_pages(
+        &self,
+        num_rows: usize,
+        stats: &dyn Fn(&str) -> Option<(Stats, usize)>,
+    ) -> RowSelection {
+        let all = || RowSelection::from(vec![RowSelector::select(num_rows)]);
+        match self {
+            Filter::And(filters) => filters.iter().fold(all(), |acc, f| {
+                acc.intersection(&f.select_pages(num_rows, stats))
+            }),
+            Filter::Or(filters) => filters.iter().fold(
+                RowSelection::from(vec![RowSelector::skip(num_rows)]),
+                |acc, f| acc.union(&f.select_pages(num_rows, stats)),
+            ),
+            Filter::Not(_) => all(),
+            leaf => {
+                let column = leaf.column().unwrap_or_default();
+                let Some((page_stats, pages)) = stats(column) else {
+                    return all();
+                };
+                let (Some(keep), Some(row_counts)) = (
+                    leaf.leaf_may_match(&page_stats),
+                    page_stats.row_counts.as_ref(),
+                ) else {
+                    return all();
+                };
+                if keep.len() != pages || row_counts.len() != pages {
+                    return all();
+                }
+                let selection: RowSelection = keep
+                    .iter()
+                    .zip(row_counts.values().iter())
+                    .map(|(keep, rows)| {
+                        if *keep {
+                            RowSelector::select(*rows as usize)
+                        } else {
+                            RowSelector::skip(*rows as usize)
+                        }
+                    })
+                    .collect();
+                if selection.row_count() + selection.skipped_row_count() == num_rows {
+                    selection
+                } else {
+                    all()
+                }
+            }
+        }
+    }
+
+    fn column(&self) -> Option<&str> {
+        match self {
+            Filter::Compare { column, .. } | Filter::In { column, .. } => Some(column),
+            Filter::IsNull(column) | Filter::IsNotNull(column) => Some(column),
+            _ => None,
+        }
+    }
+
+    /// Whether each statistics entry may contain a row matching this leaf.
+    fn leaf_may_match(&self, stats: &Stats) -> Option<Vec<bool>> {
+        let result = match self {
+            Filter::Compare { op, value, .. } => stats.compare(*op, value).ok()?,
+            Filter::In { values, .. } => {
+                let mut result = BooleanArray::from(vec![false; stats.mins.len()]);
+                for value in values {
+                    result = or_kleene(&result, &stats.compare(CompareOp::Eq, value).ok()?).ok()?;
+                }
+                result
+            }
+            Filter::IsNull(_) => stats.null_counts.iter().map(|n| n.map(|n| n > 0)).collect(),
+            Filter::IsNotNull(_) => match &stats.row_counts {
+                Some(rows) => stats
+                    .null_counts
+                    .iter()
+                    .zip(rows.iter())
+                    .map(|(n, r)| n.zip(r).map(|(n, r)| n < r))
+                    .collect(),
+                None => return None,
+            },
+            _ => return None,
+        };
+        Some(result.iter().map(|v| v.unwrap_or(true)).collect())
+    }
+}
+
+impl std::ops::Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Filter {
+        Filter::Not(Box::new(self))
+    }
+}
+
+/// Minimum, maximum and null count statistics of one column.
+struct Stats {
+    mins: ArrayRef,
+    maxes: ArrayRef,
+    null_counts: UInt64Array,
+    row_counts: Option<UInt64Array>,
+}
+
+impl Stats {
+    /// Whether the `[min, max]` range of each entry may contain a value for
+    /// which `value op column` holds.
+    fn compare(
+        &self,
+        op: CompareOp,
+        value: &FilterValue,
+    ) -> Result<BooleanArray, arrow::error::ArrowError> {
+        let literal = Scalar::new(value.to_array(self.mins.data_type())?);
+        match op {
+            CompareOp::Eq => and_kleene(
+                &cmp::lt_eq(&self.mins, &literal)?,
+                &cmp::gt_eq(&self.maxes, &literal)?,
+            ),
+            CompareOp::NotEq => or_kleene(
+                &cmp::neq(&self.mins, &literal)?,
+                &cmp::neq(&self.maxes, &literal)?,
+            ),
+            CompareOp::Lt => cmp::lt(&self.mins, &literal),
+            CompareOp::LtEq => cmp::lt_eq(&self.mins, &literal),
+            CompareOp::Gt => cmp::gt(&self.maxes, &literal),
+            CompareOp::GtEq => cmp::gt_eq(&self.maxes, &literal),
+        }
+    }
+}
+
+fn compare(
+    array: &dyn Datum,
+    op: CompareOp,
+    literal: &dyn Datum,
+) -> Result<BooleanArray, arrow::error::ArrowError> {
+    match op {
+        CompareOp::Eq => cmp::eq(array, literal),
+        CompareOp::NotEq => cmp::neq(array, literal),
+        CompareOp::Lt => cmp::lt(array, literal),
+        CompareOp::LtEq => cmp::lt_eq(array, literal),
+        CompareOp::Gt => cmp::gt(array, literal),
+        CompareOp::GtEq => cmp::gt_eq(array, literal),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int64Array, TimestampMillisecondArray};
+    use arrow::datatypes::{Field, TimeUnit};
+
+    fn batch() -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int64, true),
+            Field::new("name", DataType::Utf8, true),
+            Field::new("at", DataType::Timestamp(TimeUnit::Millisecond, None), true),
+        ]));
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int64Array::from(vec![Some(1), Some(2), None, Some(4)])),
+                Arc::new(StringArray::from(vec![
+                    Some("a"),
+                    None,
+                    Some("c"),
+                    Some("d"),
+                ])),
+                Arc::new(TimestampMillisecondArray::from(vec![
+                    0,
+                    86_400_000,
+                    2 * 86_400_000,
+                    3 * 86_400_000,
+                ])),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn rows(filter: &Filter) -> Vec<i64> {
+        let filtered = filter.apply(&batch()).unwrap();
+        let ids = filtered
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        ids.iter().map(|v| v.unwrap_or(-1)).collect()
+    }
+
+    #[test]
+    fn evaluates_row_predicates() {
+        assert_eq!(rows(&Filter::gt_eq("id", 2)), vec![2, 4]);
+        assert_eq!(rows(&!Filter::eq("id", 2)), vec![1, 4]);
+        assert_eq!(rows(&Filter::is_in("name", ["a", "d"])), vec![1, 4]);
+        assert_eq!(rows(&Filter::is_null("id")), vec![-1]);
+        assert_eq!(
+            rows(&Filter::is_not_null("name").and(Filter::lt("id", 4))),
+            vec![1]
+        );
+        assert_eq!(rows(&Filter::gt("at", "1970-01-02T12:00:00")), vec![-1, 4]);
+    }
+
+    #[test]
+    fn rejects_unknown_columns() {
+        assert!(Filter::eq("missing", 1).evaluate(&batch()).is_err());
+    }
+
+    #[test]
+    fn statistics_keep_unknown_entries() {
+        let stats = Stats {
+            mins: Arc::new(Int64Array::from(vec![Some(0), Some(10), None])),
+            maxes: Arc::new(Int64Array::from(vec![Some(9), Some(19), None])),
+            null_counts: UInt64Array::from(vec![0, 2, 0]),
+            row_counts: Some(UInt64Array::from(vec![10, 2, 10])),
+        };
+        let keep = |f: Filter| f.leaf_may_match(&stats).unwrap();
+        assert_eq!(keep(Filter::eq("id", 12)), vec![false, true, true]);
+        assert_eq!(keep(Filter::lt("id", 5)), vec![true, false, true]);
+        assert_eq!(keep(Filter::is_null("id")), vec![false, true, false]);
+        assert_eq!(keep(Filter::is_not_null("id")), vec![true, false, true]);
+    }
+
+    #[test]
+    fn selects_pages_per_column() {
+        let filter = Filter::eq("id", 5);
+        let selection = filter.select_pages(6, &|_| {
+            let stats = Stats {
+                mins: Arc::new(Int64Array::from(vec![0, 4, 8])),
+                maxes: Arc::new(Int64Array::from(vec![3, 7, 11])),
+                null_counts: UInt64Array::from(vec![0, 0, 0]),
+                row_counts: Some(UInt64Array::from(vec![2, 2, 2])),
+            };
+            Some((stats, 3))
+        });
+        assert_eq!(selection.row_count(), 2);
+        assert_eq!(selection.skipped_row_count(), 4);
+    }
+}
@@ -0,0 +1,117 @@
+//! Cancelling, pausing and resuming a running load.
+//!
+//! Pass a [`LoadHandle`] to a load with `handle` and keep a clone to control
+//! it from another task. Cancelling stops the load from sending new batches;
+//! transactions already sent are allowed to commit, and the load returns a
+//! [`LoadSummary`](crate::LoadSummary) with `cancelled` set that counts
+//! exactly the committed rows. Pausing holds new batches back until
+//! [`resume`](LoadHandle::resume) while in-flight ones finish.
+
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Running,
+    Paused,
+    Cancelled,
+}
+
+/// Controls a running load; clones control the same load.
+#[derive(Debug, Clone)]
+pub struct LoadHandle {
+    state: Arc<watch::Sender<State>>,
+}
+
+impl Default for LoadHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LoadHandle {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(watch::Sender::new(State::Running)),
+        }
+    }
+
+    /// Stop sending batches and let in-flight transactions commit. A
+    /// cancelled handle cannot be resumed.
+    pub fn cancel(&self) {
+        self.state.send_replace(State::Cancelled);
+    }
+
+    /// Hold new batches back until [`resume`](Self::resume).
+    pub fn pause(&self) {
+        self.transition(State::Running, State::Paused);
+    }
+
+    /// Continue a paused load.
+    pub fn resume(&self) {
+        self.transition(State::Paused, State::Running);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.state.borrow() == State::Cancelled
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.state.borrow() == State::Paused
+    }
+
+    fn transition(&self, from: State, to: State) {
+        self.state.send_if_modified(|state| {
+            let change = *state == from;
+            if change {
+                *state = to;
+            }
+            change
+        });
+    }
+
+    /// Wait while the load is paused. Returns whether the next batch may be
+    /// sent, which is false once the load is cancelled.
+    pub(crate) async fn proceed(&self) -> bool {
+        let mut state = self.state.subscribe();
+        match state.wait_for(|s| *s != State::Paused).await {
+            Ok(state) => *state == State::Running,
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn pauses_until_resumed_or_cancelled() {
+        let handle = LoadHandle::new();
+        assert!(handle.proceed().await);
+
+        handle.pause();
+        assert!(handle.is_paused());
+        let waiting = tokio::spawn({
+            let handle = handle.clone();
+            async move { handle.proceed().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+        handle.resume();
+        assert!(waiting.await.unwrap());
+
+        handle.pause();
+        let waiting = tokio::spawn({
+            let handle = handle.clone();
+            async move { handle.proceed().await }
+        });
+        handle.cancel();
+        assert!(!waiting.await.unwrap());
+        handle.resume();
+        assert!(handle.is_cancelled());
+        assert!(!handle.proceed().await);
+    }
+}
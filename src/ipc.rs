@@ -0,0 +1,196 @@
+//! Arrow IPC sources.
+//!
+//! Both the random access file format (Feather v2, usually `.arrow` or
+//! `.feather`) and the streaming format (usually `.arrows`) are supported. A
+//! stream can also be read from standard input, which lets the loader sit at
+//! the end of a Unix pipeline.
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use arrow::ipc::reader::{FileReader, StreamReader};
+use arrow::record_batch::RecordBatch;
+use neo4rs::Graph;
+
+use crate::convert::batch_to_records;
+use crate::job::{Format, NodeLoad, RelationshipLoad};
+use crate::loader::Record;
+
+/// Where to read Arrow IPC data from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IpcSource {
+    /// An IPC file (Feather v2).
+    File(PathBuf),
+    /// A file holding an IPC stream.
+    Stream(PathBuf),
+    /// An IPC stream on standard input.
+    Stdin,
+}
+
+impl IpcSource {
+    /// Pick the source for a command line path: `-` reads a stream from
+    /// standard input, `.arrows` files are streams and anything else is read
+    /// as an IPC file, or as a stream when it lacks the IPC file header.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        if path == Path::new("-") {
+            IpcSource::Stdin
+        } else if path.extension().and_then(|e| e.to_str()) == Some("arrows") {
+            IpcSource::Stream(path.to_path_buf())
+        } else {
+            IpcSource::File(path.to_path_buf())
+        }
+    }
+
+    /// The command line path of the source, `-` for standard input.
+    pub fn path(&self) -> PathBuf {
+        match self {
+            IpcSource::File(path) | IpcSource::Stream(path) => path.clone(),
+            IpcSource::Stdin => PathBuf::from("-"),
+        }
+    }
+}
+
+/// Load Arrow IPC data into Neo4j in parallel.
+/// Each row is mapped to properties of a node with the given label.
+pub async fn load_ipc_nodes_parallel(
+    graph: Graph,
+    source: IpcSource,
+    label: &str,
+    concurrency: usize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    NodeLoad::new(source.path(), label)
+        .format(Format::Ipc)
+        .concurrency(concurrency)
+        .run(&graph)
+        .await?;
+    Ok(())
+}
+
+/// Load relationships from Arrow IPC data in parallel.
+///
+/// Columns are mapped exactly as in
+/// [`load_parquet_relationships_parallel`](crate::load_parquet_relationships_parallel).
+#[allow(clippy::too_many_arguments)]
+pub async fn load_ipc_relationships_parallel(
+    graph: Graph,
+    source: IpcSource,
+    rel_type: &str,
+    start_label: &str,
+    start_id_col: &str,
+    start_id_prop: &str,
+    end_label: &str,
+    end_id_col: &str,
+    end_id_prop: &str,
+    concurrency: usize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    RelationshipLoad::new(source.path(), rel_type)
+        .format(Format::Ipc)
+        .start(start_label, start_id_col)
+        .start_property(start_id_prop)
+        .end(end_label, end_id_col)
+        .end_property(end_id_prop)
+        .concurrency(concurrency)
+        .run(&graph)
+        .await?;
+    Ok(())
+}
+
+/// Decode IPC data in a blocking task.
+pub(crate) async fn read_ipc_records(
+    source: IpcSource,
+) -> Result<Vec<Record>, Box<dyn std::error::Error + Send + Sync>> {
+    tokio::task::spawn_blocking(move || read_ipc_records_blocking(&source)).await?
+}
+
+fn read_ipc_records_blocking(
+    source: &IpcSource,
+) -> Result<Vec<Record>, Box<dyn std::error::Error + Send + Sync>> {
+    let batches: Box<dyn Iterator<Item = Result<RecordBatch, arrow::error::ArrowError>>> =
+        match source {
+            IpcSource::File(path) if has_file_magic(path)? => {
+                Box::new(FileReader::try_new(File::open(path)?, None)?)
+            }
+            IpcSource::File(path) | IpcSource::Stream(path) => Box::new(StreamReader::try_new(
+                BufReader::new(File::open(path)?),
+                None,
+            )?),
+            IpcSource::Stdin => Box::new(StreamReader::try_new(std::io::stdin().lock(), None)?),
+        };
+
+    let mut records = Vec::new();
+    for batch in batches {
+        records.extend(batch_to_records(&batch?)?);
+    }
+    Ok(records)
+}
+
+/// Whether `path` starts with the magic bytes of an IPC file. Streams lack
+/// them, so a stream saved under a file extension is still readable.
+fn has_file_magic(path: &Path) -> std::io::Result<bool> {
+    let mut magic = [0; 6];
+    match File::open(path)?.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == b"ARROW1"),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::Int64Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::ipc::writer::{FileWriter, StreamWriter};
+    use neo4rs::BoltType;
+    use std::sync::Arc;
+
+    fn batch() -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        RecordBatch::try_new(schema, vec![Arc::new(Int64Array::from(vec![1, 2, 3]))]).unwrap()
+    }
+
+    #[test]
+    fn picks_source_from_path() {
+        assert_eq!(IpcSource::from_path("-"), IpcSource::Stdin);
+        assert_eq!(
+            IpcSource::from_path("a.arrows"),
+            IpcSource::Stream(PathBuf::from("a.arrows"))
+        );
+        assert_eq!(
+            IpcSource::from_path("a.feather"),
+            IpcSource::File(PathBuf::from("a.feather"))
+        );
+    }
+
+    #[test]
+    fn reads_files_and_streams() {
+        let batch = batch();
+        let dir = std::env::temp_dir();
+        let file_path = dir.join(format!("{}-nodes.arrow", std::process::id()));
+        let stream_path = dir.join(format!("{}-nodes.arrows", std::process::id()));
+
+        let mut writer =
+            FileWriter::try_new(File::create(&file_path).unwrap(), &batch.schema()).unwrap();
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+        let mut writer =
+            StreamWriter::try_new(File::create(&stream_path).unwrap(), &batch.schema()).unwrap();
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+
+        for source in [
+            IpcSource::from_path(&file_path),
+            IpcSource::from_path(&stream_path),
+        ] {
+            let records = read_ipc_records_blocking(&source).unwrap();
+            assert_eq!(records.len(), 3);
+            assert_eq!(records[2][0], ("id".to_string(), BoltType::from(3i64)));
+        }
+        let misnamed = IpcSource::File(stream_path.clone());
+        assert_eq!(read_ipc_records_blocking(&misnamed).unwrap().len(), 3);
+        std::fs::remove_file(&file_path).ok();
+        std::fs::remove_file(&stream_path).ok();
+    }
+}
@@ -0,0 +1,807 @@
+//! Builder-style load jobs.
+//!
+//! [`NodeLoad`] and [`RelationshipLoad`] carry every option of a load, so new
+//! options do not change existing call sites:
+//!
+//! ```no_run
+//! # async fn example(graph: neo4rs::Graph) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+//! use neo4j_parallel_rust_loader::{NodeLoad, RelationshipLoad};
+//!
+//! NodeLoad::new("people.parquet", "Person")
+//!     .key("id")
+//!     .batch_size(5000)
+//!     .concurrency(8)
+//!     .run(&graph)
+//!     .await?;
+//! RelationshipLoad::new("knows.parquet", "KNOWS")
+//!     .start("Person", "src")
+//!     .start_property("id")
+//!     .end("Person", "dst")
+//!     .end_property("id")
+//!     .run(&graph)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The free `load_*` functions are thin wrappers around these builders.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use arrow::record_batch::RecordBatch;
+
+use crate::adaptive::Adaptive;
+use crate::bookmark::{self, Bookmark};
+use crate::convert::batch_to_records;
+use crate::csv::{CsvOptions, read_csv_records};
+use crate::dataset::{
+    DatasetOptions, LabelGroup, flatten_groups, group_label, read_parquet_dataset,
+};
+use crate::doctor::{Entity, Lookup, Target};
+use crate::handle::LoadHandle;
+use crate::ipc::{IpcSource, read_ipc_records};
+use crate::loader::{
+    LoadSummary, NodeSpec, RelationshipSpec, WriteOptions, write_nodes, write_relationships,
+};
+use crate::ndjson::{NdjsonOptions, read_ndjson_records};
+use crate::planner::{GreedyColoring, PartitionStrategy};
+use crate::routing::Router;
+use crate::run::{self, RunJob};
+use crate::throttle::Throttle;
+
+/// Number of concurrent transactions unless configured otherwise.
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// File format of a load job together with its reader options.
+#[derive(Debug, Clone)]
+pub enum Format {
+    /// A Parquet file, directory or glob; see [`crate::dataset`].
+    Parquet(DatasetOptions),
+    Csv(CsvOptions),
+    Ndjson(NdjsonOptions),
+    /// An Arrow IPC file or stream, or a stream on standard input for `-`.
+    Ipc,
+}
+
+impl Format {
+    /// Pick the format from the file extension, ignoring a trailing `.gz` or
+    /// `.zst`. Anything unrecognised, including directories and globs, is
+    /// read as Parquet.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        if path == Path::new("-") {
+            return Format::Ipc;
+        }
+        let mut extension = path.extension().and_then(|e| e.to_str());
+        if matches!(extension, Some("gz" | "gzip" | "zst" | "zstd")) {
+            extension = path
+                .file_stem()
+                .map(Path::new)
+                .and_then(|stem| stem.extension())
+                .and_then(|e| e.to_str());
+        }
+        match extension {
+            Some("csv") => Format::Csv(CsvOptions::default()),
+            Some("tsv") => Format::Csv(CsvOptions {
+                delimiter: b'\t',
+                ..CsvOptions::default()
+            }),
+            Some("json" | "jsonl" | "ndjson") => Format::Ndjson(NdjsonOptions::default()),
+            Some("arrow" | "arrows" | "feather" | "ipc") => Format::Ipc,
+            _ => Format::Parquet(DatasetOptions::default()),
+        }
+    }
+}
+
+/// Where a load job reads its rows from.
+#[derive(Clone)]
+enum Source {
+    /// A file, directory or glob read in the job's [`Format`].
+    Path(PathBuf),
+    /// Record batches held in memory; see [`crate::batches`].
+    Batches(Vec<RecordBatch>),
+}
+
+impl Source {
+    /// How the source is recorded on a run's audit node.
+    fn describe(&self) -> String {
+        match self {
+            Source::Path(path) => path.display().to_string(),
+            Source::Batches(batches) => format!("{} record batches", batches.len()),
+        }
+    }
+}
+
+impl std::fmt::Debug for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Path(path) => f.debug_tuple("Path").field(path).finish(),
+            Source::Batches(batches) => {
+                let rows: usize = batches.iter().map(RecordBatch::num_rows).sum();
+                write!(f, "Batches({} batches, {rows} rows)", batches.len())
+            }
+        }
+    }
+}
+
+/// Read the rows of `source`, in `format` or the format detected from its
+/// path, grouped by the extra labels of Parquet partitions. Other sources
+/// yield a single group without labels.
+async fn read_groups(
+    source: &Source,
+    format: &Option<Format>,
+    concurrency: usize,
+) -> Result<Vec<LabelGroup>, Box<dyn std::error::Error + Send + Sync>> {
+    let records = match source {
+        Source::Batches(batches) => {
+            let mut records = Vec::new();
+            for batch in batches {
+                records.extend(batch_to_records(batch)?);
+            }
+            records
+        }
+        Source::Path(path) => match format.clone().unwrap_or_else(|| Format::from_path(path)) {
+            Format::Parquet(options) => {
+                return read_parquet_dataset(path, &options, concurrency).await;
+            }
+            Format::Csv(options) => read_csv_records(path.clone(), options).await?,
+            Format::Ndjson(options) => read_ndjson_records(path.clone(), options).await?,
+            Format::Ipc => read_ipc_records(IpcSource::from_path(path)).await?,
+        },
+    };
+    Ok(vec![LabelGroup {
+        labels: Vec::new(),
+        records,
+    }])
+}
+
+/// A node load job.
+#[derive(Debug, Clone)]
+pub struct NodeLoad {
+    source: Source,
+    label: String,
+    keys: Vec<String>,
+    write: WriteOptions,
+    format: Option<Format>,
+}
+
+impl NodeLoad {
+    /// Load every row of `path` as a node labelled `label`.
+    pub fn new(path: impl Into<PathBuf>, label: impl Into<String>) -> Self {
+        Self::with_source(Source::Path(path.into()), label.into())
+    }
+
+    /// Load every row of in-memory record batches as a node labelled
+    /// `label`. The [`format`](Self::format) does not apply.
+    pub fn from_batches(
+        batches: impl IntoIterator<Item = RecordBatch>,
+        label: impl Into<String>,
+    ) -> Self {
+        Self::with_source(Source::Batches(batches.into_iter().collect()), label.into())
+    }
+
+    fn with_source(source: Source, label: String) -> Self {
+        Self {
+            source,
+            label,
+            keys: Vec::new(),
+            write: WriteOptions::new(DEFAULT_CONCURRENCY),
+            format: None,
+        }
+    }
+
+    /// Merge nodes on this column instead of creating them. Call repeatedly
+    /// for a composite key.
+    pub fn key(mut self, column: impl Into<String>) -> Self {
+        self.keys.push(column.into());
+        self
+    }
+
+    /// Rows written per transaction (default 1000).
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.write.batch_size = batch_size;
+        self
+    }
+
+    /// Maximum number of concurrent transactions (default 4).
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.write.concurrency = concurrency;
+        self
+    }
+
+    /// Fail the load when a transaction takes longer than `timeout`. The
+    /// timeout is enforced by the client and includes waiting for a pooled
+    /// connection.
+    pub fn transaction_timeout(mut self, timeout: Duration) -> Self {
+        self.write.transaction_timeout = Some(timeout);
+        self
+    }
+
+    /// Send `payload_size` rows per request and let the server commit them
+    /// with `CALL { } IN CONCURRENT TRANSACTIONS`, in transactions of
+    /// `batch_size` rows and `concurrency` at a time. Failed transactions
+    /// do not stop the load; they are counted in the [`LoadSummary`].
+    pub fn server_batching(mut self, payload_size: usize) -> Self {
+        self.write.server_payload = Some(payload_size);
+        self
+    }
+
+    /// Treat `batch_size` and `concurrency` as starting points and tune them
+    /// within `limits` as described in [`crate::adaptive`]. Cannot be
+    /// combined with [`server_batching`](Self::server_batching).
+    pub fn adaptive(mut self, limits: Adaptive) -> Self {
+        self.write.adaptive = Some(limits);
+        self
+    }
+
+    /// Limit rows per second and concurrent transactions, except during the
+    /// throttle's full speed windows; see [`crate::throttle`].
+    pub fn throttle(mut self, throttle: Throttle) -> Self {
+        self.write.throttle = Some(throttle);
+        self
+    }
+
+    /// Control the running load through `handle`; see [`crate::handle`].
+    pub fn handle(mut self, handle: LoadHandle) -> Self {
+        self.write.handle = Some(handle);
+        self
+    }
+
+    /// Tag created entities with `run_id` and record the load on the run's
+    /// `:LoadRun` node, so it can be rolled back; see [`crate::run`].
+    pub fn run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.write.run_id = Some(run_id.into());
+        self
+    }
+
+    /// Wait until the writer has committed the transactions of
+    /// `bookmarks`, such as those of an earlier load's [`LoadSummary`],
+    /// before writing; see [`crate::bookmark`].
+    pub fn bookmarks(mut self, bookmarks: impl IntoIterator<Item = Bookmark>) -> Self {
+        self.write.bookmarks.extend(bookmarks);
+        self
+    }
+
+    /// Fail the load when its bookmark cannot be read once it has committed.
+    /// By default such a failure prints a warning and the summary has no
+    /// bookmarks, since the rows are already written.
+    pub fn capture_bookmarks(mut self, required: bool) -> Self {
+        self.write.capture_bookmarks = required;
+        self
+    }
+
+    /// Override the format detected from the path.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// What the [`Doctor`](crate::Doctor) checks for this load.
+    pub(crate) fn target(&self) -> Target {
+        let mut lookups = Vec::new();
+        if !self.keys.is_empty() {
+            lookups.push(Lookup {
+                entity: Entity::Node,
+                name: self.label.clone(),
+                properties: self.keys.clone(),
+                purpose: "merge",
+            });
+        }
+        Target {
+            description: format!("nodes :{}", self.label),
+            probe: format!("CREATE (n:{}) DELETE n", self.label),
+            lookups,
+        }
+    }
+
+    /// Run the load against `target`, a `&Graph` or a [`Router`] that
+    /// follows the cluster leader.
+    pub async fn run(
+        &self,
+        target: impl Into<Router>,
+    ) -> Result<LoadSummary, Box<dyn std::error::Error + Send + Sync>> {
+        let router = target.into();
+        validate(&self.write)?;
+        router.detect_dialect().await?;
+        let job = self.write.run_id.as_deref().map(|run_id| RunJob {
+            run_id,
+            parameters: serde_json::json!({
+                "command": "nodes",
+                "path": self.source.describe(),
+                "label": self.label,
+                "keys": self.keys,
+                "batchSize": self.write.batch_size,
+                "concurrency": self.write.concurrency,
+            }),
+            labels: vec![self.label.as_str()],
+            types: Vec::new(),
+        });
+        bookmarked(
+            &router,
+            &self.write,
+            audited(&router, job, self.load(&router)),
+        )
+        .await
+    }
+
+    async fn load(
+        &self,
+        router: &Router,
+    ) -> Result<LoadSummary, Box<dyn std::error::Error + Send + Sync>> {
+        let groups = read_groups(&self.source, &self.format, self.write.concurrency).await?;
+        let mut summary = LoadSummary::default();
+        for group in groups {
+            let label = group_label(&self.label, &group);
+            let spec = NodeSpec {
+                label: &label,
+                keys: &self.keys,
+            };
+            summary += write_nodes(router.clone(), group.records, &spec, &self.write).await?;
+        }
+        Ok(summary)
+    }
+}
+
+/// One side of a relationship: the node label, the source column holding its
+/// identifier and the node property that identifier is matched against.
+#[derive(Debug, Clone)]
+struct Endpoint {
+    label: String,
+    column: String,
+    property: Option<String>,
+}
+
+impl Endpoint {
+    fn property(&self) -> &str {
+        self.property.as_deref().unwrap_or(&self.column)
+    }
+}
+
+/// A relationship load job.
+#[derive(Clone)]
+pub struct RelationshipLoad {
+    source: Source,
+    rel_type: String,
+    start: Option<Endpoint>,
+    end: Option<Endpoint>,
+    keys: Vec<String>,
+    write: WriteOptions,
+    strategy: Arc<dyn PartitionStrategy>,
+    format: Option<Format>,
+}
+
+impl std::fmt::Debug for RelationshipLoad {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RelationshipLoad")
+            .field("source", &self.source)
+            .field("rel_type", &self.rel_type)
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .field("keys", &self.keys)
+            .field("write", &self.write)
+            .field("strategy", &self.strategy.name())
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+impl RelationshipLoad {
+    /// Load every row of `path` as a relationship of type `rel_type`. The
+    /// endpoints must be set with [`start`](Self::start) and
+    /// [`end`](Self::end).
+    pub fn new(path: impl Into<PathBuf>, rel_type: impl Into<String>) -> Self {
+        Self::with_source(Source::Path(path.into()), rel_type.into())
+    }
+
+    /// Load every row of in-memory record batches as a relationship of type
+    /// `rel_type`. The [`format`](Self::format) does not apply.
+    pub fn from_batches(
+        batches: impl IntoIterator<Item = RecordBatch>,
+        rel_type: impl Into<String>,
+    ) -> Self {
+        Self::with_source(
+            Source::Batches(batches.into_iter().collect()),
+            rel_type.into(),
+        )
+    }
+
+    fn with_source(source: Source, rel_type: String) -> Self {
+        Self {
+            source,
+            rel_type,
+            start: None,
+            end: None,
+            keys: Vec::new(),
+            write: WriteOptions::new(DEFAULT_CONCURRENCY),
+            strategy: Arc::new(GreedyColoring),
+            format: None,
+        }
+    }
+
+    /// Match start nodes labelled `label` using the identifier in `column`.
+    /// The node property defaults to the column name.
+    pub fn start(mut self, label: impl Into<String>, column: impl Into<String>) -> Self {
+        let property = self.start.and_then(|e| e.property);
+        self.start = Some(Endpoint {
+            label: label.into(),
+            column: column.into(),
+            property,
+        });
+        self
+    }
+
+    /// Node property the start identifier is matched against.
+    pub fn start_property(mut self, property: impl Into<String>) -> Self {
+        set_property(&mut self.start, property.into());
+        self
+    }
+
+    /// Match end nodes labelled `label` using the identifier in `column`.
+    /// The node property defaults to the column name.
+    pub fn end(mut self, label: impl Into<String>, column: impl Into<String>) -> Self {
+        let property = self.end.and_then(|e| e.property);
+        self.end = Some(Endpoint {
+            label: label.into(),
+            column: column.into(),
+            property,
+        });
+        self
+    }
+
+    /// Node property the end identifier is matched against.
+    pub fn end_property(mut self, property: impl Into<String>) -> Self {
+        set_property(&mut self.end, property.into());
+        self
+    }
+
+    /// Merge relationships on this property instead of creating them. Call
+    /// repeatedly for a composite key.
+    pub fn key(mut self, column: impl Into<String>) -> Self {
+        self.keys.push(column.into());
+        self
+    }
+
+    /// Rows written per transaction (default 1000).
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.write.batch_size = batch_size;
+        self
+    }
+
+    /// Maximum number of concurrent transactions (default 4).
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.write.concurrency = concurrency;
+        self
+    }
+
+    /// Fail the load when a transaction takes longer than `timeout`. The
+    /// timeout is enforced by the client and includes waiting for a pooled
+    /// connection.
+    pub fn transaction_timeout(mut self, timeout: Duration) -> Self {
+        self.write.transaction_timeout = Some(timeout);
+        self
+    }
+
+    /// Send up to `payload_size` rows per request and let the server commit
+    /// them as described for [`NodeLoad::server_batching`]. A payload never
+    /// spans two planner rounds.
+    pub fn server_batching(mut self, payload_size: usize) -> Self {
+        self.write.server_payload = Some(payload_size);
+        self
+    }
+
+    /// Treat `batch_size` and `concurrency` as starting points and tune them
+    /// within `limits` as described in [`crate::adaptive`]. Cannot be
+    /// combined with [`server_batching`](Self::server_batching).
+    pub fn adaptive(mut self, limits: Adaptive) -> Self {
+        self.write.adaptive = Some(limits);
+        self
+    }
+
+    /// Limit rows per second and concurrent transactions, except during the
+    /// throttle's full speed windows; see [`crate::throttle`].
+    pub fn throttle(mut self, throttle: Throttle) -> Self {
+        self.write.throttle = Some(throttle);
+        self
+    }
+
+    /// Control the running load through `handle`; see [`crate::handle`].
+    pub fn handle(mut self, handle: LoadHandle) -> Self {
+        self.write.handle = Some(handle);
+        self
+    }
+
+    /// Tag created entities with `run_id` and record the load on the run's
+    /// `:LoadRun` node, so it can be rolled back; see [`crate::run`].
+    pub fn run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.write.run_id = Some(run_id.into());
+        self
+    }
+
+    /// Wait until the writer has committed the transactions of
+    /// `bookmarks`, such as those of an earlier load's [`LoadSummary`],
+    /// before writing; see [`crate::bookmark`].
+    pub fn bookmarks(mut self, bookmarks: impl IntoIterator<Item = Bookmark>) -> Self {
+        self.write.bookmarks.extend(bookmarks);
+        self
+    }
+
+    /// Fail the load when its bookmark cannot be read once it has committed.
+    /// By default such a failure prints a warning and the summary has no
+    /// bookmarks, since the rows are already written.
+    pub fn capture_bookmarks(mut self, required: bool) -> Self {
+        self.write.capture_bookmarks = required;
+        self
+    }
+
+    /// Scheduling strategy (default [`GreedyColoring`]).
+    pub fn strategy(mut self, strategy: impl PartitionStrategy + 'static) -> Self {
+        self.strategy = Arc::new(strategy);
+        self
+    }
+
+    /// Override the format detected from the path.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// What the [`Doctor`](crate::Doctor) checks for this load.
+    pub(crate) fn target(&self) -> Target {
+        let endpoints = [
+            (&self.start, "match start nodes"),
+            (&self.end, "match end nodes"),
+        ];
+        let mut lookups: Vec<Lookup> = endpoints
+            .into_iter()
+            .filter_map(|(endpoint, purpose)| {
+                let endpoint = endpoint.as_ref().filter(|e| !e.label.is_empty())?;
+                Some(Lookup {
+                    entity: Entity::Node,
+                    name: endpoint.label.clone(),
+                    properties: vec![endpoint.property().to_string()],
+                    purpose,
+                })
+            })
+            .collect();
+        if !self.keys.is_empty() {
+            lookups.push(Lookup {
+                entity: Entity::Relationship,
+                name: self.rel_type.clone(),
+                properties: self.keys.clone(),
+                purpose: "merge",
+            });
+        }
+        let label = |endpoint: &Option<Endpoint>| {
+            endpoint
+                .as_ref()
+                .filter(|e| !e.label.is_empty())
+                .map(|e| format!(":{}", e.label))
+                .unwrap_or_default()
+        };
+        Target {
+            description: format!("relationships [:{}]", self.rel_type),
+            probe: format!(
+                "CREATE (a{})-[r:{}]->(b{}) DELETE r, a, b",
+                label(&self.start),
+                self.rel_type,
+                label(&self.end)
+            ),
+            lookups,
+        }
+    }
+
+    /// Run the load against `target`, a `&Graph` or a [`Router`] that
+    /// follows the cluster leader.
+    pub async fn run(
+        &self,
+        target: impl Into<Router>,
+    ) -> Result<LoadSummary, Box<dyn std::error::Error + Send + Sync>> {
+        let router = target.into();
+        validate(&self.write)?;
+        router.detect_dialect().await?;
+        let label = |e: &Option<Endpoint>| e.as_ref().map(|e| e.label.clone());
+        let job = self.write.run_id.as_deref().map(|run_id| RunJob {
+            run_id,
+            parameters: serde_json::json!({
+                "command": "rels",
+                "path": self.source.describe(),
+                "type": self.rel_type,
+                "startLabel": label(&self.start),
+                "endLabel": label(&self.end),
+                "keys": self.keys,
+                "batchSize": self.write.batch_size,
+                "concurrency": self.write.concurrency,
+            }),
+            labels: Vec::new(),
+            types: vec![self.rel_type.as_str()],
+        });
+        bookmarked(
+            &router,
+            &self.write,
+            audited(&router, job, self.load(&router)),
+        )
+        .await
+    }
+
+    async fn load(
+        &self,
+        router: &Router,
+    ) -> Result<LoadSummary, Box<dyn std::error::Error + Send + Sync>> {
+        let start = self
+            .start
+            .as_ref()
+            .filter(|e| !e.label.is_empty())
+            .ok_or("relationship start node is not set")?;
+        let end = self
+            .end
+            .as_ref()
+            .filter(|e| !e.label.is_empty())
+            .ok_or("relationship end node is not set")?;
+        if let Some(Format::Parquet(options)) = &self.format
+            && !options.partition_labels.is_empty()
+        {
+            return Err("partition labels are not supported for relationships".into());
+        }
+        let groups = read_groups(&self.source, &self.format, self.write.concurrency).await?;
+        let spec = RelationshipSpec {
+            rel_type: &self.rel_type,
+            start_label: &start.label,
+            start_id_col: &start.column,
+            start_id_prop: start.property(),
+            end_label: &end.label,
+            end_id_col: &end.column,
+            end_id_prop: end.property(),
+            keys: &self.keys,
+        };
+        write_relationships(
+            router.clone(),
+            flatten_groups(groups),
+            &spec,
+            &self.write,
+            self.strategy.as_ref(),
+        )
+        .await
+    }
+}
+
+fn set_property(endpoint: &mut Option<Endpoint>, property: String) {
+    match endpoint {
+        Some(endpoint) => endpoint.property = Some(property),
+        None => {
+            *endpoint = Some(Endpoint {
+                label: String::new(),
+                column: String::new(),
+                property: Some(property),
+            })
+        }
+    }
+}
+
+/// Await `load` and, for a load of a run, record its start and outcome on
+/// the run's audit node.
+async fn audited(
+    router: &Router,
+    job: Option<RunJob<'_>>,
+    load: impl Future<Output = Result<LoadSummary, Box<dyn std::error::Error + Send + Sync>>>,
+) -> Result<LoadSummary, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(job) = job else {
+        return load.await;
+    };
+    run::start(router, &job).await?;
+    let outcome = load.await;
+    let recorded = run::finish(router, job.run_id, &outcome).await;
+    let summary = outcome?;
+    recorded?;
+    Ok(summary)
+}
+
+/// Wait for the bookmarks of `options`, run `load` and add the bookmark of
+/// what it committed to its summary. Failing to read the bookmark only fails
+/// the committed load when `options.capture_bookmarks` asks for it.
+async fn bookmarked(
+    router: &Router,
+    options: &WriteOptions,
+    load: impl Future<Output = Result<LoadSummary, Box<dyn std::error::Error + Send + Sync>>>,
+) -> Result<LoadSummary, Box<dyn std::error::Error + Send + Sync>> {
+    bookmark::wait(router, &options.bookmarks).await?;
+    let mut summary = load.await?;
+    match bookmark::capture(router).await {
+        Ok(captured) => summary.bookmarks.extend(captured),
+        Err(e) if options.capture_bookmarks => {
+            return Err(format!("the load finished, but reading its bookmark failed: {e}").into());
+        }
+        Err(e) => eprintln!("warning: the load finished without a bookmark: {e}"),
+    }
+    Ok(summary)
+}
+
+fn validate(options: &WriteOptions) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if options.batch_size == 0 {
+        return Err("batch size must be at least 1".into());
+    }
+    if options.concurrency == 0 {
+        return Err("concurrency must be at least 1".into());
+    }
+    if options.run_id.as_deref() == Some("") {
+        return Err("run id must not be empty".into());
+    }
+    if options.server_payload == Some(0) {
+        return Err("server batching payload size must be at least 1".into());
+    }
+    if let Some(limits) = &options.adaptive {
+        if options.server_payload.is_some() {
+            return Err("adaptive sizing cannot be combined with server batching".into());
+        }
+        limits.validate()?;
+    }
+    if let Some(throttle) = &options.throttle {
+        if throttle.max_concurrency.is_some() && options.server_payload.is_some() {
+            return Err(
+                "throttled concurrency cannot be combined with server batching; lower \
+                 concurrency instead"
+                    .into(),
+            );
+        }
+        throttle.validate()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_format_from_extension() {
+        assert!(matches!(Format::from_path("a.csv.gz"), Format::Csv(_)));
+        assert!(matches!(Format::from_path("a.tsv"), Format::Csv(o) if o.delimiter == b'\t'));
+        assert!(matches!(
+            Format::from_path("a.jsonl.zst"),
+            Format::Ndjson(_)
+        ));
+        assert!(matches!(Format::from_path("-"), Format::Ipc));
+        assert!(matches!(Format::from_path("a.arrows"), Format::Ipc));
+        assert!(matches!(
+            Format::from_path("data/*.parquet"),
+            Format::Parquet(_)
+        ));
+        assert!(matches!(Format::from_path("data"), Format::Parquet(_)));
+    }
+
+    #[test]
+    fn endpoint_property_defaults_to_column() {
+        let load = RelationshipLoad::new("knows.parquet", "KNOWS")
+            .start_property("id")
+            .start("Person", "src")
+            .end("Person", "dst");
+        assert_eq!(load.start.as_ref().unwrap().property(), "id");
+        assert_eq!(load.end.as_ref().unwrap().property(), "dst");
+    }
+
+    #[test]
+    fn rejects_adaptive_server_batching() {
+        let load = NodeLoad::new("people.csv", "Person").adaptive(Adaptive::default());
+        assert!(validate(&load.write).is_ok());
+        let load = load.server_batching(10_000);
+        assert!(validate(&load.write).is_err());
+    }
+
+    #[test]
+    fn rejects_throttled_server_concurrency() {
+        let throttle = Throttle {
+            rows_per_second: Some(5000),
+            ..Throttle::default()
+        };
+        let load = NodeLoad::new("people.csv", "Person")
+            .server_batching(10_000)
+            .throttle(throttle.clone());
+        assert!(validate(&load.write).is_ok());
+        let load = load.throttle(Throttle {
+            max_concurrency: Some(2),
+            ..throttle
+        });
+        assert!(validate(&load.write).is_err());
+    }
+}
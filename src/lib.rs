@@ -1,12 +1,58 @@
+pub mod adaptive;
+pub mod batches;
+pub mod bookmark;
 pub mod config;
+mod convert;
+pub mod csv;
+pub mod dataset;
+pub mod dialect;
+pub mod doctor;
+pub mod filter;
+pub mod handle;
+pub mod ipc;
+pub mod job;
 pub mod loader;
+pub mod ndjson;
 pub mod neo4j;
+pub mod planner;
+pub mod routing;
+pub mod run;
+pub mod source;
+pub mod throttle;
 
-pub use config::Neo4jConfig;
+pub use adaptive::Adaptive;
+pub use batches::{
+    load_batch_stream_nodes_parallel, load_batch_stream_relationships_parallel,
+    load_batches_nodes_parallel, load_batches_relationships_parallel,
+};
+pub use bookmark::Bookmark;
+pub use config::{
+    Auth, AuthScheme, ConfigFile, ConfigOverrides, DEFAULT_CONNECT_TIMEOUT,
+    DEFAULT_MAX_CONNECTIONS, Neo4jConfig, Secret, TlsConfig, TrustMode, parse_duration,
+};
+pub use csv::{CsvOptions, load_csv_nodes_parallel, load_csv_relationships_parallel};
+pub use dataset::DatasetOptions;
+pub use dialect::Dialect;
+pub use doctor::{Check, Doctor, Report, Status};
+pub use filter::{CompareOp, Filter, FilterValue};
+pub use handle::LoadHandle;
+pub use ipc::{IpcSource, load_ipc_nodes_parallel, load_ipc_relationships_parallel};
+pub use job::{Format, NodeLoad, RelationshipLoad};
 pub use loader::{
+    LoadSummary, load_parquet_dataset_nodes_parallel, load_parquet_dataset_relationships_parallel,
     load_parquet_nodes_parallel, load_parquet_parallel, load_parquet_relationships_parallel,
+    load_parquet_relationships_parallel_with_strategy, plan_parquet_relationships,
+    plan_parquet_relationships_with_labels,
 };
+pub use ndjson::{NdjsonOptions, load_ndjson_nodes_parallel, load_ndjson_relationships_parallel};
 pub use neo4j::connect;
+pub use planner::{
+    GreedyColoring, MixAndBatch, NoPartition, PartitionStrategy, Plan, PlanStats, SortByStart,
+};
+pub use routing::Router;
+pub use run::{Rollback, new_run_id, rollback};
+pub use source::Compression;
+pub use throttle::{Throttle, TimeWindow, parse_utc_offset};
 
 #[cfg(test)]
 mod tests {
@@ -25,8 +71,7 @@ mod tests {
         }
         let cfg = Neo4jConfig::from_env().unwrap();
         assert_eq!(cfg.uri, "bolt://example.com:7687");
-        assert_eq!(cfg.username, "neo4j");
-        assert_eq!(cfg.password, "pass");
+        assert_eq!(cfg.auth, Auth::basic("neo4j", "pass"));
         assert_eq!(cfg.database.as_deref(), Some("neo4j"));
     }
 
@@ -1,61 +1,371 @@
 use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use futures::{StreamExt, stream::FuturesUnordered};
-use neo4rs::{BoltType, Graph, query};
-use parquet::file::reader::{FileReader, SerializedFileReader};
-use parquet::record::Row;
-use tokio::sync::Semaphore;
+use neo4rs::{BoltType, Graph, Query, query};
+
+use crate::adaptive::{Adaptive, Controller};
+use crate::bookmark::{self, Bookmark};
+use crate::dataset::{DatasetOptions, flatten_groups, read_parquet_dataset};
+use crate::handle::LoadHandle;
+use crate::job::{Format, NodeLoad, RelationshipLoad};
+use crate::planner::{Edge, NodeKey, PartitionStrategy, PlanStats, Round};
+use crate::routing::Router;
+use crate::throttle::{Pacer, Throttle};
+
+/// A source row as column name and value pairs, in schema order.
+pub(crate) type Record = Vec<(String, BoltType)>;
+
+/// Number of rows sent per transaction unless configured otherwise.
+pub(crate) const DEFAULT_BATCH_SIZE: usize = 1000;
+
+/// How records are written: transaction size, parallelism and limits.
+#[derive(Debug, Clone)]
+pub(crate) struct WriteOptions {
+    pub batch_size: usize,
+    pub concurrency: usize,
+    /// Fail the load when a single transaction, including the wait for a
+    /// pooled connection, takes longer than this.
+    pub transaction_timeout: Option<Duration>,
+    /// Send this many rows per request and let the server split them into
+    /// transactions of `batch_size` rows, `concurrency` at a time. Rows are
+    /// batched on the client when unset.
+    pub server_payload: Option<usize>,
+    /// Tune `concurrency` and `batch_size` within these limits while
+    /// loading; they stay fixed when unset.
+    pub adaptive: Option<Adaptive>,
+    /// Limit rows per second and concurrent transactions.
+    pub throttle: Option<Throttle>,
+    /// Cancel, pause and resume the load.
+    pub handle: Option<LoadHandle>,
+    /// Tag created nodes and relationships with this run id.
+    pub run_id: Option<String>,
+    /// Wait for the writer to commit these before writing.
+    pub bookmarks: Vec<Bookmark>,
+    /// Fail the load when its bookmark cannot be read afterwards, rather
+    /// than warning and returning no bookmark.
+    pub capture_bookmarks: bool,
+}
+
+impl WriteOptions {
+    pub fn new(concurrency: usize) -> Self {
+        Self {
+            batch_size: DEFAULT_BATCH_SIZE,
+            concurrency,
+            transaction_timeout: None,
+            server_payload: None,
+            adaptive: None,
+            throttle: None,
+            handle: None,
+            run_id: None,
+            bookmarks: Vec::new(),
+            capture_bookmarks: false,
+        }
+    }
+}
+
+/// Counts reported by a finished load.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadSummary {
+    /// Rows sent to the database.
+    pub rows: usize,
+    /// Transactions committed.
+    pub transactions: usize,
+    /// Rows of transactions the server rolled back. Only server-side
+    /// batching continues past a failed transaction; otherwise a failure
+    /// ends the load with an error.
+    pub failed_rows: usize,
+    /// Transactions the server rolled back.
+    pub failed_transactions: usize,
+    /// Distinct error messages of the failed transactions.
+    pub errors: Vec<String>,
+    /// The load was cancelled through its [`LoadHandle`]; the counts cover
+    /// the transactions committed before it stopped.
+    pub cancelled: bool,
+    /// Where the database stood after the load, for later loads to wait
+    /// for; see [`crate::bookmark`]. Empty on Memgraph, and when reading the
+    /// bookmark failed without `capture_bookmarks`.
+    pub bookmarks: Vec<Bookmark>,
+}
+
+impl std::ops::AddAssign for LoadSummary {
+    fn add_assign(&mut self, other: Self) {
+        self.rows += other.rows;
+        self.transactions += other.transactions;
+        self.failed_rows += other.failed_rows;
+        self.failed_transactions += other.failed_transactions;
+        self.cancelled |= other.cancelled;
+        bookmark::merge(&mut self.bookmarks, other.bookmarks);
+        for error in other.errors {
+            if !self.errors.contains(&error) {
+                self.errors.push(error);
+            }
+        }
+    }
+}
+
+/// Label and merge keys of a node load.
+pub(crate) struct NodeSpec<'a> {
+    pub label: &'a str,
+    /// Properties identifying a node. Nodes are merged on these when
+    /// non-empty and created otherwise.
+    pub keys: &'a [String],
+}
+
+/// Column and label mapping of a relationship load.
+pub(crate) struct RelationshipSpec<'a> {
+    pub rel_type: &'a str,
+    pub start_label: &'a str,
+    pub start_id_col: &'a str,
+    pub start_id_prop: &'a str,
+    pub end_label: &'a str,
+    pub end_id_col: &'a str,
+    pub end_id_prop: &'a str,
+    /// Relationship properties to merge on; relationships are created when
+    /// empty.
+    pub keys: &'a [String],
+}
 
 /// Load Parquet data into Neo4j in parallel.
 /// Each row in the Parquet file is mapped to properties of a node with the given label.
+///
+/// `path` may also be a directory or glob pattern; see [`crate::dataset`].
 pub async fn load_parquet_nodes_parallel<P: AsRef<Path>>(
     graph: Graph,
     path: P,
     label: &str,
     concurrency: usize,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let path_buf = path.as_ref().to_path_buf();
-    // Read all rows from parquet in a blocking task
-    let rows: Vec<Row> = tokio::task::spawn_blocking(
-        move || -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
-            let file = std::fs::File::open(path_buf)?;
-            let reader = SerializedFileReader::new(file)?;
-            let iter = reader.get_row_iter(None)?;
-            let mut rows = Vec::new();
-            for row in iter {
-                rows.push(row?);
-            }
-            Ok(rows)
-        },
-    )
-    .await??;
+    load_parquet_dataset_nodes_parallel(graph, path, label, &DatasetOptions::default(), concurrency)
+        .await
+}
+
+/// Load a multi-file Parquet dataset as nodes.
+///
+/// Files are decoded in parallel and written with a single concurrency limit
+/// shared by the whole dataset. Hive partition keys are attached according to
+/// `options`.
+pub async fn load_parquet_dataset_nodes_parallel<P: AsRef<Path>>(
+    graph: Graph,
+    path: P,
+    label: &str,
+    options: &DatasetOptions,
+    concurrency: usize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    NodeLoad::new(path.as_ref(), label)
+        .format(Format::Parquet(options.clone()))
+        .concurrency(concurrency)
+        .run(&graph)
+        .await?;
+    Ok(())
+}
 
-    let semaphore = Arc::new(Semaphore::new(concurrency));
+/// Create or merge one node per record, `batch_size` records per transaction.
+pub(crate) async fn write_nodes(
+    router: Router,
+    records: Vec<Record>,
+    spec: &NodeSpec<'_>,
+    options: &WriteOptions,
+) -> Result<LoadSummary, Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(payload) = options.server_payload {
+        let cypher = router.dialect().write_nodes_in_transactions(
+            spec,
+            options.run_id.is_some(),
+            options.batch_size,
+            options.concurrency,
+        )?;
+        let pacer = options.throttle.clone().map(Pacer::new);
+        let mut summary = LoadSummary::default();
+        let mut records = records.into_iter().peekable();
+        while records.peek().is_some() {
+            let rows: Vec<BoltType> = records.by_ref().take(payload).map(node_row).collect();
+            if let Some(pacer) = &pacer {
+                pacer.admit(rows.len()).await;
+            }
+            if !proceed(options).await {
+                summary.cancelled = true;
+                break;
+            }
+            summary += run_in_transactions(&router, &cypher, rows, options, &summary).await?;
+        }
+        return Ok(summary);
+    }
+    let cypher = Arc::new(router.dialect().write_nodes(spec, options.run_id.is_some()));
+    let controller = Arc::new(Controller::new(
+        options.concurrency,
+        options.batch_size,
+        options.adaptive,
+        options.throttle.clone(),
+    ));
+    let shared = Arc::new(options.clone());
     let mut tasks = FuturesUnordered::new();
+    let mut summary = LoadSummary::default();
 
-    for row in rows {
-        let label = label.to_owned();
-        let graph = graph.clone();
-        let permit = semaphore.clone().acquire_owned().await?;
+    let mut records = records.into_iter().peekable();
+    while records.peek().is_some() {
+        let permit = controller.acquire().await;
+        let rows: Vec<BoltType> = records
+            .by_ref()
+            .take(controller.batch_size())
+            .map(node_row)
+            .collect();
+        controller.admit(rows.len()).await;
+        if !proceed(options).await {
+            summary.cancelled = true;
+            break;
+        }
+        let router = router.clone();
+        let cypher = cypher.clone();
+        let controller = controller.clone();
+        let options = shared.clone();
         tasks.push(tokio::spawn(async move {
             let _permit = permit;
-            let mut map: HashMap<String, BoltType> = HashMap::new();
-            for (key, field) in row.get_column_iter() {
-                let json = field.to_json_value();
-                let bolt: BoltType = json.try_into()?;
-                map.insert(key.clone(), bolt);
-            }
-            let q = query(format!("CREATE (n:{label} $props)").as_str()).param("props", map);
-            graph.run(q).await
+            run_rows(&router, &cypher, rows, &options, &controller).await
         }));
     }
 
     while let Some(res) = tasks.next().await {
-        res??;
+        summary += res??;
     }
-    Ok(())
+    Ok(summary)
+}
+
+/// Wait while the load is paused and tell whether it may send the next
+/// batch.
+async fn proceed(options: &WriteOptions) -> bool {
+    match &options.handle {
+        Some(handle) => handle.proceed().await,
+        None => true,
+    }
+}
+
+/// The `$rows` entry of a node record: its properties as a map.
+fn node_row(record: Record) -> BoltType {
+    record
+        .into_iter()
+        .collect::<HashMap<String, BoltType>>()
+        .into()
+}
+
+/// Run a `CALL { } IN TRANSACTIONS` query with `rows` bound to `$rows` and
+/// count what the server committed.
+///
+/// The payload is sent once and never retried: the server commits its
+/// transactions as it goes, so a payload that fails as a whole, for example
+/// because the connection broke or the leader changed, may already have
+/// committed part of its rows. The error then tells how far the load got
+/// from `done`, the summary of the payloads before this one.
+async fn run_in_transactions(
+    router: &Router,
+    cypher: &str,
+    rows: Vec<BoltType>,
+    options: &WriteOptions,
+    done: &LoadSummary,
+) -> Result<LoadSummary, Box<dyn std::error::Error + Send + Sync>> {
+    let sent = rows.len();
+    let statuses = router
+        .execute_once(
+            rows_query(cypher, rows, options),
+            options.transaction_timeout,
+        )
+        .await
+        .map_err(|e| {
+            format!(
+                "server-side batching stopped after committing {} rows: a payload of {sent} \
+                 rows failed and was not retried, as the server may have committed part of \
+                 it: {e}",
+                done.rows - done.failed_rows
+            )
+        })?
+        .iter()
+        .map(|row| {
+            Ok(TransactionStatus {
+                committed: row.get("committed")?,
+                error: row.get("error")?,
+                rows: row.get::<i64>("rows")? as usize,
+                transactions: row.get::<i64>("transactions")? as usize,
+            })
+        })
+        .collect::<Result<Vec<_>, neo4rs::DeError>>()?;
+    Ok(status_summary(&statuses, sent, options.batch_size))
+}
+
+/// One row of `REPORT STATUS`, aggregated over transactions with the same
+/// outcome.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TransactionStatus {
+    committed: bool,
+    error: Option<String>,
+    rows: usize,
+    transactions: usize,
+}
+
+/// Summarize the status rows of a payload of `sent` rows.
+///
+/// Servers that do not report statuses (Neo4j 4.4 and 5 before 5.7, and
+/// Memgraph) return no rows, and then every sent row is counted as
+/// committed. That only holds because such servers stop at the first failed
+/// transaction and fail the whole query, which [`run_in_transactions`]
+/// raises as an error instead of summarizing.
+fn status_summary(statuses: &[TransactionStatus], sent: usize, batch_size: usize) -> LoadSummary {
+    let mut summary = LoadSummary {
+        rows: sent,
+        ..LoadSummary::default()
+    };
+    if statuses.is_empty() {
+        summary.transactions = sent.div_ceil(batch_size.max(1));
+        return summary;
+    }
+    for status in statuses {
+        if status.committed {
+            summary.transactions += status.transactions;
+            continue;
+        }
+        summary.failed_rows += status.rows;
+        summary.failed_transactions += status.transactions;
+        if let Some(error) = &status.error
+            && !summary.errors.contains(error)
+        {
+            summary.errors.push(error.clone());
+        }
+    }
+    summary
+}
+
+/// `cypher` with `rows` bound to `$rows` and the run id, if any, to `$run`.
+fn rows_query(cypher: &str, rows: Vec<BoltType>, options: &WriteOptions) -> Query {
+    let query = query(cypher).param("rows", rows);
+    match &options.run_id {
+        Some(run_id) => query.param("run", run_id.as_str()),
+        None => query,
+    }
+}
+
+/// Run `cypher` in one transaction with `rows` bound to `$rows`, report how
+/// it went to `controller` and count what was committed.
+async fn run_rows(
+    router: &Router,
+    cypher: &str,
+    rows: Vec<BoltType>,
+    options: &WriteOptions,
+    controller: &Controller,
+) -> Result<LoadSummary, Box<dyn std::error::Error + Send + Sync>> {
+    let count = rows.len();
+    let started = Instant::now();
+    let retries = router
+        .write(
+            rows_query(cypher, rows, options),
+            options.transaction_timeout,
+        )
+        .await?;
+    controller.record(count, started.elapsed(), retries);
+    Ok(LoadSummary {
+        rows: count,
+        transactions: 1,
+        ..LoadSummary::default()
+    })
 }
 
 /// Alias kept for backwards compatibility
@@ -75,6 +385,10 @@ pub async fn load_parquet_parallel<P: AsRef<Path>>(
 /// specify the column names used to read the identifiers from the Parquet file,
 /// while `start_id_prop` and `end_id_prop` specify the property names used to
 /// match existing nodes. Nodes are matched by label and property value.
+///
+/// Rows are scheduled with the [`GreedyColoring`](crate::planner::GreedyColoring) strategy; use
+/// [`load_parquet_relationships_parallel_with_strategy`] to pick another one.
+#[allow(clippy::too_many_arguments)]
 pub async fn load_parquet_relationships_parallel<P: AsRef<Path>>(
     graph: Graph,
     path: P,
@@ -87,139 +401,373 @@ pub async fn load_parquet_relationships_parallel<P: AsRef<Path>>(
     end_id_prop: &str,
     concurrency: usize,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let path_buf = path.as_ref().to_path_buf();
-    // Read all rows from parquet in a blocking task
-    let rows: Vec<Row> = tokio::task::spawn_blocking(
-        move || -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
-            let file = std::fs::File::open(path_buf)?;
-            let reader = SerializedFileReader::new(file)?;
-            let iter = reader.get_row_iter(None)?;
-            let mut rows = Vec::new();
-            for row in iter {
-                rows.push(row?);
-            }
-            Ok(rows)
-        },
-    )
-    .await??;
-
-    // ------------------------------------------------------------------
-    // Build color groups so that relationships within the same color do not
-    // touch the same nodes. Each color can be processed concurrently without
-    // lock conflicts. Colors themselves are processed sequentially.
-    #[derive(Default)]
-    struct ColorBatch {
-        rows: Vec<Row>,
-        used: std::collections::HashSet<String>,
-    }
+    RelationshipLoad::new(path.as_ref(), rel_type)
+        .start(start_label, start_id_col)
+        .start_property(start_id_prop)
+        .end(end_label, end_id_col)
+        .end_property(end_id_prop)
+        .concurrency(concurrency)
+        .run(&graph)
+        .await?;
+    Ok(())
+}
 
-    let mut batches: Vec<ColorBatch> = Vec::new();
+/// Load relationships from a Parquet file using the given [`PartitionStrategy`].
+///
+/// The strategy decides which rows may be written concurrently; see the
+/// [`planner`](crate::planner) module for the available implementations.
+#[allow(clippy::too_many_arguments)]
+pub async fn load_parquet_relationships_parallel_with_strategy<P: AsRef<Path>>(
+    graph: Graph,
+    path: P,
+    rel_type: &str,
+    start_label: &str,
+    start_id_col: &str,
+    start_id_prop: &str,
+    end_label: &str,
+    end_id_col: &str,
+    end_id_prop: &str,
+    concurrency: usize,
+    strategy: impl PartitionStrategy + 'static,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    RelationshipLoad::new(path.as_ref(), rel_type)
+        .start(start_label, start_id_col)
+        .start_property(start_id_prop)
+        .end(end_label, end_id_col)
+        .end_property(end_id_prop)
+        .strategy(strategy)
+        .concurrency(concurrency)
+        .run(&graph)
+        .await?;
+    Ok(())
+}
 
-    for row in rows {
-        let mut start_val = None;
-        let mut end_val = None;
-        for (key, field) in row.get_column_iter() {
-            if key == start_id_col {
-                start_val = Some(field.to_json_value().to_string());
-            } else if key == end_id_col {
-                end_val = Some(field.to_json_value().to_string());
-            }
-        }
-        let start_val = start_val.ok_or_else(|| {
-            std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("missing start id column: {}", start_id_col),
-            )
-        })?;
-        let end_val = end_val.ok_or_else(|| {
-            std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("missing end id column: {}", end_id_col),
-            )
-        })?;
+/// Load a multi-file Parquet dataset as relationships.
+///
+/// Partition keys can only be attached as properties; relationships have no
+/// labels, so `options.partition_labels` must be empty.
+#[allow(clippy::too_many_arguments)]
+pub async fn load_parquet_dataset_relationships_parallel<P: AsRef<Path>>(
+    graph: Graph,
+    path: P,
+    rel_type: &str,
+    start_label: &str,
+    start_id_col: &str,
+    start_id_prop: &str,
+    end_label: &str,
+    end_id_col: &str,
+    end_id_prop: &str,
+    options: &DatasetOptions,
+    concurrency: usize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    RelationshipLoad::new(path.as_ref(), rel_type)
+        .format(Format::Parquet(options.clone()))
+        .start(start_label, start_id_col)
+        .start_property(start_id_prop)
+        .end(end_label, end_id_col)
+        .end_property(end_id_prop)
+        .concurrency(concurrency)
+        .run(&graph)
+        .await?;
+    Ok(())
+}
 
-        let mut placed = false;
-        for batch in &mut batches {
-            if !batch.used.contains(&start_val) && !batch.used.contains(&end_val) {
-                batch.used.insert(start_val.clone());
-                batch.used.insert(end_val.clone());
-                batch.rows.push(row.clone());
-                placed = true;
-                break;
+/// Create or merge one relationship per record, scheduled by `strategy`.
+///
+/// When the batches of a round touch disjoint nodes, they are packed into
+/// transactions of up to `batch_size` rows; see [`pack_round`]. A batch
+/// larger than that is split into consecutive transactions run in order.
+pub(crate) async fn write_relationships(
+    router: Router,
+    records: Vec<Record>,
+    spec: &RelationshipSpec<'_>,
+    options: &WriteOptions,
+    strategy: &dyn PartitionStrategy,
+) -> Result<LoadSummary, Box<dyn std::error::Error + Send + Sync>> {
+    let rows = relationship_rows(records, spec.start_id_col, spec.end_id_col)?;
+    let edges = relationship_edges(&rows, spec.start_label, spec.end_label);
+    let plan = strategy.plan(&edges);
+    let mut rows: Vec<Option<BoltType>> = rows.into_iter().map(|r| Some(r.into())).collect();
+    if let Some(payload) = options.server_payload {
+        // Rows of a disjoint round touch distinct nodes, so the server may
+        // commit them concurrently; rounds still run one after another. The
+        // server splits payloads by row count, so rounds that are not
+        // disjoint may contend as described in the planner.
+        let cypher = router.dialect().write_relationships_in_transactions(
+            spec,
+            options.run_id.is_some(),
+            options.batch_size,
+            options.concurrency,
+        )?;
+        let pacer = options.throttle.clone().map(Pacer::new);
+        let mut summary = LoadSummary::default();
+        for round in plan.rounds {
+            let round_rows: Vec<BoltType> = round
+                .batches
+                .into_iter()
+                .flatten()
+                .filter_map(|i| rows[i].take())
+                .collect();
+            for chunk in round_rows.chunks(payload) {
+                if let Some(pacer) = &pacer {
+                    pacer.admit(chunk.len()).await;
+                }
+                if !proceed(options).await {
+                    summary.cancelled = true;
+                    return Ok(summary);
+                }
+                summary += run_in_transactions(&router, &cypher, chunk.to_vec(), options, &summary)
+                    .await?;
             }
         }
-        if !placed {
-            let mut used = std::collections::HashSet::new();
-            used.insert(start_val.clone());
-            used.insert(end_val.clone());
-            batches.push(ColorBatch {
-                rows: vec![row.clone()],
-                used,
-            });
-        }
+        return Ok(summary);
     }
+    let cypher = Arc::new(
+        router
+            .dialect()
+            .write_relationships(spec, options.run_id.is_some()),
+    );
+    let controller = Arc::new(Controller::new(
+        options.concurrency,
+        options.batch_size,
+        options.adaptive,
+        options.throttle.clone(),
+    ));
+    let shared = Arc::new(options.clone());
+    let mut summary = LoadSummary::default();
 
-    // Process each color sequentially but allow parallelism within the color.
-    for batch in batches {
-        let semaphore = Arc::new(Semaphore::new(concurrency));
+    // Process each round sequentially but allow parallelism within the round.
+    for round in plan.rounds {
+        let batch_size = controller.batch_size();
         let mut tasks = FuturesUnordered::new();
 
-        for row in batch.rows {
-            let graph = graph.clone();
-            let rel_type = rel_type.to_owned();
-            let start_label = start_label.to_owned();
-            let end_label = end_label.to_owned();
-            let start_id_col = start_id_col.to_owned();
-            let start_id_prop = start_id_prop.to_owned();
-            let end_id_col = end_id_col.to_owned();
-            let end_id_prop = end_id_prop.to_owned();
-            let permit = semaphore.clone().acquire_owned().await?;
+        let disjoint = round.is_disjoint(&edges);
+        let packed: Vec<Vec<BoltType>> = pack_round(round, disjoint, batch_size)
+            .into_iter()
+            .map(|task| task.into_iter().filter_map(|i| rows[i].take()).collect())
+            .collect();
+
+        for task_rows in packed {
+            let chunks: Vec<Vec<BoltType>> = task_rows
+                .chunks(batch_size)
+                .map(|chunk| chunk.to_vec())
+                .collect();
+            let permit = controller.acquire().await;
+            controller.admit(task_rows.len()).await;
+            if !proceed(options).await {
+                summary.cancelled = true;
+                break;
+            }
+            let router = router.clone();
+            let cypher = cypher.clone();
+            let controller = controller.clone();
+            let options = shared.clone();
             tasks.push(tokio::spawn(async move {
                 let _permit = permit;
-                let mut props: HashMap<String, BoltType> = HashMap::new();
-                let mut start_id: Option<BoltType> = None;
-                let mut end_id: Option<BoltType> = None;
-                for (key, field) in row.get_column_iter() {
-                    let json = field.to_json_value();
-                    let bolt: BoltType = json.try_into()?;
-                    if key == &start_id_col {
-                        start_id = Some(bolt);
-                    } else if key == &end_id_col {
-                        end_id = Some(bolt);
-                    } else {
-                        props.insert(key.clone(), bolt);
+                let mut committed = LoadSummary::default();
+                for chunk in chunks {
+                    if !proceed(&options).await {
+                        committed.cancelled = true;
+                        break;
                     }
+                    committed += run_rows(&router, &cypher, chunk, &options, &controller).await?;
                 }
-                let start_val = start_id.ok_or_else(|| {
-                    std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("missing start id column: {}", start_id_col),
-                    )
-                })?;
-                let end_val = end_id.ok_or_else(|| {
-                    std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("missing end id column: {}", end_id_col),
-                    )
-                })?;
-                let q = query(
-                    format!(
-                        "MATCH (a:{start_label} {{{start_id_prop}: $start}}) \
-                     MATCH (b:{end_label} {{{end_id_prop}: $end}}) \
-                     CREATE (a)-[r:{rel_type} $props]->(b)"
-                    )
-                    .as_str(),
-                )
-                .param("start", start_val)
-                .param("end", end_val)
-                .param("props", props);
-                graph.run(q).await
+                Ok::<_, Box<dyn std::error::Error + Send + Sync>>(committed)
             }));
         }
 
         while let Some(res) = tasks.next().await {
-            res??;
+            summary += res??;
+        }
+        if summary.cancelled {
+            break;
         }
     }
-    Ok(())
+    Ok(summary)
+}
+
+/// Group the batches of `round` into tasks that run concurrently, each
+/// committing its rows in consecutive transactions of `batch_size` rows.
+///
+/// Batches of a `disjoint` round are packed together until a task holds at
+/// least `batch_size` rows, so rounds of many small batches still commit
+/// full transactions. Otherwise every batch is a task of its own: packing
+/// would put rows of different batches into the same transaction, and two
+/// such transactions could lock the same node, which is the contention the
+/// plan is meant to limit.
+fn pack_round(round: Round, disjoint: bool, batch_size: usize) -> Vec<Vec<usize>> {
+    if !disjoint {
+        return round.batches;
+    }
+    let mut packed = Vec::new();
+    let mut current = Vec::new();
+    for batch in round.batches {
+        current.extend(batch);
+        if current.len() >= batch_size {
+            packed.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        packed.push(current);
+    }
+    packed
+}
+
+/// Compute the execution plan for a relationship file without touching the
+/// database and return its statistics.
+///
+/// Start and end ids are treated as one key space, as if both endpoints had
+/// the same label; use [`plan_parquet_relationships_with_labels`] to plan
+/// with the labels the load matches.
+pub async fn plan_parquet_relationships<P: AsRef<Path>>(
+    path: P,
+    start_id_col: &str,
+    end_id_col: &str,
+    strategy: &dyn PartitionStrategy,
+) -> Result<PlanStats, Box<dyn std::error::Error + Send + Sync>> {
+    plan_parquet_relationships_with_labels(path, "", start_id_col, "", end_id_col, strategy).await
+}
+
+/// Compute the execution plan for a relationship file whose endpoints are
+/// nodes labelled `start_label` and `end_label`, without touching the
+/// database, and return its statistics.
+pub async fn plan_parquet_relationships_with_labels<P: AsRef<Path>>(
+    path: P,
+    start_label: &str,
+    start_id_col: &str,
+    end_label: &str,
+    end_id_col: &str,
+    strategy: &dyn PartitionStrategy,
+) -> Result<PlanStats, Box<dyn std::error::Error + Send + Sync>> {
+    let groups = read_parquet_dataset(path.as_ref(), &DatasetOptions::default(), 1).await?;
+    plan_relationships(
+        flatten_groups(groups),
+        start_label,
+        start_id_col,
+        end_label,
+        end_id_col,
+        strategy,
+    )
+}
+
+/// Compute plan statistics for relationship records.
+pub(crate) fn plan_relationships(
+    records: Vec<Record>,
+    start_label: &str,
+    start_id_col: &str,
+    end_label: &str,
+    end_id_col: &str,
+    strategy: &dyn PartitionStrategy,
+) -> Result<PlanStats, Box<dyn std::error::Error + Send + Sync>> {
+    let rows = relationship_rows(records, start_id_col, end_id_col)?;
+    let edges = relationship_edges(&rows, start_label, end_label);
+    let plan = strategy.plan(&edges);
+    Ok(PlanStats::new(&edges, &plan))
+}
+
+/// A relationship row converted to the parameters of its CREATE query.
+struct RelRow {
+    start: BoltType,
+    end: BoltType,
+    props: HashMap<String, BoltType>,
+}
+
+impl From<RelRow> for BoltType {
+    fn from(row: RelRow) -> Self {
+        let mut map = HashMap::new();
+        map.insert("start".to_string(), row.start);
+        map.insert("end".to_string(), row.end);
+        map.insert("props".to_string(), BoltType::from(row.props));
+        map.into()
+    }
+}
+
+/// Split records into start id, end id and relationship properties.
+fn relationship_rows(
+    records: Vec<Record>,
+    start_id_col: &str,
+    end_id_col: &str,
+) -> Result<Vec<RelRow>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut out = Vec::with_capacity(records.len());
+    for record in records {
+        let mut props: HashMap<String, BoltType> = HashMap::new();
+        let mut start_id: Option<BoltType> = None;
+        let mut end_id: Option<BoltType> = None;
+        for (key, bolt) in record {
+            if key == start_id_col {
+                start_id = Some(bolt);
+            } else if key == end_id_col {
+                end_id = Some(bolt);
+            } else {
+                props.insert(key, bolt);
+            }
+        }
+        let start = start_id.ok_or_else(|| {
+            std::io::Error::other(format!("missing start id column: {}", start_id_col))
+        })?;
+        let end = end_id.ok_or_else(|| {
+            std::io::Error::other(format!("missing end id column: {}", end_id_col))
+        })?;
+        out.push(RelRow { start, end, props });
+    }
+    Ok(out)
+}
+
+/// Derive the conflict keys of every relationship row from its MATCH parameters.
+fn relationship_edges(rows: &[RelRow], start_label: &str, end_label: &str) -> Vec<Edge> {
+    rows.iter()
+        .map(|row| {
+            Edge::new(
+                NodeKey::new(start_label, [&row.start]),
+                NodeKey::new(end_label, [&row.end]),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_transaction_statuses() {
+        let summary = status_summary(&[], 2500, 1000);
+        assert_eq!((summary.rows, summary.transactions), (2500, 3));
+
+        let statuses = [
+            TransactionStatus {
+                committed: true,
+                error: None,
+                rows: 2000,
+                transactions: 2,
+            },
+            TransactionStatus {
+                committed: false,
+                error: Some("Node(1) already exists".into()),
+                rows: 500,
+                transactions: 1,
+            },
+        ];
+        let mut summary = status_summary(&statuses, 2500, 1000);
+        assert_eq!(summary.rows, 2500);
+        assert_eq!(summary.transactions, 2);
+        assert_eq!(summary.failed_rows, 500);
+        assert_eq!(summary.failed_transactions, 1);
+
+        summary += status_summary(&statuses[1..], 500, 1000);
+        assert_eq!(summary.failed_transactions, 2);
+        assert_eq!(summary.errors, ["Node(1) already exists"]);
+    }
+
+    #[test]
+    fn packs_only_disjoint_rounds() {
+        let round = Round {
+            batches: vec![vec![0], vec![1, 2], vec![3], vec![4]],
+        };
+        assert_eq!(
+            pack_round(round.clone(), true, 3),
+            [vec![0, 1, 2], vec![3, 4]]
+        );
+        assert_eq!(pack_round(round.clone(), false, 3), round.batches);
+    }
 }
@@ -1,123 +1,1218 @@
+use clap::{Arg, ArgAction, ArgMatches, Command, value_parser};
 use neo4j_parallel_rust_loader::{
-    Neo4jConfig, connect, load_parquet_nodes_parallel, load_parquet_relationships_parallel,
+    Adaptive, Bookmark, Compression, ConfigOverrides, CsvOptions, DatasetOptions, Doctor, Filter,
+    FilterValue, Format, GreedyColoring, LoadHandle, MixAndBatch, NdjsonOptions, Neo4jConfig,
+    NoPartition, NodeLoad, RelationshipLoad, Router, Secret, SortByStart, Throttle, TimeWindow,
+    bookmark, new_run_id, parse_duration, parse_utc_offset, rollback,
 };
-use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    dotenvy::dotenv().ok();
-    let cfg = Neo4jConfig::from_env()?;
-    let mut args = env::args();
-    let _bin = args.next();
-    let mode = match args.next() {
-        Some(m) => m,
-        None => {
-            eprintln!(
-                "Usage:\n  cargo run -- nodes <path> <label> [concurrency]\n  cargo run -- rels <path> <rel-type> <start-label> <start-col> <end-label> <end-col> [concurrency]"
-            );
-            std::process::exit(1);
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// A load parsed from the command line or a job file.
+struct Job {
+    load: Load,
+    /// Concurrency of the load, which the connection pool must serve.
+    concurrency: usize,
+}
+
+enum Load {
+    Nodes(NodeLoad),
+    Relationships(RelationshipLoad),
+}
+
+fn cli() -> Command {
+    Command::new(env!("CARGO_PKG_NAME"))
+        .about("Load Parquet, CSV, JSON Lines and Arrow IPC data into Neo4j")
+        .long_about(
+            "Load Parquet, CSV, JSON Lines and Arrow IPC data into Neo4j.\n\n\
+             Connection settings are taken from the first of: the connection flags, the \
+             selected profile of the config file, the top level of the config file, and \
+             NEO4J_URI, NEO4J_USERNAME, NEO4J_PASSWORD and NEO4J_DATABASE (also read from \
+             a .env file).",
+        )
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .args(connection_args())
+        .args(run_args())
+        .subcommand(
+            Command::new("nodes")
+                .about("Create or merge one node per input row")
+                .arg(path_arg())
+                .arg(
+                    Arg::new("label")
+                        .long("label")
+                        .value_name("LABEL")
+                        .required(true)
+                        .help("Label of the created nodes"),
+                )
+                .args(write_args())
+                .args(source_args()),
+        )
+        .subcommand(
+            Command::new("rels")
+                .about("Create or merge one relationship per input row")
+                .arg(path_arg())
+                .arg(
+                    Arg::new("type")
+                        .long("type")
+                        .value_name("TYPE")
+                        .required(true)
+                        .help("Relationship type"),
+                )
+                .args(endpoint_args("start"))
+                .args(endpoint_args("end"))
+                .arg(
+                    Arg::new("strategy")
+                        .long("strategy")
+                        .value_name("STRATEGY")
+                        .value_parser(["greedy", "mix-and-batch", "sort-by-start", "none"])
+                        .default_value("greedy")
+                        .help("How rows are scheduled to avoid lock conflicts"),
+                )
+                .arg(
+                    Arg::new("partitions")
+                        .long("partitions")
+                        .value_name("N")
+                        .value_parser(value_parser!(usize))
+                        .help("Number of partitions of the mix-and-batch strategy [default: 8]"),
+                )
+                .args(write_args())
+                .args(source_args()),
+        )
+        .subcommand(
+            Command::new("run")
+                .about("Run the loads described in a JSON job file, in order")
+                .long_about(
+                    "Run the loads described in a JSON job file, in order.\n\n\
+                     The file holds {\"jobs\": [...]}, where every job has a \"command\" of \
+                     \"nodes\" or \"rels\" and the same settings as the subcommand's flags, \
+                     e.g. {\"command\": \"nodes\", \"path\": \"people.csv\", \"label\": \
+                     \"Person\", \"key\": [\"id\"], \"batch-size\": 5000}. Connection and \
+                     run settings such as --uri or --run-id apply to every job and are \
+                     rejected in a job. All jobs are validated before anything is written, and \
+                     each job waits until the cluster's writer has committed the jobs before it.",
+                )
+                .arg(
+                    Arg::new("job-file")
+                        .value_name("JOB_FILE")
+                        .value_parser(value_parser!(PathBuf))
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("doctor")
+                .about("Check the connection, database and privileges, then exit")
+                .long_about(
+                    "Check the connection, database and privileges, then exit.\n\n\
+                     Reports the server version and edition, the database in use and the \
+                     current user's roles, and verifies that the database accepts writes by \
+                     rolling back a test write. With a job file, the writes of every job are \
+                     tried instead and the indexes and constraints its lookups rely on are \
+                     listed. Exits with status 1 if any check fails.",
+                )
+                .arg(
+                    Arg::new("job-file")
+                        .value_name("JOB_FILE")
+                        .value_parser(value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("rollback")
+                .about("Delete everything a tagged run created, in batched transactions")
+                .long_about(
+                    "Delete everything a tagged run created, in batched transactions.\n\n\
+                     Loads started with --run-id or --tag-run store the run id on every node \
+                     and relationship they create and record themselves on a (:LoadRun {id}) \
+                     node. This deletes the run's relationships, then its nodes with any \
+                     relationships attached, and marks the :LoadRun node as rolled back. \
+                     Nodes and relationships that merges only updated are kept as they are.",
+                )
+                .arg(
+                    Arg::new("id")
+                        .value_name("RUN_ID")
+                        .required(true)
+                        .help("Id of the run, as printed when it started"),
+                )
+                .arg(
+                    Arg::new("batch-size")
+                        .long("batch-size")
+                        .value_name("ROWS")
+                        .value_parser(value_parser!(u64).range(1..))
+                        .default_value("1000")
+                        .help("Nodes or relationships deleted per transaction"),
+                ),
+        )
+}
+
+/// Flags tagging every load of the invocation with one run id and
+/// ordering it after earlier loads.
+fn run_args() -> [Arg; 3] {
+    [
+        Arg::new("run-id")
+            .long("run-id")
+            .value_name("ID")
+            .global(true)
+            .help(
+                "Tag created nodes and relationships with this run id and record the run on a \
+                 :LoadRun node, so `rollback ID` can undo it",
+            ),
+        Arg::new("tag-run")
+            .long("tag-run")
+            .action(ArgAction::SetTrue)
+            .global(true)
+            .conflicts_with("run-id")
+            .help("Like --run-id with a generated id, which is printed at the start"),
+        Arg::new("bookmark")
+            .long("bookmark")
+            .value_name("DATABASE:TRANSACTION")
+            .value_parser(value_parser!(Bookmark))
+            .action(ArgAction::Append)
+            .global(true)
+            .help(
+                "Wait until the writer has committed this bookmark, printed by an earlier \
+                 invocation, before loading",
+            ),
+    ]
+}
+
+fn connection_args() -> [Arg; 15] {
+    let global = |id: &'static str, value_name: &'static str, help: &'static str| {
+        Arg::new(id)
+            .long(id)
+            .value_name(value_name)
+            .global(true)
+            .help(help)
+    };
+    [
+        global(
+            "config",
+            "FILE",
+            "Config file [default: $NEO4J_CONFIG, else ./neo4j-loader.toml if present]",
+        )
+        .value_parser(value_parser!(PathBuf)),
+        global(
+            "profile",
+            "NAME",
+            "Config file profile [default: $NEO4J_PROFILE, else the file's default_profile]",
+        ),
+        global("uri", "URI", "Neo4j URI, e.g. neo4j://localhost:7687"),
+        global("username", "USER", "Neo4j user name"),
+        global(
+            "password",
+            "PASSWORD",
+            "Neo4j password; prefer --password-file, as flags are visible to other users",
+        ),
+        global(
+            "password-file",
+            "FILE",
+            "File holding the Neo4j password, such as a mounted secret",
+        )
+        .value_parser(value_parser!(PathBuf)),
+        global(
+            "auth-scheme",
+            "SCHEME",
+            "Authentication scheme [default: basic]",
+        )
+        .value_parser(["basic", "empty-basic"]),
+        global("database", "NAME", "Database to load into"),
+        global(
+            "tls-trust",
+            "MODE",
+            "Verify the server certificate against the system roots, or trust any certificate",
+        )
+        .value_parser(["system", "all"]),
+        global(
+            "tls-ca-file",
+            "FILE",
+            "PEM file of additional CA certificates",
+        )
+        .value_parser(value_parser!(PathBuf)),
+        global(
+            "max-connections",
+            "N",
+            "Connection pool size [default: 16, or the largest concurrency if higher]",
+        )
+        .value_parser(value_parser!(u64).range(1..)),
+        global(
+            "fetch-size",
+            "N",
+            "Records fetched per round trip [default: 200]",
+        )
+        .value_parser(value_parser!(u64).range(1..)),
+        global(
+            "connect-timeout",
+            "DURATION",
+            "Time allowed to connect, e.g. 10s [default: 30s]",
+        )
+        .value_parser(parse_duration),
+        global(
+            "transaction-timeout",
+            "DURATION",
+            "Fail a load when one of its transactions takes longer, e.g. 5m",
+        )
+        .value_parser(parse_duration),
+        global(
+            "dialect",
+            "DIALECT",
+            "Cypher dialect of the server: neo4j-4, neo4j-5, neo4j-5.MINOR or memgraph \
+             [default: detected]",
+        ),
+    ]
+}
+
+fn path_arg() -> Arg {
+    Arg::new("path")
+        .value_name("PATH")
+        .value_parser(value_parser!(PathBuf))
+        .required(true)
+        .help("Input file, Parquet directory or glob; `-` reads an Arrow IPC stream from stdin")
+}
+
+fn endpoint_args(side: &'static str) -> [Arg; 3] {
+    let (label, column, property) = match side {
+        "start" => ("start-label", "start-column", "start-property"),
+        _ => ("end-label", "end-column", "end-property"),
+    };
+    [
+        Arg::new(label)
+            .long(label)
+            .value_name("LABEL")
+            .required(true)
+            .help(format!("Label of the {side} node")),
+        Arg::new(column)
+            .long(column)
+            .value_name("COLUMN")
+            .required(true)
+            .help(format!("Column holding the {side} node identifier")),
+        Arg::new(property)
+            .long(property)
+            .value_name("PROPERTY")
+            .help(format!(
+                "Node property matched against the {side} identifier [default: the column name]"
+            )),
+    ]
+}
+
+fn write_args() -> [Arg; 11] {
+    [
+        Arg::new("key")
+            .long("key")
+            .value_name("PROPERTY")
+            .action(ArgAction::Append)
+            .help("Merge on this property instead of creating; repeat for a composite key"),
+        Arg::new("batch-size")
+            .long("batch-size")
+            .value_name("ROWS")
+            .value_parser(value_parser!(u64).range(1..))
+            .default_value("1000")
+            .help("Rows written per transaction"),
+        Arg::new("concurrency")
+            .long("concurrency")
+            .value_name("N")
+            .value_parser(value_parser!(u64).range(1..))
+            .default_value("4")
+            .help("Maximum number of concurrent transactions"),
+        Arg::new("server-batching")
+            .long("server-batching")
+            .value_name("ROWS")
+            .value_parser(value_parser!(u64).range(1..))
+            .help(
+                "Send ROWS rows per request and let the server commit them in --batch-size \
+                 transactions, --concurrency at a time (CALL { } IN CONCURRENT TRANSACTIONS)",
+            ),
+        Arg::new("adaptive")
+            .long("adaptive")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("server-batching")
+            .help(
+                "Start at --batch-size and --concurrency, grow both while throughput improves \
+                 and halve them on latency spikes, deadlocks or lock timeouts",
+            ),
+        Arg::new("max-concurrency")
+            .long("max-concurrency")
+            .value_name("N")
+            .value_parser(value_parser!(u64).range(1..))
+            .requires("adaptive")
+            .help("Upper bound of --adaptive concurrency [default: 32]"),
+        Arg::new("max-batch-size")
+            .long("max-batch-size")
+            .value_name("ROWS")
+            .value_parser(value_parser!(u64).range(1..))
+            .requires("adaptive")
+            .help("Upper bound of --adaptive rows per transaction [default: 20000]"),
+        Arg::new("max-rows-per-second")
+            .long("max-rows-per-second")
+            .value_name("N")
+            .value_parser(value_parser!(u64).range(1..))
+            .help("Send at most N rows per second"),
+        Arg::new("throttle-concurrency")
+            .long("throttle-concurrency")
+            .value_name("N")
+            .value_parser(value_parser!(u64).range(1..))
+            .help("Run at most N transactions at a time, outside --full-speed windows"),
+        Arg::new("full-speed")
+            .long("full-speed")
+            .value_name("HH:MM-HH:MM")
+            .action(ArgAction::Append)
+            .value_parser(|s: &str| s.parse::<TimeWindow>())
+            .help(
+                "Lift --max-rows-per-second and --throttle-concurrency during this daily \
+                 window; repeat for several windows",
+            ),
+        Arg::new("utc-offset")
+            .long("utc-offset")
+            .value_name("OFFSET")
+            .allow_hyphen_values(true)
+            .value_parser(parse_utc_offset)
+            .requires("full-speed")
+            .help("UTC offset of the --full-speed times, such as +02:00 [default: UTC]"),
+    ]
+}
+
+/// Flags only valid for one input format, with the format's name.
+const FORMAT_FLAGS: &[(&str, &str)] = &[
+    ("no-header", "csv"),
+    ("delimiter", "csv"),
+    ("quote", "csv"),
+    ("escape", "csv"),
+    ("comment", "csv"),
+    ("flatten", "ndjson"),
+    ("flatten-separator", "ndjson"),
+    ("partition-properties", "parquet"),
+    ("partition-label", "parquet"),
+    ("decode-parallelism", "parquet"),
+    ("unordered", "parquet"),
+    ("filter", "parquet"),
+];
+
+fn source_args() -> Vec<Arg> {
+    vec![
+        Arg::new("format")
+            .long("format")
+            .value_name("FORMAT")
+            .value_parser(["parquet", "csv", "ndjson", "ipc"])
+            .help("Input format [default: from the file extension]"),
+        Arg::new("compression")
+            .long("compression")
+            .value_name("CODEC")
+            .value_parser(["auto", "none", "gzip", "zstd"])
+            .help("Compression of CSV and JSON Lines input [default: auto]"),
+        Arg::new("infer-max-records")
+            .long("infer-max-records")
+            .value_name("N")
+            .value_parser(value_parser!(usize))
+            .help("Records sampled to infer CSV and JSON Lines schemas [default: 1000]"),
+        Arg::new("read-batch-size")
+            .long("read-batch-size")
+            .value_name("ROWS")
+            .value_parser(value_parser!(u64).range(1..))
+            .help("Rows decoded per Arrow batch [default: 8192]"),
+        Arg::new("no-header")
+            .long("no-header")
+            .action(ArgAction::SetTrue)
+            .help("CSV: the first line is data, not a header"),
+        Arg::new("delimiter")
+            .long("delimiter")
+            .value_name("CHAR")
+            .value_parser(parse_byte)
+            .help("CSV: field delimiter [default: ,]"),
+        Arg::new("quote")
+            .long("quote")
+            .value_name("CHAR")
+            .value_parser(parse_byte)
+            .help("CSV: quote character [default: \"]"),
+        Arg::new("escape")
+            .long("escape")
+            .value_name("CHAR")
+            .value_parser(parse_byte)
+            .help("CSV: escape character"),
+        Arg::new("comment")
+            .long("comment")
+            .value_name("CHAR")
+            .value_parser(parse_byte)
+            .help("CSV: skip lines starting with this character"),
+        Arg::new("flatten")
+            .long("flatten")
+            .action(ArgAction::SetTrue)
+            .help("JSON Lines: flatten nested objects into separate properties"),
+        Arg::new("flatten-separator")
+            .long("flatten-separator")
+            .value_name("SEP")
+            .help("JSON Lines: separator of flattened property names [default: .]"),
+        Arg::new("partition-properties")
+            .long("partition-properties")
+            .action(ArgAction::SetTrue)
+            .help("Parquet: add Hive partition keys as properties"),
+        Arg::new("partition-label")
+            .long("partition-label")
+            .value_name("KEY")
+            .action(ArgAction::Append)
+            .help("Parquet: add the value of this partition key as a label; repeatable"),
+        Arg::new("decode-parallelism")
+            .long("decode-parallelism")
+            .value_name("N")
+            .value_parser(value_parser!(u64).range(1..))
+            .help("Parquet: row groups decoded at once [default: CPU cores]"),
+        Arg::new("unordered")
+            .long("unordered")
+            .action(ArgAction::SetTrue)
+            .help("Parquet: emit row groups as soon as they are decoded"),
+        Arg::new("filter")
+            .long("filter")
+            .value_name("EXPR")
+            .action(ArgAction::Append)
+            .value_parser(parse_filter)
+            .help(
+                "Parquet: only load rows matching `COLUMN OP VALUE` (OP is one of \
+                 = != < <= > >=) or `COLUMN is [not] null`; repeated filters must all match",
+            ),
+    ]
+}
+
+/// Parse a single ASCII character; `\t` and `tab` stand for a tab.
+fn parse_byte(value: &str) -> Result<u8, String> {
+    match value {
+        "\\t" | "tab" => Ok(b'\t'),
+        _ if value.len() == 1 && value.is_ascii() => Ok(value.as_bytes()[0]),
+        _ => Err(format!("expected a single ASCII character, got `{value}`")),
+    }
+}
+
+/// Parse a filter expression such as `age >= 18`, `name = "Ada"` or
+/// `email is not null`.
+fn parse_filter(expr: &str) -> Result<Filter, String> {
+    let lower = expr.to_ascii_lowercase();
+    if let Some(column) = lower.strip_suffix(" is not null") {
+        return Ok(Filter::is_not_null(expr[..column.len()].trim()));
+    }
+    if let Some(column) = lower.strip_suffix(" is null") {
+        return Ok(Filter::is_null(expr[..column.len()].trim()));
+    }
+    let (index, op) = expr
+        .char_indices()
+        .find_map(|(i, _)| {
+            ["<=", ">=", "!=", "=", "<", ">"]
+                .into_iter()
+                .find(|op| expr[i..].starts_with(op))
+                .map(|op| (i, op))
+        })
+        .ok_or_else(|| {
+            format!("expected `COLUMN OP VALUE` or `COLUMN is [not] null`, got `{expr}`")
+        })?;
+    let column = expr[..index].trim();
+    let value = expr[index + op.len()..].trim();
+    if column.is_empty() || value.is_empty() {
+        return Err(format!("incomplete filter `{expr}`"));
+    }
+    let value = filter_value(value);
+    Ok(match op {
+        "=" => Filter::eq(column, value),
+        "!=" => Filter::not_eq(column, value),
+        "<" => Filter::lt(column, value),
+        "<=" => Filter::lt_eq(column, value),
+        ">" => Filter::gt(column, value),
+        _ => Filter::gt_eq(column, value),
+    })
+}
+
+/// Quoted literals are strings; anything else is a number or boolean if it
+/// parses as one and a string otherwise.
+fn filter_value(value: &str) -> FilterValue {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|v| v.strip_suffix(quote))
+        {
+            return FilterValue::from(inner);
+        }
+    }
+    if let Ok(v) = value.parse::<i64>() {
+        FilterValue::Int(v)
+    } else if let Ok(v) = value.parse::<f64>() {
+        FilterValue::Float(v)
+    } else if let Ok(v) = value.parse::<bool>() {
+        FilterValue::Bool(v)
+    } else {
+        FilterValue::from(value)
+    }
+}
+
+fn usize_arg(m: &ArgMatches, id: &str) -> Option<usize> {
+    m.get_one::<u64>(id).map(|v| *v as usize)
+}
+
+fn format_name(format: &Format) -> &'static str {
+    match format {
+        Format::Parquet(_) => "parquet",
+        Format::Csv(_) => "csv",
+        Format::Ndjson(_) => "ndjson",
+        Format::Ipc => "ipc",
+    }
+}
+
+/// Build the input format from the source flags, rejecting flags that do not
+/// apply to it.
+fn source_format(m: &ArgMatches, path: &Path) -> Result<Format, Error> {
+    let format = match m.get_one::<String>("format").map(String::as_str) {
+        Some("parquet") => Format::Parquet(DatasetOptions::default()),
+        Some("csv") => Format::Csv(CsvOptions::default()),
+        Some("ndjson") => Format::Ndjson(NdjsonOptions::default()),
+        Some(_) => Format::Ipc,
+        None => Format::from_path(path),
+    };
+    let name = format_name(&format);
+    for (flag, owner) in FORMAT_FLAGS {
+        let given = m
+            .value_source(flag)
+            .is_some_and(|s| s == clap::parser::ValueSource::CommandLine);
+        if given && *owner != name {
+            return Err(format!("--{flag} only applies to {owner} input, not {name}").into());
         }
+    }
+    let compression = match m.get_one::<String>("compression").map(String::as_str) {
+        Some("none") => Some(Compression::None),
+        Some("gzip") => Some(Compression::Gzip),
+        Some("zstd") => Some(Compression::Zstd),
+        Some(_) => Some(Compression::Auto),
+        None => None,
     };
-    let graph = connect(&cfg).await?;
-    if mode == "nodes" {
-        let path = match args.next() {
-            Some(p) => p,
-            None => {
-                eprintln!("Usage: cargo run -- nodes <path> <label> [concurrency]");
-                std::process::exit(1);
+    if compression.is_some() && !matches!(format, Format::Csv(_) | Format::Ndjson(_)) {
+        return Err(
+            format!("--compression only applies to csv and ndjson input, not {name}").into(),
+        );
+    }
+    if m.contains_id("infer-max-records") && !matches!(format, Format::Csv(_) | Format::Ndjson(_)) {
+        return Err(format!(
+            "--infer-max-records only applies to csv and ndjson input, not {name}"
+        )
+        .into());
+    }
+    let infer_max_records = m.get_one::<usize>("infer-max-records").copied();
+    let read_batch_size = usize_arg(m, "read-batch-size");
+
+    Ok(match format {
+        Format::Parquet(mut options) => {
+            options.partition_properties = m.get_flag("partition-properties");
+            options.partition_labels = m
+                .get_many::<String>("partition-label")
+                .map(|v| v.cloned().collect())
+                .unwrap_or_default();
+            if let Some(n) = usize_arg(m, "decode-parallelism") {
+                options.decode_parallelism = n;
             }
-        };
-        let label = match args.next() {
-            Some(l) => l,
-            None => {
-                eprintln!("Usage: cargo run -- nodes <path> <label> [concurrency]");
-                std::process::exit(1);
+            options.preserve_order = !m.get_flag("unordered");
+            if let Some(n) = read_batch_size {
+                options.batch_size = n;
             }
-        };
-        let concurrency: usize = args
-            .next()
-            .unwrap_or_else(|| "4".to_string())
-            .parse()
-            .unwrap_or(4);
-        load_parquet_nodes_parallel(graph, path, &label, concurrency).await?;
-    } else if mode == "rels" {
-        let path = match args.next() {
-            Some(p) => p,
-            None => {
-                eprintln!(
-                    "Usage: cargo run -- rels <path> <rel-type> <start-label> <start-col> <end-label> <end-col> [concurrency]"
-                );
-                std::process::exit(1);
+            let mut filters: Vec<Filter> = m
+                .get_many::<Filter>("filter")
+                .map(|v| v.cloned().collect())
+                .unwrap_or_default();
+            options.filter = match filters.len() {
+                0 => None,
+                1 => filters.pop(),
+                _ => Some(Filter::And(filters)),
+            };
+            Format::Parquet(options)
+        }
+        Format::Csv(mut options) => {
+            options.has_header = !m.get_flag("no-header");
+            if let Some(&b) = m.get_one::<u8>("delimiter") {
+                options.delimiter = b;
             }
-        };
-        let rel_type = match args.next() {
-            Some(l) => l,
-            None => {
-                eprintln!(
-                    "Usage: cargo run -- rels <path> <rel-type> <start-label> <start-col> <end-label> <end-col> [concurrency]"
-                );
-                std::process::exit(1);
+            if let Some(&b) = m.get_one::<u8>("quote") {
+                options.quote = b;
             }
-        };
-        let start_label = match args.next() {
-            Some(l) => l,
-            None => {
-                eprintln!(
-                    "Usage: cargo run -- rels <path> <rel-type> <start-label> <start-col> <end-label> <end-col> [concurrency]"
-                );
-                std::process::exit(1);
+            options.escape = m.get_one::<u8>("escape").copied();
+            options.comment = m.get_one::<u8>("comment").copied();
+            if let Some(c) = compression {
+                options.compression = c;
             }
-        };
-        let start_col = match args.next() {
-            Some(c) => c,
-            None => {
-                eprintln!(
-                    "Usage: cargo run -- rels <path> <rel-type> <start-label> <start-col> <end-label> <end-col> [concurrency]"
-                );
-                std::process::exit(1);
+            if infer_max_records.is_some() {
+                options.infer_max_records = infer_max_records;
+            }
+            if let Some(n) = read_batch_size {
+                options.batch_size = n;
+            }
+            Format::Csv(options)
+        }
+        Format::Ndjson(mut options) => {
+            options.flatten = m.get_flag("flatten");
+            if let Some(sep) = m.get_one::<String>("flatten-separator") {
+                options.flatten_separator = sep.clone();
+            }
+            if let Some(c) = compression {
+                options.compression = c;
+            }
+            if infer_max_records.is_some() {
+                options.infer_max_records = infer_max_records;
             }
+            if let Some(n) = read_batch_size {
+                options.batch_size = n;
+            }
+            Format::Ndjson(options)
+        }
+        Format::Ipc => {
+            if read_batch_size.is_some() {
+                return Err("--read-batch-size does not apply to ipc input".into());
+            }
+            Format::Ipc
+        }
+    })
+}
+
+/// Limits of `--adaptive`, if given.
+fn adaptive(m: &ArgMatches) -> Option<Adaptive> {
+    if !m.get_flag("adaptive") {
+        return None;
+    }
+    let defaults = Adaptive::default();
+    Some(Adaptive {
+        max_concurrency: usize_arg(m, "max-concurrency").unwrap_or(defaults.max_concurrency),
+        max_batch_size: usize_arg(m, "max-batch-size").unwrap_or(defaults.max_batch_size),
+        ..defaults
+    })
+}
+
+/// The throttle given by `--max-rows-per-second`, `--throttle-concurrency`
+/// and `--full-speed`, if any.
+fn throttle(m: &ArgMatches) -> Result<Option<Throttle>, Error> {
+    let throttle = Throttle {
+        rows_per_second: m.get_one::<u64>("max-rows-per-second").copied(),
+        max_concurrency: usize_arg(m, "throttle-concurrency"),
+        full_speed: m
+            .get_many::<TimeWindow>("full-speed")
+            .map(|w| w.copied().collect())
+            .unwrap_or_default(),
+        utc_offset_minutes: m.get_one::<i32>("utc-offset").copied().unwrap_or(0),
+    };
+    if throttle.rows_per_second.is_none() && throttle.max_concurrency.is_none() {
+        if !throttle.full_speed.is_empty() {
+            return Err(
+                "--full-speed needs --max-rows-per-second or --throttle-concurrency".into(),
+            );
+        }
+        return Ok(None);
+    }
+    Ok(Some(throttle))
+}
+
+fn keys(m: &ArgMatches) -> Vec<String> {
+    m.get_many::<String>("key")
+        .map(|v| v.cloned().collect())
+        .unwrap_or_default()
+}
+
+fn node_load(m: &ArgMatches) -> Result<NodeLoad, Error> {
+    let path = m.get_one::<PathBuf>("path").expect("required");
+    let mut load = NodeLoad::new(path, m.get_one::<String>("label").expect("required"))
+        .format(source_format(m, path)?)
+        .batch_size(usize_arg(m, "batch-size").expect("defaulted"))
+        .concurrency(usize_arg(m, "concurrency").expect("defaulted"));
+    if let Some(payload) = usize_arg(m, "server-batching") {
+        load = load.server_batching(payload);
+    }
+    if let Some(limits) = adaptive(m) {
+        load = load.adaptive(limits);
+    }
+    if let Some(throttle) = throttle(m)? {
+        load = load.throttle(throttle);
+    }
+    for key in keys(m) {
+        load = load.key(key);
+    }
+    Ok(load)
+}
+
+fn relationship_load(m: &ArgMatches) -> Result<RelationshipLoad, Error> {
+    let path = m.get_one::<PathBuf>("path").expect("required");
+    let arg = |id: &str| m.get_one::<String>(id).expect("required").as_str();
+    let mut load = RelationshipLoad::new(path, arg("type"))
+        .format(source_format(m, path)?)
+        .start(arg("start-label"), arg("start-column"))
+        .end(arg("end-label"), arg("end-column"))
+        .batch_size(usize_arg(m, "batch-size").expect("defaulted"))
+        .concurrency(usize_arg(m, "concurrency").expect("defaulted"));
+    if let Some(payload) = usize_arg(m, "server-batching") {
+        load = load.server_batching(payload);
+    }
+    if let Some(limits) = adaptive(m) {
+        load = load.adaptive(limits);
+    }
+    if let Some(throttle) = throttle(m)? {
+        load = load.throttle(throttle);
+    }
+    if let Some(property) = m.get_one::<String>("start-property") {
+        load = load.start_property(property);
+    }
+    if let Some(property) = m.get_one::<String>("end-property") {
+        load = load.end_property(property);
+    }
+    for key in keys(m) {
+        load = load.key(key);
+    }
+    let strategy = arg("strategy");
+    let partitions = m.get_one::<usize>("partitions").copied();
+    if partitions.is_some() && strategy != "mix-and-batch" {
+        return Err("--partitions only applies to the mix-and-batch strategy".into());
+    }
+    Ok(match strategy {
+        "greedy" => load.strategy(GreedyColoring),
+        "mix-and-batch" => match partitions {
+            Some(0) => return Err("--partitions must be at least 1".into()),
+            Some(n) => load.strategy(MixAndBatch::new(n)),
+            None => load.strategy(MixAndBatch::default()),
+        },
+        "sort-by-start" => load.strategy(SortByStart),
+        _ => load.strategy(NoPartition),
+    })
+}
+
+/// Turn one job of a job file into the arguments of the matching subcommand,
+/// so job files accept exactly the flags of the command line.
+///
+/// Connection and run settings such as `uri` or `run-id` apply to the whole
+/// invocation, so a job giving them is rejected rather than silently run
+/// against the invocation's database and run.
+fn job_args(job: &serde_json::Value) -> Result<Vec<String>, Error> {
+    let object = job.as_object().ok_or("every job must be a JSON object")?;
+    let command = object
+        .get("command")
+        .and_then(|c| c.as_str())
+        .ok_or("every job needs a \"command\" of \"nodes\" or \"rels\"")?;
+    let cli = cli();
+    let subcommand = cli
+        .find_subcommand(command)
+        .filter(|_| matches!(command, "nodes" | "rels"))
+        .ok_or_else(|| format!("unknown command \"{command}\"; expected \"nodes\" or \"rels\""))?;
+    let mut args = vec![env!("CARGO_PKG_NAME").to_string(), command.to_string()];
+    for (name, value) in object {
+        if name == "command" || name == "path" {
+            continue;
+        }
+        let long = name.replace('_', "-");
+        if !subcommand
+            .get_arguments()
+            .any(|arg| arg.get_long() == Some(long.as_str()) && !arg.is_global_set())
+        {
+            return Err(format!(
+                "\"{name}\" is not a setting of `{command}`; connection and run settings \
+                 apply to every job and must be given on the command line or in the config file"
+            )
+            .into());
+        }
+        let flag = format!("--{long}");
+        let values = match value {
+            serde_json::Value::Array(values) => values.clone(),
+            other => vec![other.clone()],
         };
-        let end_label = match args.next() {
-            Some(l) => l,
-            None => {
-                eprintln!(
-                    "Usage: cargo run -- rels <path> <rel-type> <start-label> <start-col> <end-label> <end-col> [concurrency]"
-                );
-                std::process::exit(1);
+        for value in values {
+            match value {
+                serde_json::Value::Bool(true) => args.push(flag.clone()),
+                serde_json::Value::Bool(false) => {}
+                serde_json::Value::String(s) => args.push(format!("{flag}={s}")),
+                serde_json::Value::Number(n) => args.push(format!("{flag}={n}")),
+                _ => return Err(format!("unsupported value for \"{name}\"").into()),
+            }
+        }
+    }
+    // The path goes after `--` so it is never mistaken for a flag.
+    if let Some(path) = object.get("path") {
+        let path = path.as_str().ok_or("\"path\" must be a string")?;
+        args.push("--".to_string());
+        args.push(path.to_string());
+    }
+    Ok(args)
+}
+
+fn job(name: &str, m: &ArgMatches) -> Result<Job, Error> {
+    let load = match name {
+        "nodes" => Load::Nodes(node_load(m)?),
+        "rels" => Load::Relationships(relationship_load(m)?),
+        _ => return Err(format!("unknown job command `{name}`").into()),
+    };
+    let concurrency = usize_arg(m, "concurrency").expect("defaulted");
+    Ok(Job {
+        load,
+        concurrency: adaptive(m).map_or(concurrency, |a| a.max_concurrency.max(concurrency)),
+    })
+}
+
+fn job_file(path: &Path) -> Result<Vec<Job>, Error> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read job file {}: {e}", path.display()))?;
+    let file: serde_json::Value = serde_json::from_str(&text)
+        .map_err(|e| format!("invalid job file {}: {e}", path.display()))?;
+    let jobs = file
+        .get("jobs")
+        .and_then(|j| j.as_array())
+        .ok_or("a job file must hold {\"jobs\": [...]}")?;
+    let mut out = Vec::with_capacity(jobs.len());
+    for (i, value) in jobs.iter().enumerate() {
+        let context = |e: &dyn std::fmt::Display| format!("job {}: {e}", i + 1);
+        let args = job_args(value).map_err(|e| context(&e))?;
+        let matches = cli()
+            .try_get_matches_from(&args)
+            .map_err(|e| context(&e.render()))?;
+        let (name, m) = matches
+            .subcommand()
+            .ok_or_else(|| context(&"missing command"))?;
+        out.push(job(name, m).map_err(|e| context(&e))?);
+    }
+    Ok(out)
+}
+
+/// Cancel `handle` on the first SIGINT or SIGTERM, so loads stop sending
+/// batches and wait for in-flight transactions, and exit on the second.
+async fn cancel_on_signal(handle: LoadHandle) -> std::io::Result<()> {
+    shutdown_signal().await?;
+    eprintln!("cancelling: waiting for in-flight transactions; signal again to abort them");
+    handle.cancel();
+    shutdown_signal().await?;
+    std::process::exit(130);
+}
+
+#[cfg(unix)]
+async fn shutdown_signal() -> std::io::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result,
+        _ = terminate.recv() => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+async fn shutdown_signal() -> std::io::Result<()> {
+    tokio::signal::ctrl_c().await
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    dotenvy::dotenv().ok();
+    let matches = cli().get_matches();
+    let jobs = match matches.subcommand() {
+        Some(("run", m)) => job_file(m.get_one::<PathBuf>("job-file").expect("required"))?,
+        Some(("doctor", m)) => match m.get_one::<PathBuf>("job-file") {
+            Some(path) => job_file(path)?,
+            None => Vec::new(),
+        },
+        Some(("rollback", _)) => Vec::new(),
+        Some((name, m)) => vec![job(name, m)?],
+        None => unreachable!("a subcommand is required"),
+    };
+
+    let arg = |id: &str| matches.get_one::<String>(id).cloned();
+    let overrides = ConfigOverrides {
+        uri: arg("uri"),
+        username: arg("username"),
+        password: arg("password").map(Secret::from),
+        password_file: matches.get_one::<PathBuf>("password-file").cloned(),
+        auth_scheme: arg("auth-scheme").map(|s| s.parse()).transpose()?,
+        tls_trust: arg("tls-trust").map(|t| t.parse()).transpose()?,
+        tls_ca_file: matches.get_one::<PathBuf>("tls-ca-file").cloned(),
+        database: arg("database"),
+        max_connections: usize_arg(&matches, "max-connections"),
+        fetch_size: usize_arg(&matches, "fetch-size"),
+        connect_timeout: matches.get_one::<Duration>("connect-timeout").copied(),
+        transaction_timeout: matches.get_one::<Duration>("transaction-timeout").copied(),
+        dialect: arg("dialect").map(|d| d.parse()).transpose()?,
+    };
+    let cfg = Neo4jConfig::load(
+        matches.get_one::<PathBuf>("config").map(PathBuf::as_path),
+        arg("profile").as_deref(),
+        &overrides,
+    )?
+    .with_concurrency(jobs.iter().map(|j| j.concurrency).max().unwrap_or(1))?;
+    if let Some(("doctor", _)) = matches.subcommand() {
+        let doctor = jobs
+            .iter()
+            .fold(Doctor::new(), |doctor, job| match &job.load {
+                Load::Nodes(load) => doctor.node_load(load),
+                Load::Relationships(load) => doctor.relationship_load(load),
+            });
+        let report = doctor.run(&cfg).await;
+        print!("{report}");
+        if !report.is_healthy() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let router = Router::connect(&cfg).await?;
+    if let Some(("rollback", m)) = matches.subcommand() {
+        let id = m.get_one::<String>("id").expect("required");
+        let deleted = rollback(&router, id, usize_arg(m, "batch-size").expect("defaulted")).await?;
+        eprintln!(
+            "rolled back run {id}: deleted {} relationships and {} nodes",
+            deleted.relationships, deleted.nodes
+        );
+        return Ok(());
+    }
+    let run_id = match arg("run-id") {
+        Some(id) => Some(id),
+        None => matches.get_flag("tag-run").then(new_run_id),
+    };
+    if let Some(id) = &run_id {
+        eprintln!("run id {id}");
+    }
+    let handle = LoadHandle::new();
+    tokio::spawn(cancel_on_signal(handle.clone()));
+    // Each job waits for everything the jobs before it committed.
+    let mut bookmarks: Vec<Bookmark> = matches
+        .get_many::<Bookmark>("bookmark")
+        .map(|b| b.cloned().collect())
+        .unwrap_or_default();
+    let last = jobs.len().saturating_sub(1);
+    for (index, job) in jobs.into_iter().enumerate() {
+        // A later job can only wait for this one with its bookmark.
+        let chained = index < last;
+        let result = match job.load {
+            Load::Nodes(mut load) => {
+                if let Some(timeout) = cfg.transaction_timeout {
+                    load = load.transaction_timeout(timeout);
+                }
+                if let Some(id) = &run_id {
+                    load = load.run_id(id);
+                }
+                load.bookmarks(bookmarks.clone())
+                    .capture_bookmarks(chained)
+                    .handle(handle.clone())
+                    .run(&router)
+                    .await
+            }
+            Load::Relationships(mut load) => {
+                if let Some(timeout) = cfg.transaction_timeout {
+                    load = load.transaction_timeout(timeout);
+                }
+                if let Some(id) = &run_id {
+                    load = load.run_id(id);
+                }
+                load.bookmarks(bookmarks.clone())
+                    .capture_bookmarks(chained)
+                    .handle(handle.clone())
+                    .run(&router)
+                    .await
             }
         };
-        let end_col = match args.next() {
-            Some(c) => c,
-            None => {
-                eprintln!(
-                    "Usage: cargo run -- rels <path> <rel-type> <start-label> <start-col> <end-label> <end-col> [concurrency]"
-                );
-                std::process::exit(1);
+        // Point at the way back from partial data.
+        let hint = |error: String| -> Error {
+            match &run_id {
+                Some(id) => format!("{error}; undo the run with `rollback {id}`").into(),
+                None => error.into(),
             }
         };
-        let concurrency: usize = args
-            .next()
-            .unwrap_or_else(|| "4".to_string())
-            .parse()
-            .unwrap_or(4);
-        load_parquet_relationships_parallel(
-            graph,
-            path,
-            &rel_type,
-            &start_label,
-            &start_col,
-            &start_col,
-            &end_label,
-            &end_col,
-            &end_col,
-            concurrency,
-        )
-        .await?;
-    } else {
+        let summary = result.map_err(|e| hint(e.to_string()))?;
         eprintln!(
-            "Usage:\n  cargo run -- nodes <path> <label> [concurrency]\n  cargo run -- rels <path> <rel-type> <start-label> <start-col> <end-label> <end-col> [concurrency]"
+            "loaded {} rows in {} transactions",
+            summary.rows, summary.transactions
         );
-        std::process::exit(1);
+        if summary.cancelled {
+            return Err(hint(
+                "load cancelled; the rows above were committed".to_string(),
+            ));
+        }
+        if summary.failed_transactions > 0 {
+            return Err(hint(format!(
+                "{} rows in {} transactions failed: {}",
+                summary.failed_rows,
+                summary.failed_transactions,
+                summary.errors.join("; ")
+            )));
+        }
+        bookmark::merge(&mut bookmarks, summary.bookmarks);
+    }
+    for bookmark in &bookmarks {
+        eprintln!("bookmark {bookmark}");
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Job, Error> {
+        let matches = cli().try_get_matches_from(args)?;
+        let (name, m) = matches.subcommand().unwrap();
+        job(name, m)
+    }
+
+    #[test]
+    fn parses_filters() {
+        assert_eq!(parse_filter("age >= 18").unwrap(), Filter::gt_eq("age", 18));
+        assert_eq!(
+            parse_filter("name='Ada'").unwrap(),
+            Filter::eq("name", "Ada")
+        );
+        assert_eq!(
+            parse_filter("score < 1.5").unwrap(),
+            Filter::lt("score", 1.5)
+        );
+        assert_eq!(
+            parse_filter("email IS NOT NULL").unwrap(),
+            Filter::is_not_null("email")
+        );
+        assert!(parse_filter("age").is_err());
+    }
+
+    #[test]
+    fn validates_arguments() {
+        let bin = env!("CARGO_PKG_NAME");
+        assert!(parse(&[bin, "nodes", "a.csv", "--label", "P", "--delimiter", ";"]).is_ok());
+        assert!(
+            parse(&[
+                bin,
+                "nodes",
+                "a.parquet",
+                "--label",
+                "P",
+                "--delimiter",
+                ";"
+            ])
+            .is_err()
+        );
+        assert!(parse(&[bin, "nodes", "a.csv", "--label", "P", "--concurrency", "x"]).is_err());
+        assert!(parse(&[bin, "nodes", "a.csv", "--label", "P", "--concurrency", "0"]).is_err());
+        assert!(
+            parse(&[
+                bin,
+                "nodes",
+                "a.csv",
+                "--label",
+                "P",
+                "--server-batching",
+                "0"
+            ])
+            .is_err()
+        );
+        assert!(
+            parse(&[
+                bin,
+                "nodes",
+                "a.csv",
+                "--label",
+                "P",
+                "--server-batching",
+                "50000"
+            ])
+            .is_ok()
+        );
+        let adaptive = [bin, "nodes", "a.csv", "--label", "P", "--adaptive"];
+        assert!(parse(&adaptive).is_ok());
+        assert!(parse(&[&adaptive[..], &["--max-concurrency", "64"]].concat()).is_ok());
+        assert!(parse(&[&adaptive[..], &["--server-batching", "50000"]].concat()).is_err());
+        assert!(
+            parse(&[
+                bin,
+                "nodes",
+                "a.csv",
+                "--label",
+                "P",
+                "--max-batch-size",
+                "9"
+            ])
+            .is_err()
+        );
+        assert!(
+            cli()
+                .try_get_matches_from([bin, "--connect-timeout", "10s", "run", "jobs.json"])
+                .is_ok()
+        );
+        assert!(
+            cli()
+                .try_get_matches_from([bin, "rollback", "run-1", "--batch-size", "500"])
+                .is_ok()
+        );
+        assert!(cli().try_get_matches_from([bin, "rollback"]).is_err());
+        assert!(
+            cli()
+                .try_get_matches_from([bin, "--tag-run", "--run-id", "x", "run", "jobs.json"])
+                .is_err()
+        );
+        assert!(
+            cli()
+                .try_get_matches_from([bin, "--connect-timeout", "soon", "run", "jobs.json"])
+                .is_err()
+        );
+        let matches = cli()
+            .try_get_matches_from([bin, "--bookmark", "neo4j:7", "run", "jobs.json"])
+            .unwrap();
+        assert_eq!(
+            matches.get_one::<Bookmark>("bookmark").unwrap().to_string(),
+            "neo4j:7"
+        );
+        assert!(
+            cli()
+                .try_get_matches_from([bin, "--bookmark", "neo4j", "run", "jobs.json"])
+                .is_err()
+        );
+        assert!(
+            parse(&[
+                bin,
+                "rels",
+                "r.parquet",
+                "--type",
+                "KNOWS",
+                "--start-label",
+                "P",
+                "--start-column",
+                "src",
+                "--end-label",
+                "P",
+                "--end-column",
+                "dst",
+                "--partitions",
+                "4",
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn converts_jobs_to_arguments() {
+        let job = serde_json::json!({
+            "command": "rels",
+            "path": "-x.csv",
+            "type": "KNOWS",
+            "key": ["since"],
+            "batch_size": 10,
+            "no-header": true,
+            "flatten": false,
+        });
+        let args = job_args(&job).unwrap();
+        assert_eq!(args[1], "rels");
+        assert_eq!(&args[args.len() - 2..], ["--", "-x.csv"]);
+        assert!(args.contains(&"--batch-size=10".to_string()));
+        assert!(args.contains(&"--key=since".to_string()));
+        assert!(args.contains(&"--no-header".to_string()));
+        assert!(!args.iter().any(|a| a.contains("flatten")));
+    }
+
+    #[test]
+    fn rejects_global_settings_in_jobs() {
+        let path = std::env::temp_dir().join(format!("{}-jobs.json", std::process::id()));
+        let jobs = serde_json::json!({"jobs": [
+            {"command": "nodes", "path": "a.csv", "label": "P"},
+            {"command": "nodes", "path": "b.csv", "label": "P", "uri": "bolt://other"},
+        ]});
+        std::fs::write(&path, jobs.to_string()).unwrap();
+        let err = job_file(&path).err().unwrap().to_string();
+        std::fs::remove_file(&path).ok();
+        assert!(err.starts_with("job 2: \"uri\""), "{err}");
+
+        for key in [
+            "password",
+            "config",
+            "run_id",
+            "transaction-timeout",
+            "tag-run",
+        ] {
+            let job = serde_json::json!({"command": "rels", "type": "KNOWS", key: "x"});
+            let err = job_args(&job).unwrap_err().to_string();
+            assert!(err.contains(&format!("\"{key}\"")), "{err}");
+        }
+        assert!(job_args(&serde_json::json!({"command": "run", "path": "jobs.json"})).is_err());
+    }
+}
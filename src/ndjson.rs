@@ -0,0 +1,161 @@
+//! JSON Lines (NDJSON) sources.
+//!
+//! Each line holds one JSON object. Files are decoded with [`arrow::json`] and
+//! written through the same path as the Parquet loaders.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use arrow::datatypes::SchemaRef;
+use arrow::json::ReaderBuilder;
+use arrow::json::reader::infer_json_schema;
+use neo4rs::Graph;
+
+use crate::convert::{batch_to_records, flatten_structs};
+use crate::job::{Format, NodeLoad, RelationshipLoad};
+use crate::loader::Record;
+use crate::source::{Compression, open_decompressed};
+
+/// Options controlling how an NDJSON file is decoded.
+#[derive(Debug, Clone)]
+pub struct NdjsonOptions {
+    /// Explicit schema. When `None` the schema is inferred from the file.
+    pub schema: Option<SchemaRef>,
+    /// Number of lines sampled for schema inference; `None` reads them all.
+    pub infer_max_records: Option<usize>,
+    /// Flatten nested objects into separate properties. Neo4j properties
+    /// cannot hold maps, so nested objects must be flattened to be stored.
+    pub flatten: bool,
+    /// Separator between the parent and child names of flattened fields.
+    pub flatten_separator: String,
+    pub compression: Compression,
+    /// Number of lines decoded per Arrow batch.
+    pub batch_size: usize,
+}
+
+impl Default for NdjsonOptions {
+    fn default() -> Self {
+        Self {
+            schema: None,
+            infer_max_records: Some(1000),
+            flatten: false,
+            flatten_separator: ".".to_string(),
+            compression: Compression::Auto,
+            batch_size: 8192,
+        }
+    }
+}
+
+/// Load an NDJSON file into Neo4j in parallel.
+/// Each object is mapped to properties of a node with the given label.
+pub async fn load_ndjson_nodes_parallel<P: AsRef<Path>>(
+    graph: Graph,
+    path: P,
+    label: &str,
+    options: &NdjsonOptions,
+    concurrency: usize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    NodeLoad::new(path.as_ref(), label)
+        .format(Format::Ndjson(options.clone()))
+        .concurrency(concurrency)
+        .run(&graph)
+        .await?;
+    Ok(())
+}
+
+/// Load relationships from an NDJSON file in parallel.
+///
+/// Fields are mapped exactly as in
+/// [`load_parquet_relationships_parallel`](crate::load_parquet_relationships_parallel);
+/// with flattening enabled the id columns may name nested fields, e.g.
+/// `"from.id"`.
+#[allow(clippy::too_many_arguments)]
+pub async fn load_ndjson_relationships_parallel<P: AsRef<Path>>(
+    graph: Graph,
+    path: P,
+    rel_type: &str,
+    start_label: &str,
+    start_id_col: &str,
+    start_id_prop: &str,
+    end_label: &str,
+    end_id_col: &str,
+    end_id_prop: &str,
+    options: &NdjsonOptions,
+    concurrency: usize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    RelationshipLoad::new(path.as_ref(), rel_type)
+        .format(Format::Ndjson(options.clone()))
+        .start(start_label, start_id_col)
+        .start_property(start_id_prop)
+        .end(end_label, end_id_col)
+        .end_property(end_id_prop)
+        .concurrency(concurrency)
+        .run(&graph)
+        .await?;
+    Ok(())
+}
+
+/// Decode an NDJSON file in a blocking task.
+pub(crate) async fn read_ndjson_records(
+    path: PathBuf,
+    options: NdjsonOptions,
+) -> Result<Vec<Record>, Box<dyn std::error::Error + Send + Sync>> {
+    tokio::task::spawn_blocking(move || read_ndjson_records_blocking(&path, &options)).await?
+}
+
+fn read_ndjson_records_blocking(
+    path: &Path,
+    options: &NdjsonOptions,
+) -> Result<Vec<Record>, Box<dyn std::error::Error + Send + Sync>> {
+    let schema = match &options.schema {
+        Some(schema) => schema.clone(),
+        None => {
+            let reader = open_decompressed(path, options.compression)?;
+            let (schema, _) = infer_json_schema(reader, options.infer_max_records)?;
+            Arc::new(schema)
+        }
+    };
+    let reader = ReaderBuilder::new(schema)
+        .with_batch_size(options.batch_size)
+        .build(open_decompressed(path, options.compression)?)?;
+
+    let mut records = Vec::new();
+    for batch in reader {
+        let mut batch = batch?;
+        if options.flatten {
+            batch = flatten_structs(&batch, &options.flatten_separator)?;
+        }
+        records.extend(batch_to_records(&batch)?);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neo4rs::BoltType;
+
+    #[test]
+    fn reads_and_flattens_objects() {
+        let path = std::env::temp_dir().join(format!("{}-events.ndjson", std::process::id()));
+        std::fs::write(
+            &path,
+            "{\"id\": 1, \"user\": {\"name\": \"ann\"}}\n{\"id\": 2, \"user\": {\"name\": \"bob\"}}\n",
+        )
+        .unwrap();
+        let options = NdjsonOptions {
+            flatten: true,
+            flatten_separator: "_".to_string(),
+            ..NdjsonOptions::default()
+        };
+        let records = read_ndjson_records_blocking(&path, &options).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1][0], ("id".to_string(), BoltType::from(2i64)));
+        assert_eq!(
+            records[1][1],
+            ("user_name".to_string(), BoltType::from("bob"))
+        );
+    }
+}
@@ -1,18 +1,130 @@
-use crate::config::Neo4jConfig;
+use crate::config::{Auth, DEFAULT_CONNECT_TIMEOUT, Neo4jConfig, TlsConfig, TrustMode};
 use neo4rs::{ConfigBuilder, Graph};
 use rustls::crypto::ring;
 
 /// Create a [`Graph`] connection using the provided config.
-pub async fn connect(config: &Neo4jConfig) -> Result<Graph, neo4rs::Error> {
+///
+/// The URI scheme decides whether the connection is encrypted. When TLS
+/// settings are given for a `bolt://` or `neo4j://` URI, the scheme is
+/// upgraded to `+s`, or `+ssc` with [`TrustMode::All`].
+///
+/// [`Auth::EmptyBasic`] connects with empty basic credentials, which servers
+/// with authentication disabled accept.
+///
+/// The pool is sized by `max_connections` and results are streamed in
+/// chunks of `fetch_size`. The connection is verified with a trivial query
+/// before returning, so an unreachable server or bad credentials fail here,
+/// within `connect_timeout`, rather than on the first load.
+pub async fn connect(
+    config: &Neo4jConfig,
+) -> Result<Graph, Box<dyn std::error::Error + Send + Sync>> {
+    let graph = graph(config, &tls_uri(&config.uri, &config.tls)?, None)?;
+    let limit = config.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT);
+    tokio::time::timeout(limit, graph.run(neo4rs::query("RETURN 1")))
+        .await
+        .map_err(|_| format!("could not connect to {} within {limit:?}", config.uri))??;
+    Ok(graph)
+}
+
+/// A lazily connecting [`Graph`] for `uri`, which must already reflect the
+/// TLS settings, using `database` instead of the configured one if given.
+pub(crate) fn graph(
+    config: &Neo4jConfig,
+    uri: &str,
+    database: Option<&str>,
+) -> Result<Graph, Box<dyn std::error::Error + Send + Sync>> {
     // Install the rustls crypto provider so neo4rs can build TLS configs.
     let _ = ring::default_provider().install_default();
+    let (user, password) = credentials(&config.auth);
     let mut builder = ConfigBuilder::default()
-        .uri(&config.uri)
-        .user(&config.username)
-        .password(&config.password);
-    if let Some(db) = &config.database {
-        builder = builder.db(db.as_str());
-    }
-    let cfg = builder.build().map_err(neo4rs::Error::from)?;
-    Graph::connect(cfg)
+        .uri(uri)
+        .user(user)
+        .password(password);
+    if let Some(db) = database.or(config.database.as_deref()) {
+        builder = builder.db(db);
+    }
+    if let Some(ca_file) = &config.tls.ca_file {
+        builder = builder.with_client_certificate(ca_file);
+    }
+    if config.tls.trust == TrustMode::All {
+        builder = builder.skip_ssl_validation();
+    }
+    if let Some(max) = config.max_connections {
+        builder = builder.max_connections(max);
+    }
+    if let Some(fetch_size) = config.fetch_size {
+        builder = builder.fetch_size(fetch_size);
+    }
+    Ok(Graph::connect(builder.build()?)?)
+}
+
+/// The user name and password to send for `auth`.
+fn credentials(auth: &Auth) -> (&str, &str) {
+    match auth {
+        Auth::Basic { username, password } => (username, password.expose()),
+        Auth::EmptyBasic => ("", ""),
+    }
+}
+
+/// The URI with its scheme adjusted to the TLS settings.
+pub(crate) fn tls_uri(
+    uri: &str,
+    tls: &TlsConfig,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let Some((scheme, rest)) = uri.split_once("://") else {
+        return Ok(uri.to_owned());
+    };
+    let (base, security) = scheme.split_once('+').unwrap_or((scheme, ""));
+    if !matches!(base, "bolt" | "neo4j") {
+        return Ok(uri.to_owned());
+    }
+    let security = match (security, tls.trust) {
+        ("ssc", _) if tls.ca_file.is_some() => {
+            return Err(format!(
+                "tls_ca_file has no effect with `{scheme}://`, which skips certificate \
+                 validation; use `{base}+s://`"
+            )
+            .into());
+        }
+        (_, TrustMode::All) => "+ssc",
+        ("", _) if tls.is_requested() => "+s",
+        ("", _) => "",
+        ("s", _) => "+s",
+        ("ssc", _) => "+ssc",
+        (other, _) => return Err(format!("unsupported URI scheme `{base}+{other}`").into()),
+    };
+    Ok(format!("{base}{security}://{rest}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn adjusts_scheme_to_tls_settings() {
+        let plain = TlsConfig::default();
+        assert_eq!(tls_uri("bolt://h:7687", &plain).unwrap(), "bolt://h:7687");
+        assert_eq!(tls_uri("neo4j+s://h", &plain).unwrap(), "neo4j+s://h");
+
+        let ca = TlsConfig {
+            ca_file: Some(PathBuf::from("ca.pem")),
+            ..TlsConfig::default()
+        };
+        assert_eq!(tls_uri("neo4j://h", &ca).unwrap(), "neo4j+s://h");
+        assert!(tls_uri("bolt+ssc://h", &ca).is_err());
+
+        let all = TlsConfig {
+            trust: TrustMode::All,
+            ..TlsConfig::default()
+        };
+        assert_eq!(tls_uri("bolt://h", &all).unwrap(), "bolt+ssc://h");
+        assert_eq!(tls_uri("neo4j+s://h", &all).unwrap(), "neo4j+ssc://h");
+    }
+
+    #[test]
+    fn maps_auth_to_credentials() {
+        assert_eq!(credentials(&Auth::basic("neo4j", "pw")), ("neo4j", "pw"));
+        assert_eq!(credentials(&Auth::EmptyBasic), ("", ""));
+    }
 }
@@ -0,0 +1,495 @@
+//! Batch partitioning for relationship loads.
+//!
+//! Creating a relationship takes write locks on both of its end nodes, so two
+//! concurrent transactions touching the same node contend with each other and
+//! may deadlock. A [`PartitionStrategy`] turns the list of relationships to be
+//! written into a [`Plan`]: a sequence of rounds, where every batch inside a
+//! round can be executed concurrently with the other batches of that round.
+//!
+//! The loader packs the batches of a round into transactions of up to
+//! `batch_size` rows only when the round is node-disjoint (see
+//! [`Round::is_disjoint`]), which [`GreedyColoring`] guarantees. Otherwise
+//! each batch runs as its own chain of transactions, concurrently with the
+//! other batches of its round, so batches sharing a node may still contend
+//! for its lock and be retried after a deadlock.
+//!
+//! Plans are computed purely from the input keys, so they can be inspected
+//! through [`PlanStats`] without connecting to a database.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use neo4rs::BoltType;
+
+/// Identifies a node for the purpose of lock conflict detection.
+///
+/// A key is the node label together with the values used to match the node,
+/// so `Person` 1 and `Company` 1 are distinct while two rows matching the same
+/// node always produce equal keys.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeKey {
+    pub label: String,
+    pub values: Vec<KeyValue>,
+}
+
+impl NodeKey {
+    /// Build a key from the Bolt values sent as MATCH parameters.
+    pub fn new<'a>(label: &str, values: impl IntoIterator<Item = &'a BoltType>) -> Self {
+        Self {
+            label: label.to_owned(),
+            values: values.into_iter().map(KeyValue::from).collect(),
+        }
+    }
+}
+
+/// A hashable representation of a Bolt value used in a [`NodeKey`].
+///
+/// Values are normalized the way Cypher compares them: an integral float
+/// equals the corresponding integer, and `-0.0` equals `0.0`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum KeyValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    /// Bit pattern of a non-integral float.
+    Float(u64),
+    String(String),
+    List(Vec<KeyValue>),
+    /// Any other value, compared by its debug representation.
+    Other(String),
+}
+
+impl From<&BoltType> for KeyValue {
+    fn from(value: &BoltType) -> Self {
+        match value {
+            BoltType::Null(_) => KeyValue::Null,
+            BoltType::Boolean(b) => KeyValue::Bool(b.value),
+            BoltType::Integer(i) => KeyValue::Int(i.value),
+            BoltType::Float(f) => {
+                let v = f.value;
+                if v.fract() == 0.0 && v >= i64::MIN as f64 && v < i64::MAX as f64 {
+                    KeyValue::Int(v as i64)
+                } else {
+                    KeyValue::Float(v.to_bits())
+                }
+            }
+            BoltType::String(s) => KeyValue::String(s.value.clone()),
+            BoltType::List(l) => KeyValue::List(l.value.iter().map(KeyValue::from).collect()),
+            other => KeyValue::Other(format!("{other:?}")),
+        }
+    }
+}
+
+/// The start and end node of a single relationship row.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Edge {
+    pub start: NodeKey,
+    pub end: NodeKey,
+}
+
+impl Edge {
+    pub fn new(start: NodeKey, end: NodeKey) -> Self {
+        Self { start, end }
+    }
+}
+
+/// A set of batches that may run concurrently.
+///
+/// Each batch holds indices into the edge slice the plan was built from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Round {
+    pub batches: Vec<Vec<usize>>,
+}
+
+impl Round {
+    /// Whether no node of `edges` is touched by two batches of the round.
+    /// Only then can batches be merged into larger transactions and run
+    /// concurrently without two transactions contending for a lock.
+    pub fn is_disjoint(&self, edges: &[Edge]) -> bool {
+        let mut owner: HashMap<&NodeKey, usize> = HashMap::new();
+        for (batch, rows) in self.batches.iter().enumerate() {
+            for &idx in rows {
+                for key in [&edges[idx].start, &edges[idx].end] {
+                    if *owner.entry(key).or_insert(batch) != batch {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+}
+
+/// An execution plan: rounds run one after another, batches within a round
+/// run in parallel and rows within a batch run sequentially.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Plan {
+    pub rounds: Vec<Round>,
+}
+
+impl Plan {
+    /// Total number of batches across all rounds.
+    pub fn batch_count(&self) -> usize {
+        self.rounds.iter().map(|r| r.batches.len()).sum()
+    }
+
+    /// Total number of rows scheduled by the plan.
+    pub fn row_count(&self) -> usize {
+        self.rounds
+            .iter()
+            .flat_map(|r| r.batches.iter())
+            .map(Vec::len)
+            .sum()
+    }
+}
+
+/// Splits relationship rows into a [`Plan`].
+pub trait PartitionStrategy: Send + Sync {
+    /// Short human readable name, used in logs and statistics.
+    fn name(&self) -> &'static str;
+
+    /// Build a plan covering every index of `edges` exactly once.
+    fn plan(&self, edges: &[Edge]) -> Plan;
+}
+
+/// Greedy graph coloring.
+///
+/// Every row is placed into the first round that does not yet touch either of
+/// its nodes, so no two rows of the same round share a node. Each row forms its
+/// own batch. This is the default strategy.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GreedyColoring;
+
+impl PartitionStrategy for GreedyColoring {
+    fn name(&self) -> &'static str {
+        "greedy"
+    }
+
+    fn plan(&self, edges: &[Edge]) -> Plan {
+        let mut rounds: Vec<Round> = Vec::new();
+        let mut used: Vec<HashSet<&NodeKey>> = Vec::new();
+
+        for (idx, edge) in edges.iter().enumerate() {
+            let slot = used
+                .iter()
+                .position(|u| !u.contains(&edge.start) && !u.contains(&edge.end));
+            let slot = match slot {
+                Some(slot) => slot,
+                None => {
+                    rounds.push(Round::default());
+                    used.push(HashSet::new());
+                    rounds.len() - 1
+                }
+            };
+            used[slot].insert(&edge.start);
+            used[slot].insert(&edge.end);
+            rounds[slot].batches.push(vec![idx]);
+        }
+        Plan { rounds }
+    }
+}
+
+/// Hash-grid bucketing in the style of Neo4j's "mix and batch" technique.
+///
+/// Start and end nodes are hashed into `partitions` buckets, which places each
+/// row in a cell of a `partitions x partitions` grid. Round `k` contains the
+/// cells `(i, (i + k) % partitions)`, so the cells of one round never share a
+/// start bucket or an end bucket. Each non-empty cell becomes a batch.
+///
+/// Batches are only guaranteed to be node-disjoint when start and end nodes
+/// come from separate key spaces (e.g. different labels); a node that appears
+/// on both sides may be touched by two batches of the same round. Such
+/// rounds are not packed into larger transactions, and their concurrent
+/// batches may contend for that node's lock.
+#[derive(Debug, Clone, Copy)]
+pub struct MixAndBatch {
+    partitions: usize,
+}
+
+impl MixAndBatch {
+    /// Create a strategy using `partitions` buckets per side (at least one).
+    pub fn new(partitions: usize) -> Self {
+        Self {
+            partitions: partitions.max(1),
+        }
+    }
+
+    fn bucket(&self, key: &NodeKey) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (hasher.finish() % self.partitions as u64) as usize
+    }
+}
+
+impl Default for MixAndBatch {
+    fn default() -> Self {
+        Self::new(8)
+    }
+}
+
+impl PartitionStrategy for MixAndBatch {
+    fn name(&self) -> &'static str {
+        "mix-and-batch"
+    }
+
+    fn plan(&self, edges: &[Edge]) -> Plan {
+        let n = self.partitions;
+        let mut cells: Vec<Vec<usize>> = vec![Vec::new(); n * n];
+        for (idx, edge) in edges.iter().enumerate() {
+            let s = self.bucket(&edge.start);
+            let e = self.bucket(&edge.end);
+            cells[s * n + e].push(idx);
+        }
+
+        let mut rounds = Vec::new();
+        for k in 0..n {
+            let batches: Vec<Vec<usize>> = (0..n)
+                .map(|i| std::mem::take(&mut cells[i * n + (i + k) % n]))
+                .filter(|cell| !cell.is_empty())
+                .collect();
+            if !batches.is_empty() {
+                rounds.push(Round { batches });
+            }
+        }
+        Plan { rounds }
+    }
+}
+
+/// Group rows by start node.
+///
+/// All rows sharing a start node end up in the same batch, and all batches run
+/// in a single round. This removes contention on start nodes but does not
+/// protect end nodes, so it suits data where end nodes are rarely shared:
+/// batches sharing an end node run concurrently and may deadlock, and a
+/// round with shared end nodes is not packed into larger transactions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SortByStart;
+
+impl PartitionStrategy for SortByStart {
+    fn name(&self) -> &'static str {
+        "sort-by-start"
+    }
+
+    fn plan(&self, edges: &[Edge]) -> Plan {
+        let mut order: Vec<usize> = (0..edges.len()).collect();
+        order.sort_by(|a, b| edges[*a].start.cmp(&edges[*b].start));
+
+        let mut batches: Vec<Vec<usize>> = Vec::new();
+        for idx in order {
+            match batches.last_mut() {
+                Some(batch) if edges[batch[0]].start == edges[idx].start => batch.push(idx),
+                _ => batches.push(vec![idx]),
+            }
+        }
+        if batches.is_empty() {
+            return Plan::default();
+        }
+        Plan {
+            rounds: vec![Round { batches }],
+        }
+    }
+}
+
+/// No partitioning: a single round in which every row is its own batch.
+///
+/// Offers the most parallelism and no protection against lock contention.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoPartition;
+
+impl PartitionStrategy for NoPartition {
+    fn name(&self) -> &'static str {
+        "none"
+    }
+
+    fn plan(&self, edges: &[Edge]) -> Plan {
+        if edges.is_empty() {
+            return Plan::default();
+        }
+        Plan {
+            rounds: vec![Round {
+                batches: (0..edges.len()).map(|i| vec![i]).collect(),
+            }],
+        }
+    }
+}
+
+/// Summary statistics of a [`Plan`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlanStats {
+    pub rows: usize,
+    pub rounds: usize,
+    pub batches: usize,
+    /// Number of batches per size bucket. Buckets are powers of two and keyed
+    /// by their lower bound, so key `4` counts batches with 4 to 7 rows.
+    pub batch_size_histogram: BTreeMap<usize, usize>,
+    /// Largest number of relationships attached to a single node.
+    pub max_degree: usize,
+}
+
+impl PlanStats {
+    /// Compute statistics for `plan`, which must have been built from `edges`.
+    pub fn new(edges: &[Edge], plan: &Plan) -> Self {
+        let mut histogram = BTreeMap::new();
+        for batch in plan.rounds.iter().flat_map(|r| r.batches.iter()) {
+            if batch.is_empty() {
+                continue;
+            }
+            let bucket = 1usize << batch.len().ilog2();
+            *histogram.entry(bucket).or_insert(0) += 1;
+        }
+
+        let mut degree: HashMap<&NodeKey, usize> = HashMap::new();
+        for edge in edges {
+            *degree.entry(&edge.start).or_insert(0) += 1;
+            if edge.end != edge.start {
+                *degree.entry(&edge.end).or_insert(0) += 1;
+            }
+        }
+
+        Self {
+            rows: plan.row_count(),
+            rounds: plan.rounds.len(),
+            batches: plan.batch_count(),
+            batch_size_histogram: histogram,
+            max_degree: degree.values().copied().max().unwrap_or(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(id: &str) -> NodeKey {
+        NodeKey::new("Node", [&BoltType::from(id)])
+    }
+
+    fn edges(pairs: &[(&str, &str)]) -> Vec<Edge> {
+        pairs
+            .iter()
+            .map(|(s, e)| Edge::new(key(s), key(e)))
+            .collect()
+    }
+
+    fn assert_covers(plan: &Plan, len: usize) {
+        let mut seen: Vec<usize> = plan
+            .rounds
+            .iter()
+            .flat_map(|r| r.batches.iter().flatten().copied())
+            .collect();
+        seen.sort_unstable();
+        assert_eq!(seen, (0..len).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn greedy_rounds_are_node_disjoint() {
+        let input = edges(&[("a", "b"), ("b", "c"), ("c", "d"), ("a", "d"), ("e", "f")]);
+        let plan = GreedyColoring.plan(&input);
+        assert_covers(&plan, input.len());
+        for round in &plan.rounds {
+            let mut nodes = HashSet::new();
+            for idx in round.batches.iter().flatten() {
+                assert!(nodes.insert(&input[*idx].start));
+                assert!(nodes.insert(&input[*idx].end));
+            }
+        }
+    }
+
+    #[test]
+    fn mix_and_batch_rounds_use_distinct_buckets() {
+        let input: Vec<Edge> = (0..200)
+            .map(|i| Edge::new(key(&format!("s{i}")), key(&format!("e{}", i % 17))))
+            .collect();
+        let strategy = MixAndBatch::new(4);
+        let plan = strategy.plan(&input);
+        assert_covers(&plan, input.len());
+        assert!(plan.rounds.len() <= 4);
+        for round in &plan.rounds {
+            let mut starts = HashSet::new();
+            let mut ends = HashSet::new();
+            for batch in &round.batches {
+                let first = &input[batch[0]];
+                assert!(starts.insert(strategy.bucket(&first.start)));
+                assert!(ends.insert(strategy.bucket(&first.end)));
+            }
+        }
+    }
+
+    #[test]
+    fn sort_by_start_groups_start_nodes() {
+        let input = edges(&[("b", "x"), ("a", "y"), ("b", "z"), ("a", "x")]);
+        let plan = SortByStart.plan(&input);
+        assert_covers(&plan, input.len());
+        assert_eq!(plan.rounds.len(), 1);
+        assert_eq!(plan.rounds[0].batches, vec![vec![1, 3], vec![0, 2]]);
+        // Both batches lock `x`.
+        assert!(!plan.rounds[0].is_disjoint(&input));
+    }
+
+    #[test]
+    fn detects_disjoint_rounds() {
+        let input = edges(&[("a", "b"), ("b", "c"), ("c", "a")]);
+        for round in &GreedyColoring.plan(&input).rounds {
+            assert!(round.is_disjoint(&input));
+        }
+        // A node shared within one batch is locked by a single transaction.
+        let round = Round {
+            batches: vec![vec![0, 1], vec![2]],
+        };
+        assert!(!round.is_disjoint(&input));
+        let round = Round {
+            batches: vec![vec![0, 1]],
+        };
+        assert!(round.is_disjoint(&input));
+    }
+
+    #[test]
+    fn no_partition_is_single_round() {
+        let input = edges(&[("a", "b"), ("a", "b")]);
+        let plan = NoPartition.plan(&input);
+        assert_eq!(plan.rounds.len(), 1);
+        assert_eq!(plan.batch_count(), 2);
+        assert_eq!(NoPartition.plan(&[]), Plan::default());
+    }
+
+    #[test]
+    fn stats_report_histogram_and_degree() {
+        let input = edges(&[("a", "b"), ("a", "c"), ("a", "d"), ("e", "f")]);
+        let plan = SortByStart.plan(&input);
+        let stats = PlanStats::new(&input, &plan);
+        assert_eq!(stats.rows, 4);
+        assert_eq!(stats.rounds, 1);
+        assert_eq!(stats.batches, 2);
+        assert_eq!(stats.max_degree, 3);
+        assert_eq!(stats.batch_size_histogram.get(&1), Some(&1));
+        assert_eq!(stats.batch_size_histogram.get(&2), Some(&1));
+    }
+
+    #[test]
+    fn keys_include_label() {
+        let id = BoltType::from(1i64);
+        assert_ne!(
+            NodeKey::new("Person", [&id]),
+            NodeKey::new("Company", [&id])
+        );
+        assert_eq!(NodeKey::new("Person", [&id]), NodeKey::new("Person", [&id]));
+    }
+
+    #[test]
+    fn keys_follow_cypher_equality() {
+        let int = BoltType::from(1i64);
+        let float = BoltType::from(1.0f64);
+        let string = BoltType::from("1");
+        assert_eq!(KeyValue::from(&int), KeyValue::from(&float));
+        assert_ne!(KeyValue::from(&int), KeyValue::from(&string));
+        assert_eq!(
+            KeyValue::from(&BoltType::from(-0.0f64)),
+            KeyValue::from(&BoltType::from(0i64))
+        );
+        assert_eq!(
+            KeyValue::from(&BoltType::from(1.5f64)),
+            KeyValue::Float(1.5f64.to_bits())
+        );
+    }
+}
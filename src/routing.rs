@@ -0,0 +1,527 @@
+//! Routing writes to the leader of a cluster.
+//!
+//! A `neo4j://` URI names a router rather than the server to write to. The
+//! driver connects to that address directly, so [`Router`] fetches the
+//! cluster's routing table itself with `dbms.routing.getRoutingTable` and
+//! sends every write transaction to the current leader.
+//!
+//! When a write fails because the server is no longer the leader
+//! (`Neo.ClientError.Cluster.NotALeader`), the database became read only on
+//! it, or the connection broke, the routing table is refreshed and the
+//! transaction retried on the new leader. Transient errors such as deadlocks
+//! are retried with backoff on the same server. A retried transaction may
+//! have been committed before the connection broke, so loads that must not
+//! create duplicates should merge on a key. Server-batched payloads are
+//! never retried, since the server commits part of a payload before it can
+//! fail; their errors end the load.
+//!
+//! With a `bolt://` URI, or a [`Graph`] passed directly, writes go to that
+//! server and only transient errors are retried.
+//!
+//! The `MATCH`es of a relationship load run inside its write transactions on
+//! the leader. A leader elected mid-run, or a load in another process, may
+//! not have applied an earlier load's writes yet, so loads wait for the
+//! bookmarks they are given; see [`crate::bookmark`].
+
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use neo4rs::{Graph, Neo4jErrorKind, Query, Row, query};
+
+use crate::config::{DEFAULT_CONNECT_TIMEOUT, Neo4jConfig};
+use crate::dialect::Dialect;
+use crate::neo4j::{connect, graph, tls_uri};
+
+/// How many times a write transaction is attempted before giving up.
+const MAX_ATTEMPTS: u32 = 8;
+
+/// Delay before the second attempt, doubled after every failure.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+
+/// A handle sending write transactions to the current leader.
+///
+/// Cloning is cheap and clones share the routing table. Every load accepts
+/// a `&Router` as well as a `&Graph`; the [`Dialect`] of a `Graph` is
+/// detected when a load first uses it, unless one is set with
+/// [`with_dialect`](Self::with_dialect).
+#[derive(Clone)]
+pub struct Router {
+    inner: Arc<Inner>,
+    /// Set when connecting or with [`with_dialect`](Self::with_dialect).
+    dialect: Option<Dialect>,
+}
+
+struct Inner {
+    /// Present when routing with a `neo4j://` URI.
+    routing: Option<Routing>,
+    writer: RwLock<Writer>,
+    /// Serializes routing table refreshes.
+    refresh: tokio::sync::Mutex<()>,
+    /// Dialect detected by [`Router::detect_dialect`].
+    detected: tokio::sync::OnceCell<Dialect>,
+}
+
+struct Routing {
+    config: Neo4jConfig,
+    /// `+s`, `+ssc` or empty, appended to `bolt` for every server.
+    security: String,
+    /// Address from the URI, tried when no known router answers.
+    seed: String,
+}
+
+#[derive(Clone)]
+struct Writer {
+    graph: Graph,
+    address: String,
+    routers: Vec<String>,
+    expires: Option<Instant>,
+    /// Incremented on every refresh, so concurrent failures on the same
+    /// leader trigger a single refresh.
+    generation: u64,
+}
+
+/// A routing table as returned by the server.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct RoutingTable {
+    ttl: Duration,
+    writers: Vec<String>,
+    routers: Vec<String>,
+}
+
+/// What to do after a failed write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Recovery {
+    /// Refresh the routing table and retry on the leader it names.
+    Reroute,
+    /// Retry on the same server after a delay.
+    Retry,
+    Fail,
+}
+
+impl Router {
+    /// Connect using `config`. For `neo4j://` URIs the routing table is
+    /// fetched from the URI's address, within `connect_timeout`; other URIs
+    /// are connected as with [`connect`]. The server's [`Dialect`] is
+    /// detected unless `config.dialect` sets it.
+    pub async fn connect(
+        config: &Neo4jConfig,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let uri = tls_uri(&config.uri, &config.tls)?;
+        let (scheme, rest) = uri.split_once("://").unwrap_or(("", &uri));
+        let Some(security) = scheme.strip_prefix("neo4j") else {
+            let graph = connect(config).await?;
+            let dialect = match config.dialect {
+                Some(dialect) => dialect,
+                None => Dialect::detect(&graph).await?,
+            };
+            return Ok(Self::from(graph).with_dialect(dialect));
+        };
+        if let Some(dialect) = config.dialect {
+            dialect.routing_query()?;
+        }
+        let seed = rest.split(['/', '?']).next().unwrap_or(rest).to_string();
+        let routing = Routing {
+            config: config.clone(),
+            security: security.to_string(),
+            seed,
+        };
+        let limit = config.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT);
+        let writer = tokio::time::timeout(limit, routing.writer(&[], 0))
+            .await
+            .map_err(|_| format!("could not route {} within {limit:?}", config.uri))??;
+        let dialect = match config.dialect {
+            Some(dialect) => dialect,
+            None => Dialect::detect(&writer.graph).await?,
+        };
+        Ok(Self {
+            inner: Arc::new(Inner {
+                routing: Some(routing),
+                writer: RwLock::new(writer),
+                refresh: tokio::sync::Mutex::new(()),
+                detected: tokio::sync::OnceCell::new(),
+            }),
+            dialect: Some(dialect),
+        })
+    }
+
+    /// Generate Cypher for `dialect` instead of the detected one.
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = Some(dialect);
+        self
+    }
+
+    /// The dialect loads generate their Cypher in: the one set when
+    /// connecting or with [`with_dialect`](Self::with_dialect), else the
+    /// one [`detect_dialect`](Self::detect_dialect) found, else the default.
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+            .or_else(|| self.inner.detected.get().copied())
+            .unwrap_or_default()
+    }
+
+    /// Detect the dialect of a router made from a [`Graph`], once for it
+    /// and its clones, and return the dialect loads will use. Loads call
+    /// this before generating any Cypher.
+    pub async fn detect_dialect(
+        &self,
+    ) -> Result<Dialect, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(dialect) = self.dialect {
+            return Ok(dialect);
+        }
+        let graph = self.graph();
+        let detected = self
+            .inner
+            .detected
+            .get_or_try_init(|| Dialect::detect(&graph))
+            .await?;
+        Ok(*detected)
+    }
+
+    /// The connection to the current leader.
+    pub fn graph(&self) -> Graph {
+        self.writer().graph
+    }
+
+    /// Address of the current leader, as `host:port`.
+    pub fn leader(&self) -> String {
+        self.writer().address
+    }
+
+    fn writer(&self) -> Writer {
+        self.inner
+            .writer
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Run `query` in a write transaction on the leader, retrying as
+    /// described in the [module documentation](self). `timeout` applies to
+    /// each attempt. Returns how many attempts failed before the commit,
+    /// which signals contention such as deadlocks or lock timeouts.
+    pub(crate) async fn write(
+        &self,
+        query: Query,
+        timeout: Option<Duration>,
+    ) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
+        let ((), retries) = self
+            .retry(timeout, |graph| commit(graph, query.clone()))
+            .await?;
+        Ok(retries)
+    }
+
+    /// Run `query` on the leader as an auto-commit query and return its
+    /// rows, retrying like [`write`](Self::write). Only for queries that
+    /// are safe to repeat; see [`execute_once`](Self::execute_once).
+    pub(crate) async fn execute(
+        &self,
+        query: Query,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Row>, Box<dyn std::error::Error + Send + Sync>> {
+        let (rows, _) = self
+            .retry(timeout, |graph| rows(graph, query.clone()))
+            .await?;
+        Ok(rows)
+    }
+
+    /// Run `query` on the leader as an auto-commit query and return its
+    /// rows, without retrying. Needed by queries that commit their own
+    /// transactions, such as `CALL { } IN TRANSACTIONS`: one that fails
+    /// halfway may have committed some of them, so sending it again could
+    /// write rows twice. Only the driver resends a query, when the server
+    /// refuses it before running anything. A routing error still refreshes
+    /// the routing table, so the next query goes to the new leader.
+    pub(crate) async fn execute_once(
+        &self,
+        query: Query,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Row>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut writer = self.writer();
+        if writer.expires.is_some_and(|at| at <= Instant::now()) {
+            self.refresh(writer.generation).await?;
+            writer = self.writer();
+        }
+        match within(timeout, rows(writer.graph.clone(), query)).await? {
+            Ok(rows) => Ok(rows),
+            Err(error) => {
+                if recovery(&error) == Recovery::Reroute {
+                    // The load fails either way; this only helps later ones.
+                    let _ = self.refresh(writer.generation).await;
+                }
+                Err(error.into())
+            }
+        }
+    }
+
+    /// Call `run` with the leader's connection until it succeeds or fails
+    /// in a way that retrying cannot fix, and count the failed attempts.
+    async fn retry<T, F, Fut>(
+        &self,
+        timeout: Option<Duration>,
+        run: F,
+    ) -> Result<(T, u32), Box<dyn std::error::Error + Send + Sync>>
+    where
+        F: Fn(Graph) -> Fut,
+        Fut: Future<Output = Result<T, neo4rs::Error>>,
+    {
+        let mut backoff = INITIAL_BACKOFF;
+        for attempt in 1.. {
+            let mut writer = self.writer();
+            if writer.expires.is_some_and(|at| at <= Instant::now()) {
+                self.refresh(writer.generation).await?;
+                writer = self.writer();
+            }
+            let error = match within(timeout, run(writer.graph.clone())).await? {
+                Ok(value) => return Ok((value, attempt - 1)),
+                Err(error) => error,
+            };
+            let recovery = match recovery(&error) {
+                Recovery::Reroute if self.inner.routing.is_none() => match &error {
+                    neo4rs::Error::Neo4j(_) => Recovery::Fail,
+                    _ => Recovery::Retry,
+                },
+                recovery => recovery,
+            };
+            if recovery == Recovery::Fail || attempt >= MAX_ATTEMPTS {
+                return Err(error.into());
+            }
+            if recovery == Recovery::Reroute
+                && self.refresh(writer.generation).await.is_ok()
+                && self.writer().address != writer.address
+            {
+                // A new leader is known, so retry on it right away.
+                continue;
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+        unreachable!("the attempts loop only ends by returning")
+    }
+
+    /// Fetch a new routing table unless another task already did so since
+    /// `generation` was current.
+    async fn refresh(
+        &self,
+        generation: u64,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let Some(routing) = &self.inner.routing else {
+            return Ok(());
+        };
+        let _guard = self.inner.refresh.lock().await;
+        let current = self.writer();
+        if current.generation != generation {
+            return Ok(());
+        }
+        let writer = routing
+            .writer(&current.routers, current.generation + 1)
+            .await?;
+        *self.inner.writer.write().unwrap_or_else(|e| e.into_inner()) = writer;
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for Router {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Router")
+            .field("routing", &self.inner.routing.is_some())
+            .field("leader", &self.leader())
+            .field("dialect", &self.dialect())
+            .finish()
+    }
+}
+
+impl From<Graph> for Router {
+    /// Write to `graph` without routing.
+    fn from(graph: Graph) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                routing: None,
+                writer: RwLock::new(Writer {
+                    graph,
+                    address: String::new(),
+                    routers: Vec::new(),
+                    expires: None,
+                    generation: 0,
+                }),
+                refresh: tokio::sync::Mutex::new(()),
+                detected: tokio::sync::OnceCell::new(),
+            }),
+            dialect: None,
+        }
+    }
+}
+
+impl From<&Graph> for Router {
+    fn from(graph: &Graph) -> Self {
+        graph.clone().into()
+    }
+}
+
+impl From<&Router> for Router {
+    fn from(router: &Router) -> Self {
+        router.clone()
+    }
+}
+
+impl Routing {
+    /// Ask the known routers, then the seed address, for a routing table and
+    /// connect to the leader it names.
+    async fn writer(
+        &self,
+        routers: &[String],
+        generation: u64,
+    ) -> Result<Writer, Box<dyn std::error::Error + Send + Sync>> {
+        let mut last_error = None;
+        let candidates = routers
+            .iter()
+            .chain(Some(&self.seed).filter(|seed| !routers.contains(seed)));
+        for router in candidates {
+            let table = match self.table(router).await {
+                Ok(table) => table,
+                Err(e) => {
+                    last_error = Some(format!("router {router}: {e}"));
+                    continue;
+                }
+            };
+            let Some(address) = table.writers.first() else {
+                last_error = Some(format!("router {router} knows no leader"));
+                continue;
+            };
+            return Ok(Writer {
+                graph: graph(&self.config, &self.uri(address), None)?,
+                address: address.clone(),
+                expires: Some(Instant::now() + table.ttl),
+                routers: table.routers,
+                generation,
+            });
+        }
+        Err(format!(
+            "cannot route {}: {}",
+            self.config.uri,
+            last_error.unwrap_or_else(|| "no router available".to_string())
+        )
+        .into())
+    }
+
+    async fn table(
+        &self,
+        router: &str,
+    ) -> Result<RoutingTable, Box<dyn std::error::Error + Send + Sync>> {
+        let cypher = self.config.dialect.unwrap_or_default().routing_query()?;
+        let graph = graph(&self.config, &self.uri(router), Some("system"))?;
+        let context: std::collections::HashMap<String, String> =
+            [("address".to_string(), router.to_string())].into();
+        let mut stream = graph
+            .execute(
+                query(cypher)
+                    .param("context", context)
+                    .param("database", self.config.database.clone()),
+            )
+            .await?;
+        let mut rows = Vec::new();
+        while let Some(row) = stream.next().await? {
+            rows.push((
+                row.get::<i64>("ttl")?,
+                row.get::<String>("role")?,
+                row.get::<Vec<String>>("addresses")?,
+            ));
+        }
+        Ok(RoutingTable::from_rows(rows))
+    }
+
+    fn uri(&self, address: &str) -> String {
+        format!("bolt{}://{address}", self.security)
+    }
+}
+
+impl RoutingTable {
+    fn from_rows(rows: Vec<(i64, String, Vec<String>)>) -> Self {
+        let mut table = RoutingTable::default();
+        for (ttl, role, addresses) in rows {
+            table.ttl = Duration::from_secs(ttl.max(0) as u64);
+            match role.as_str() {
+                "WRITE" => table.writers.extend(addresses),
+                "ROUTE" => table.routers.extend(addresses),
+                _ => {}
+            }
+        }
+        table
+    }
+}
+
+/// Await `run`, failing once `timeout` has passed.
+async fn within<T>(
+    timeout: Option<Duration>,
+    run: impl Future<Output = Result<T, neo4rs::Error>>,
+) -> Result<Result<T, neo4rs::Error>, Box<dyn std::error::Error + Send + Sync>> {
+    match timeout {
+        Some(limit) => Ok(tokio::time::timeout(limit, run)
+            .await
+            .map_err(|_| format!("transaction timed out after {limit:?}"))?),
+        None => Ok(run.await),
+    }
+}
+
+/// Run `query` in an explicit transaction and commit it. Unlike
+/// [`Graph::run`], this does not retry, so routing errors surface at once.
+async fn commit(graph: Graph, query: Query) -> Result<(), neo4rs::Error> {
+    let mut txn = graph.start_txn().await?;
+    txn.run(query).await?;
+    txn.commit().await
+}
+
+/// Run `query` outside an explicit transaction and collect its rows.
+async fn rows(graph: Graph, query: Query) -> Result<Vec<Row>, neo4rs::Error> {
+    let mut stream = graph.execute(query).await?;
+    let mut rows = Vec::new();
+    while let Some(row) = stream.next().await? {
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn recovery(error: &neo4rs::Error) -> Recovery {
+    match error {
+        neo4rs::Error::Neo4j(e) => match e.code() {
+            "Neo.ClientError.Cluster.NotALeader"
+            | "Neo.ClientError.General.ForbiddenOnReadOnlyDatabase" => Recovery::Reroute,
+            _ if e.kind() == Neo4jErrorKind::Transient => Recovery::Retry,
+            _ => Recovery::Fail,
+        },
+        neo4rs::Error::IOError { .. }
+        | neo4rs::Error::ConnectionError
+        | neo4rs::Error::ServerUnavailableError(_) => Recovery::Reroute,
+        _ => Recovery::Fail,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_routing_tables() {
+        let table = RoutingTable::from_rows(vec![
+            (300, "WRITE".into(), vec!["a:7687".into()]),
+            (300, "READ".into(), vec!["b:7687".into(), "c:7687".into()]),
+            (300, "ROUTE".into(), vec!["a:7687".into(), "b:7687".into()]),
+        ]);
+        assert_eq!(table.ttl, Duration::from_secs(300));
+        assert_eq!(table.writers, ["a:7687"]);
+        assert_eq!(table.routers, ["a:7687", "b:7687"]);
+    }
+
+    #[test]
+    fn classifies_errors() {
+        let io = neo4rs::Error::IOError {
+            detail: std::io::Error::from(std::io::ErrorKind::ConnectionReset),
+        };
+        assert_eq!(recovery(&io), Recovery::Reroute);
+        assert_eq!(
+            recovery(&neo4rs::Error::UnexpectedMessage("x".into())),
+            Recovery::Fail
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Run tagging, the `:LoadRun` audit node and rollback.
+//!
+//! A load given a run id with `run_id` stores the id in the `_loadRun`
+//! property of every node and relationship it creates. Entities that a merge
+//! only matched are left untagged, so a rollback never deletes data that
+//! existed before the run; their property updates are not undone either.
+//!
+//! Every load of a run also records itself on a `(:LoadRun {id})` node: the
+//! job parameters as JSON in `jobs`, the labels and relationship types it
+//! writes, timestamps in milliseconds since the epoch, and its outcome in
+//! `status` (`running`, `completed`, `failed`, `cancelled` or `rolled
+//! back`), `rows`, `transactions`, `failedRows` and `errors`. Several loads
+//! may share a run id; the counts add up and a failure or cancellation
+//! sticks.
+//!
+//! [`rollback`] deletes the tagged relationships and then the tagged nodes
+//! of the recorded types and labels, `batch_size` at a time, each batch in
+//! its own transaction. Nodes are detached first, which also removes
+//! relationships of other runs attached to them. An index on the property,
+//! such as `CREATE INDEX FOR (n:Person) ON (n._loadRun)`, keeps the batches
+//! from scanning every node of the label.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use neo4rs::query;
+
+use crate::loader::LoadSummary;
+use crate::routing::Router;
+
+/// Property holding the run id on tagged nodes and relationships.
+pub const RUN_PROPERTY: &str = "_loadRun";
+
+/// What a load of a run writes, for the audit node and rollback.
+pub(crate) struct RunJob<'a> {
+    pub run_id: &'a str,
+    /// The job's parameters, stored as a JSON string.
+    pub parameters: serde_json::Value,
+    pub labels: Vec<&'a str>,
+    pub types: Vec<&'a str>,
+}
+
+const START_RUN: &str = "MERGE (r:LoadRun {id: $id}) \
+    ON CREATE SET r.startedAt = $now, r.jobs = [], r.labels = [], r.types = [], \
+    r.rows = 0, r.transactions = 0, r.failedRows = 0, r.failedTransactions = 0, r.errors = [] \
+    SET r.status = CASE WHEN r.status IN ['failed', 'cancelled'] THEN r.status ELSE 'running' END, \
+    r.jobs = r.jobs + [$job], \
+    r.labels = r.labels + [l IN $labels WHERE NOT l IN r.labels], \
+    r.types = r.types + [t IN $types WHERE NOT t IN r.types]";
+
+const FINISH_RUN: &str = "MATCH (r:LoadRun {id: $id}) \
+    SET r.finishedAt = $now, \
+    r.status = CASE WHEN r.status IN ['failed', 'cancelled'] THEN r.status ELSE $status END, \
+    r.rows = r.rows + $rows, r.transactions = r.transactions + $transactions, \
+    r.failedRows = r.failedRows + $failedRows, \
+    r.failedTransactions = r.failedTransactions + $failedTransactions, \
+    r.errors = r.errors + [e IN $errors WHERE NOT e IN r.errors]";
+
+/// Record the start of `job` on its run's audit node.
+pub(crate) async fn start(
+    router: &Router,
+    job: &RunJob<'_>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let labels: Vec<String> = job.labels.iter().map(|l| l.to_string()).collect();
+    let types: Vec<String> = job.types.iter().map(|t| t.to_string()).collect();
+    router
+        .execute(
+            query(START_RUN)
+                .param("id", job.run_id)
+                .param("now", now())
+                .param("job", job.parameters.to_string())
+                .param("labels", labels)
+                .param("types", types),
+            None,
+        )
+        .await?;
+    Ok(())
+}
+
+/// Record the outcome of a load of run `run_id` on the audit node.
+pub(crate) async fn finish(
+    router: &Router,
+    run_id: &str,
+    outcome: &Result<LoadSummary, Box<dyn std::error::Error + Send + Sync>>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let (status, summary) = match outcome {
+        Ok(summary) if summary.cancelled => ("cancelled", summary.clone()),
+        Ok(summary) if summary.failed_transactions > 0 => ("failed", summary.clone()),
+        Ok(summary) => ("completed", summary.clone()),
+        Err(e) => (
+            "failed",
+            LoadSummary {
+                errors: vec![e.to_string()],
+                ..LoadSummary::default()
+            },
+        ),
+    };
+    router
+        .execute(
+            query(FINISH_RUN)
+                .param("id", run_id)
+                .param("now", now())
+                .param("status", status)
+                .param("rows", summary.rows as i64)
+                .param("transactions", summary.transactions as i64)
+                .param("failedRows", summary.failed_rows as i64)
+                .param("failedTransactions", summary.failed_transactions as i64)
+                .param("errors", summary.errors),
+            None,
+        )
+        .await?;
+    Ok(())
+}
+
+/// What a [`rollback`] deleted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rollback {
+    pub nodes: usize,
+    pub relationships: usize,
+}
+
+/// Delete every node and relationship tagged with `run_id`, `batch_size`
+/// per transaction, and mark the run as rolled back.
+pub async fn rollback(
+    target: impl Into<Router>,
+    run_id: &str,
+    batch_size: usize,
+) -> Result<Rollback, Box<dyn std::error::Error + Send + Sync>> {
+    if batch_size == 0 {
+        return Err("batch size must be at least 1".into());
+    }
+    let router = target.into();
+    let rows = router
+        .execute(
+            query("MATCH (r:LoadRun {id: $id}) RETURN r.labels AS labels, r.types AS types")
+                .param("id", run_id),
+            None,
+        )
+        .await?;
+    let row = rows
+        .first()
+        .ok_or_else(|| format!("no :LoadRun node with id `{run_id}`"))?;
+    let labels: Vec<String> = row.get("labels")?;
+    let types: Vec<String> = row.get("types")?;
+
+    let mut deleted = Rollback::default();
+    for rel_type in &types {
+        let cypher = delete_relationships(rel_type);
+        deleted.relationships += delete_batches(&router, &cypher, run_id, batch_size).await?;
+    }
+    for label in &labels {
+        let cypher = delete_nodes(label);
+        deleted.nodes += delete_batches(&router, &cypher, run_id, batch_size).await?;
+    }
+    router
+        .execute(
+            query(
+                "MATCH (r:LoadRun {id: $id}) SET r.status = 'rolled back', \
+                 r.rolledBackAt = $now, r.deletedNodes = $nodes, \
+                 r.deletedRelationships = $relationships",
+            )
+            .param("id", run_id)
+            .param("now", now())
+            .param("nodes", deleted.nodes as i64)
+            .param("relationships", deleted.relationships as i64),
+            None,
+        )
+        .await?;
+    Ok(deleted)
+}
+
+fn delete_relationships(rel_type: &str) -> String {
+    format!(
+        "MATCH ()-[r:{rel_type}]->() WHERE r.{RUN_PROPERTY} = $run \
+         WITH r LIMIT $limit DELETE r RETURN count(*) AS deleted"
+    )
+}
+
+fn delete_nodes(label: &str) -> String {
+    format!(
+        "MATCH (n:{label}) WHERE n.{RUN_PROPERTY} = $run \
+         WITH n LIMIT $limit DETACH DELETE n RETURN count(*) AS deleted"
+    )
+}
+
+/// Run a batched delete until it deletes nothing and return the total.
+async fn delete_batches(
+    router: &Router,
+    cypher: &str,
+    run_id: &str,
+    batch_size: usize,
+) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+    let mut total = 0;
+    loop {
+        let rows = router
+            .execute(
+                query(cypher)
+                    .param("run", run_id)
+                    .param("limit", batch_size as i64),
+                None,
+            )
+            .await?;
+        let deleted = match rows.first() {
+            Some(row) => row.get::<i64>("deleted")? as usize,
+            None => 0,
+        };
+        if deleted == 0 {
+            return Ok(total);
+        }
+        total += deleted;
+    }
+}
+
+/// Milliseconds since the epoch, for the audit node's timestamps.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64)
+}
+
+/// A new run id: the current time in milliseconds and the process id.
+pub fn new_run_id() -> String {
+    format!("run-{}-{}", now(), std::process::id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deletes_tagged_entities_in_batches() {
+        assert_eq!(
+            delete_relationships("KNOWS"),
+            "MATCH ()-[r:KNOWS]->() WHERE r._loadRun = $run \
+             WITH r LIMIT $limit DELETE r RETURN count(*) AS deleted"
+        );
+        assert_eq!(
+            delete_nodes("Person"),
+            "MATCH (n:Person) WHERE n._loadRun = $run \
+             WITH n LIMIT $limit DETACH DELETE n RETURN count(*) AS deleted"
+        );
+        assert!(new_run_id().starts_with("run-"));
+    }
+}
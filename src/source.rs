@@ -0,0 +1,59 @@
+//! Helpers shared by the file based sources.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Compression applied to a text source file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// Detect from the file extension (`.gz`, `.zst` or `.zstd`).
+    #[default]
+    Auto,
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    fn resolve(self, path: &Path) -> Self {
+        if self != Compression::Auto {
+            return self;
+        }
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("gz") | Some("gzip") => Compression::Gzip,
+            Some("zst") | Some("zstd") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+}
+
+/// Open `path` for reading, transparently decompressing it.
+pub(crate) fn open_decompressed(
+    path: &Path,
+    compression: Compression,
+) -> Result<Box<dyn BufRead + Send>, Box<dyn std::error::Error + Send + Sync>> {
+    let file = BufReader::new(File::open(path)?);
+    Ok(match compression.resolve(path) {
+        Compression::Gzip => Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(file))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::with_buffer(file)?)),
+        Compression::Auto | Compression::None => Box::new(file),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_compression_from_extension() {
+        let auto = Compression::Auto;
+        assert_eq!(auto.resolve(Path::new("a.csv.gz")), Compression::Gzip);
+        assert_eq!(auto.resolve(Path::new("a.csv.zst")), Compression::Zstd);
+        assert_eq!(auto.resolve(Path::new("a.csv")), Compression::None);
+        assert_eq!(
+            Compression::Gzip.resolve(Path::new("a.csv")),
+            Compression::Gzip
+        );
+    }
+}
@@ -0,0 +1,231 @@
+//! Throughput limits for loads into live databases.
+//!
+//! A [`Throttle`] caps the rows written per second and the number of
+//! concurrent transactions so a load leaves room for online traffic. Outside
+//! business hours the load may run at full speed: during any of the
+//! `full_speed` windows neither limit applies. Windows are times of day at a
+//! fixed `utc_offset` and do not follow daylight saving changes.
+
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// Limits on how fast a load writes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Throttle {
+    /// Rows sent per second, averaged over consecutive batches.
+    pub rows_per_second: Option<u64>,
+    /// Concurrent transactions, below the load's own `concurrency`.
+    pub max_concurrency: Option<usize>,
+    /// Times of day during which the limits are lifted.
+    pub full_speed: Vec<TimeWindow>,
+    /// Offset from UTC of the `full_speed` times, in minutes.
+    pub utc_offset_minutes: i32,
+}
+
+impl Throttle {
+    pub(crate) fn validate(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if self.rows_per_second == Some(0) {
+            return Err("throttle rows per second must be at least 1".into());
+        }
+        if self.max_concurrency == Some(0) {
+            return Err("throttle concurrency must be at least 1".into());
+        }
+        if self.utc_offset_minutes.unsigned_abs() >= MINUTES_PER_DAY {
+            return Err("throttle UTC offset must be less than a day".into());
+        }
+        Ok(())
+    }
+
+    /// Whether the limits apply at `now`, that is outside every full speed
+    /// window.
+    pub(crate) fn applies_at(&self, now: SystemTime) -> bool {
+        if self.full_speed.is_empty() {
+            return true;
+        }
+        let minutes = now
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() / 60) as i64;
+        let minute = (minutes + i64::from(self.utc_offset_minutes))
+            .rem_euclid(i64::from(MINUTES_PER_DAY)) as u32;
+        !self.full_speed.iter().any(|w| w.contains(minute))
+    }
+
+    /// Concurrent transactions allowed right now, given the load's own
+    /// `concurrency`.
+    pub(crate) fn concurrency(&self, concurrency: usize) -> usize {
+        match self.max_concurrency {
+            Some(max) if self.applies_at(SystemTime::now()) => concurrency.min(max),
+            _ => concurrency,
+        }
+    }
+}
+
+/// A daily time window such as `22:00-06:00`, which may wrap past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    /// Start as minutes after midnight, inclusive.
+    pub start: u32,
+    /// End as minutes after midnight, exclusive.
+    pub end: u32,
+}
+
+impl TimeWindow {
+    fn contains(&self, minute: u32) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+impl FromStr for TimeWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| format!("expected a window like `22:00-06:00`, got `{s}`"))?;
+        let window = Self {
+            start: time_of_day(start.trim())?,
+            end: time_of_day(end.trim())?,
+        };
+        if window.start == window.end {
+            return Err(format!("time window `{s}` is empty"));
+        }
+        Ok(window)
+    }
+}
+
+/// Parse `HH:MM` as minutes after midnight; `24:00` is the end of the day.
+fn time_of_day(s: &str) -> Result<u32, String> {
+    let invalid = || format!("expected a time like `06:30`, got `{s}`");
+    let (hours, minutes) = s.split_once(':').ok_or_else(invalid)?;
+    let hours: u32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u32 = minutes.parse().map_err(|_| invalid())?;
+    let total = hours * 60 + minutes;
+    if minutes >= 60 || total > MINUTES_PER_DAY {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+/// Parse a UTC offset such as `+02:00`, `-05:30` or `Z` as minutes.
+pub fn parse_utc_offset(s: &str) -> Result<i32, String> {
+    if s == "Z" || s == "UTC" {
+        return Ok(0);
+    }
+    let (sign, rest) = match s.split_at_checked(1) {
+        Some(("+", rest)) => (1, rest),
+        Some(("-", rest)) => (-1, rest),
+        _ => return Err(format!("expected an offset like `+02:00`, got `{s}`")),
+    };
+    let minutes = time_of_day(rest)
+        .ok()
+        .filter(|&m| m < MINUTES_PER_DAY)
+        .ok_or_else(|| format!("expected an offset like `+02:00`, got `{s}`"))?;
+    Ok(sign * minutes as i32)
+}
+
+/// Spaces out batches so that a [`Throttle`]'s rows per second hold.
+#[derive(Debug)]
+pub(crate) struct Pacer {
+    throttle: Throttle,
+    /// When the next batch may be sent.
+    next: Mutex<Instant>,
+}
+
+impl Pacer {
+    pub(crate) fn new(throttle: Throttle) -> Self {
+        Self {
+            throttle,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub(crate) fn throttle(&self) -> &Throttle {
+        &self.throttle
+    }
+
+    /// Wait until `rows` more rows may be sent.
+    pub(crate) async fn admit(&self, rows: usize) {
+        let Some(rate) = self.throttle.rows_per_second else {
+            return;
+        };
+        if !self.throttle.applies_at(SystemTime::now()) {
+            return;
+        }
+        let at = {
+            let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+            let at = (*next).max(Instant::now());
+            *next = at + Duration::from_secs_f64(rows as f64 / rate as f64);
+            at
+        };
+        tokio::time::sleep_until(at.into()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_windows_and_offsets() {
+        let night: TimeWindow = "22:00-06:30".parse().unwrap();
+        assert_eq!((night.start, night.end), (22 * 60, 6 * 60 + 30));
+        assert!(night.contains(23 * 60) && night.contains(60) && !night.contains(12 * 60));
+        let day: TimeWindow = "09:00-24:00".parse().unwrap();
+        assert!(day.contains(23 * 60 + 59) && !day.contains(0));
+        assert!("09:00-09:00".parse::<TimeWindow>().is_err());
+        assert!("9-17".parse::<TimeWindow>().is_err());
+        assert!("09:60-17:00".parse::<TimeWindow>().is_err());
+
+        assert_eq!(parse_utc_offset("+02:00"), Ok(120));
+        assert_eq!(parse_utc_offset("-05:30"), Ok(-330));
+        assert_eq!(parse_utc_offset("Z"), Ok(0));
+        assert!(parse_utc_offset("02:00").is_err());
+        assert!(parse_utc_offset("+24:00").is_err());
+    }
+
+    #[test]
+    fn lifts_limits_during_full_speed_windows() {
+        let throttle = Throttle {
+            max_concurrency: Some(2),
+            full_speed: vec!["22:00-06:00".parse().unwrap()],
+            utc_offset_minutes: 120,
+            ..Throttle::default()
+        };
+        // 21:00 UTC is 23:00 at the offset, 08:00 UTC is 10:00.
+        let at = |hour: u64| UNIX_EPOCH + Duration::from_secs(hour * 3600);
+        assert!(!throttle.applies_at(at(21)));
+        assert!(throttle.applies_at(at(8)));
+        assert!(Throttle::default().applies_at(at(21)));
+        assert_eq!(Throttle::default().concurrency(8), 8);
+        assert_eq!(
+            Throttle {
+                max_concurrency: Some(2),
+                ..Throttle::default()
+            }
+            .concurrency(8),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn paces_rows_per_second() {
+        let pacer = Pacer::new(Throttle {
+            rows_per_second: Some(1000),
+            ..Throttle::default()
+        });
+        let started = Instant::now();
+        for _ in 0..3 {
+            pacer.admit(100).await;
+        }
+        // The first batch goes right away, the others 100ms apart.
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
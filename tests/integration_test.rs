@@ -3,7 +3,8 @@ use arrow::datatypes::{DataType, Field, Schema};
 use arrow::record_batch::RecordBatch;
 use dotenvy::dotenv;
 use neo4j_parallel_rust_loader::{
-    Neo4jConfig, connect, load_parquet_nodes_parallel, load_parquet_relationships_parallel,
+    CsvOptions, Doctor, Neo4jConfig, NodeLoad, Router, Status, connect, load_csv_nodes_parallel,
+    load_parquet_nodes_parallel, load_parquet_relationships_parallel, rollback,
 };
 use parquet::arrow::ArrowWriter;
 use std::fs::File;
@@ -150,3 +151,188 @@ async fn test_relationship_loader() {
     }
     assert!(count >= 2);
 }
+
+#[tokio::test]
+async fn test_csv_loader() {
+    dotenv().ok();
+    let cfg = match Neo4jConfig::from_env() {
+        Ok(cfg) => cfg,
+        Err(_) => {
+            eprintln!("Skipping test_csv_loader: missing env vars");
+            return;
+        }
+    };
+    let graph = match connect(&cfg).await {
+        Ok(g) => g,
+        Err(e) => {
+            eprintln!("Could not connect to database: {e}");
+            return;
+        }
+    };
+    let csv = "tests/data/cities.csv";
+    std::fs::write(csv, "id,name\n1,Oslo\n2,Lima\n").unwrap();
+    load_csv_nodes_parallel(graph.clone(), csv, "CsvCity", &CsvOptions::default(), 4)
+        .await
+        .unwrap();
+    let mut result = graph
+        .execute(neo4rs::query(
+            "MATCH (n:CsvCity {name: 'Lima'}) RETURN count(n) as c",
+        ))
+        .await
+        .unwrap();
+    let mut count = 0;
+    while let Ok(Some(row)) = result.next().await {
+        count = row.get::<i64>("c").unwrap();
+    }
+    assert!(count >= 1);
+}
+
+#[tokio::test]
+async fn test_node_load_merges_on_key() {
+    dotenv().ok();
+    let cfg = match Neo4jConfig::from_env() {
+        Ok(cfg) => cfg,
+        Err(_) => {
+            eprintln!("Skipping test_node_load_merges_on_key: missing env vars");
+            return;
+        }
+    };
+    let graph = match connect(&cfg).await {
+        Ok(g) => g,
+        Err(e) => {
+            eprintln!("Could not connect to database: {e}");
+            return;
+        }
+    };
+    let parquet = "tests/data/keyed.parquet";
+    create_parquet(parquet).unwrap();
+    let load = NodeLoad::new(parquet, "KeyedPerson")
+        .key("id")
+        .batch_size(2);
+    for _ in 0..2 {
+        let summary = load.run(&graph).await.unwrap();
+        assert_eq!(summary.rows, 3);
+        assert_eq!(summary.transactions, 2);
+    }
+    let mut result = graph
+        .execute(neo4rs::query("MATCH (n:KeyedPerson) RETURN count(n) as c"))
+        .await
+        .unwrap();
+    let mut count = 0;
+    while let Ok(Some(row)) = result.next().await {
+        count = row.get::<i64>("c").unwrap();
+    }
+    assert_eq!(count, 3);
+}
+
+#[tokio::test]
+async fn test_server_side_batching() {
+    dotenv().ok();
+    let cfg = match Neo4jConfig::from_env() {
+        Ok(cfg) => cfg,
+        Err(_) => {
+            eprintln!("Skipping test_server_side_batching: missing env vars");
+            return;
+        }
+    };
+    let router = match Router::connect(&cfg).await {
+        Ok(router) => router,
+        Err(e) => {
+            eprintln!("Could not connect to database: {e}");
+            return;
+        }
+    };
+    let parquet = "tests/data/server_batched.parquet";
+    create_parquet(parquet).unwrap();
+    let summary = NodeLoad::new(parquet, "ServerBatchedPerson")
+        .key("id")
+        .batch_size(2)
+        .concurrency(1)
+        .server_batching(10)
+        .run(&router)
+        .await
+        .unwrap();
+    assert_eq!(summary.rows, 3);
+    assert_eq!(summary.transactions, 2);
+    assert_eq!(summary.failed_transactions, 0, "{:?}", summary.errors);
+}
+
+#[tokio::test]
+async fn test_rollback_deletes_created_nodes_only() {
+    dotenv().ok();
+    let cfg = match Neo4jConfig::from_env() {
+        Ok(cfg) => cfg,
+        Err(_) => {
+            eprintln!("Skipping test_rollback_deletes_created_nodes_only: missing env vars");
+            return;
+        }
+    };
+    let router = match Router::connect(&cfg).await {
+        Ok(router) => router,
+        Err(e) => {
+            eprintln!("Could not connect to database: {e}");
+            return;
+        }
+    };
+    let graph = router.graph();
+    for cypher in [
+        "MATCH (n:RollbackPerson) DETACH DELETE n",
+        "CREATE (:RollbackPerson {id: 1})",
+    ] {
+        graph.run(neo4rs::query(cypher)).await.unwrap();
+    }
+    let run_id = format!("test-rollback-{}", std::process::id());
+    let parquet = "tests/data/rollback.parquet";
+    create_parquet(parquet).unwrap();
+    let summary = NodeLoad::new(parquet, "RollbackPerson")
+        .key("id")
+        .run_id(&run_id)
+        .run(&router)
+        .await
+        .unwrap();
+    assert_eq!(summary.rows, 3);
+
+    let deleted = rollback(&router, &run_id, 1).await.unwrap();
+    assert_eq!((deleted.nodes, deleted.relationships), (2, 0));
+    let mut result = graph
+        .execute(
+            neo4rs::query(
+                "MATCH (n:RollbackPerson) WITH count(n) AS c \
+                 MATCH (r:LoadRun {id: $id}) RETURN c, r.status AS status, r.rows AS rows",
+            )
+            .param("id", run_id.as_str()),
+        )
+        .await
+        .unwrap();
+    let row = result.next().await.unwrap().unwrap();
+    assert_eq!(row.get::<i64>("c").unwrap(), 1);
+    assert_eq!(row.get::<String>("status").unwrap(), "rolled back");
+    assert_eq!(row.get::<i64>("rows").unwrap(), 3);
+}
+
+#[tokio::test]
+async fn test_doctor_reports_healthy_server() {
+    dotenv().ok();
+    let cfg = match Neo4jConfig::from_env() {
+        Ok(cfg) => cfg,
+        Err(_) => {
+            eprintln!("Skipping test_doctor_reports_healthy_server: missing env vars");
+            return;
+        }
+    };
+    let report = Doctor::new()
+        .node_load(&NodeLoad::new("people.csv", "DoctorPerson").key("id"))
+        .run(&cfg)
+        .await;
+    if report.checks[0].status == Status::Failed {
+        eprintln!("Could not connect to database: {}", report.checks[0].detail);
+        return;
+    }
+    assert!(report.is_healthy(), "{report}");
+    assert!(
+        report
+            .checks
+            .iter()
+            .any(|c| c.name == "index :DoctorPerson(id)")
+    );
+}
@@ -0,0 +1,696 @@
+//! Leader failover against fake cluster members speaking just enough Bolt
+//! 4.4 for the loader: routing table queries and write transactions.
+
+use neo4j_parallel_rust_loader::{
+    Adaptive, Auth, Bookmark, Dialect, LoadHandle, Neo4jConfig, NodeLoad, Router, Throttle,
+    TlsConfig, connect,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    List(Vec<Value>),
+    Map(Vec<(String, Value)>),
+    Struct(u8, Vec<Value>),
+}
+
+impl Value {
+    fn str(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+
+    fn map(entries: &[(&str, Value)]) -> Self {
+        Value::Map(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        )
+    }
+
+    fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+fn decode(bytes: &[u8], pos: &mut usize) -> Value {
+    let marker = bytes[*pos];
+    *pos += 1;
+    let mut take = |n: usize| {
+        let slice = &bytes[*pos..*pos + n];
+        *pos += n;
+        slice
+    };
+    let be = |slice: &[u8]| slice.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+    let (kind, len) = match marker {
+        0xC0 => return Value::Null,
+        0xC2 => return Value::Bool(false),
+        0xC3 => return Value::Bool(true),
+        0xC1 => return Value::Float(f64::from_be_bytes(take(8).try_into().unwrap())),
+        0xC8 => return Value::Int(take(1)[0] as i8 as i64),
+        0xC9 => return Value::Int(i16::from_be_bytes(take(2).try_into().unwrap()) as i64),
+        0xCA => return Value::Int(i32::from_be_bytes(take(4).try_into().unwrap()) as i64),
+        0xCB => return Value::Int(i64::from_be_bytes(take(8).try_into().unwrap())),
+        0x00..=0x7F | 0xF0..=0xFF => return Value::Int(marker as i8 as i64),
+        0x80..=0x8F => ('s', (marker & 0x0F) as usize),
+        0xD0 => ('s', be(take(1))),
+        0xD1 => ('s', be(take(2))),
+        0xD2 => ('s', be(take(4))),
+        0x90..=0x9F => ('l', (marker & 0x0F) as usize),
+        0xD4 => ('l', be(take(1))),
+        0xD5 => ('l', be(take(2))),
+        0xD6 => ('l', be(take(4))),
+        0xA0..=0xAF => ('m', (marker & 0x0F) as usize),
+        0xD8 => ('m', be(take(1))),
+        0xD9 => ('m', be(take(2))),
+        0xDA => ('m', be(take(4))),
+        0xB0..=0xBF => ('t', (marker & 0x0F) as usize),
+        other => panic!("unsupported PackStream marker {other:#x}"),
+    };
+    match kind {
+        's' => Value::String(String::from_utf8(take(len).to_vec()).unwrap()),
+        'l' => Value::List((0..len).map(|_| decode(bytes, pos)).collect()),
+        'm' => Value::Map(
+            (0..len)
+                .map(|_| match decode(bytes, pos) {
+                    Value::String(key) => (key, decode(bytes, pos)),
+                    other => panic!("map key {other:?}"),
+                })
+                .collect(),
+        ),
+        _ => {
+            let signature = take(1)[0];
+            Value::Struct(signature, (0..len).map(|_| decode(bytes, pos)).collect())
+        }
+    }
+}
+
+fn encode(value: &Value, out: &mut Vec<u8>) {
+    let header = |out: &mut Vec<u8>, tiny: u8, wide: u8, len: usize| {
+        if len < 16 {
+            out.push(tiny | len as u8);
+        } else {
+            out.push(wide);
+            out.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+    };
+    match value {
+        Value::Null => out.push(0xC0),
+        Value::Bool(b) => out.push(if *b { 0xC3 } else { 0xC2 }),
+        Value::Int(i) => {
+            out.push(0xCB);
+            out.extend_from_slice(&i.to_be_bytes());
+        }
+        Value::Float(f) => {
+            out.push(0xC1);
+            out.extend_from_slice(&f.to_be_bytes());
+        }
+        Value::String(s) => {
+            header(out, 0x80, 0xD2, s.len());
+            out.extend_from_slice(s.as_bytes());
+        }
+        Value::List(items) => {
+            header(out, 0x90, 0xD6, items.len());
+            items.iter().for_each(|item| encode(item, out));
+        }
+        Value::Map(entries) => {
+            header(out, 0xA0, 0xDA, entries.len());
+            for (key, value) in entries {
+                encode(&Value::String(key.clone()), out);
+                encode(value, out);
+            }
+        }
+        Value::Struct(signature, fields) => {
+            out.push(0xB0 | fields.len() as u8);
+            out.push(*signature);
+            fields.iter().for_each(|field| encode(field, out));
+        }
+    }
+}
+
+async fn read_message(stream: &mut TcpStream) -> Option<Value> {
+    let mut bytes = Vec::new();
+    loop {
+        let len = stream.read_u16().await.ok()? as usize;
+        if len == 0 {
+            if bytes.is_empty() {
+                continue;
+            }
+            return Some(decode(&bytes, &mut 0));
+        }
+        let start = bytes.len();
+        bytes.resize(start + len, 0);
+        stream.read_exact(&mut bytes[start..]).await.ok()?;
+    }
+}
+
+async fn write_message(stream: &mut TcpStream, signature: u8, fields: Vec<Value>) {
+    let mut bytes = Vec::new();
+    encode(&Value::Struct(signature, fields), &mut bytes);
+    for chunk in bytes.chunks(u16::MAX as usize) {
+        stream.write_u16(chunk.len() as u16).await.unwrap();
+        stream.write_all(chunk).await.unwrap();
+    }
+    stream.write_u16(0).await.unwrap();
+}
+
+const SUCCESS: u8 = 0x70;
+const RECORD: u8 = 0x71;
+const IGNORED: u8 = 0x7E;
+const FAILURE: u8 = 0x7F;
+
+/// State shared by the members of a fake cluster.
+#[derive(Default)]
+struct Cluster {
+    members: Vec<String>,
+    leader: String,
+    /// Rows committed by each member.
+    committed: HashMap<String, usize>,
+    /// Move leadership to this member once the leader committed this many
+    /// transactions.
+    fail_over: Option<(usize, String)>,
+    transactions: HashMap<String, usize>,
+    /// Server-batched payloads received by each member.
+    payloads: HashMap<String, usize>,
+    /// Fail server-batched payloads while they run with this error code.
+    fail_payloads: Option<&'static str>,
+    /// `lastCommittedTxn` of the writer, incremented by every commit.
+    last_transaction: i64,
+    /// Fail queries for `lastCommittedTxn`, as without access to `system`.
+    fail_bookmarks: bool,
+}
+
+async fn start_cluster(size: usize) -> Arc<Mutex<Cluster>> {
+    let cluster = Arc::new(Mutex::new(Cluster::default()));
+    for _ in 0..size {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        cluster.lock().unwrap().members.push(address.clone());
+        let cluster = cluster.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, address.clone(), cluster.clone()));
+            }
+        });
+    }
+    let mut state = cluster.lock().unwrap();
+    state.leader = state.members[0].clone();
+    drop(state);
+    cluster
+}
+
+/// Serve one client connection as cluster member `me`.
+async fn serve(mut stream: TcpStream, me: String, cluster: Arc<Mutex<Cluster>>) {
+    let mut handshake = [0u8; 20];
+    if stream.read_exact(&mut handshake).await.is_err() {
+        return;
+    }
+    stream.write_all(&[0, 0, 4, 4]).await.unwrap();
+
+    let mut failed = false;
+    let mut records: Vec<Value> = Vec::new();
+    let mut pending_rows = 0;
+    let mut payload_error = None;
+    while let Some(Value::Struct(signature, fields)) = read_message(&mut stream).await {
+        if failed && signature != 0x0F {
+            write_message(&mut stream, IGNORED, vec![]).await;
+            continue;
+        }
+        match signature {
+            // HELLO
+            0x01 => {
+                let meta = Value::map(&[
+                    ("server", Value::str("Neo4j/4.4.0")),
+                    ("connection_id", Value::str("bolt-1")),
+                ]);
+                write_message(&mut stream, SUCCESS, vec![meta]).await;
+            }
+            // GOODBYE
+            0x02 => return,
+            // RESET
+            0x0F => {
+                failed = false;
+                write_message(&mut stream, SUCCESS, vec![Value::map(&[])]).await;
+            }
+            // RUN
+            0x10 => {
+                let Value::String(cypher) = &fields[0] else {
+                    panic!("RUN without a query")
+                };
+                let (leader, members) = {
+                    let mut state = cluster.lock().unwrap();
+                    if cypher.contains("IN TRANSACTIONS") {
+                        *state.payloads.entry(me.clone()).or_default() += 1;
+                        payload_error = state.fail_payloads;
+                    }
+                    (state.leader.clone(), state.members.clone())
+                };
+                if cypher.contains("getRoutingTable") {
+                    let server = |role: &str, members: Vec<String>| {
+                        Value::List(vec![
+                            Value::Int(300),
+                            Value::str(role),
+                            Value::List(members.into_iter().map(Value::String).collect()),
+                        ])
+                    };
+                    records = vec![server("WRITE", vec![leader]), server("ROUTE", members)];
+                    let fields = ["ttl", "role", "addresses"].map(Value::str).to_vec();
+                    let meta = Value::map(&[("fields", Value::List(fields))]);
+                    write_message(&mut stream, SUCCESS, vec![meta]).await;
+                } else if cypher.contains("db.info") {
+                    records = vec![Value::List(vec![Value::str("neo4j")])];
+                    let meta = Value::map(&[("fields", Value::List(vec![Value::str("name")]))]);
+                    write_message(&mut stream, SUCCESS, vec![meta]).await;
+                } else if cypher.contains("lastCommittedTxn")
+                    && cluster.lock().unwrap().fail_bookmarks
+                {
+                    failed = true;
+                    let meta = Value::map(&[
+                        ("code", Value::str("Neo.ClientError.Security.Forbidden")),
+                        ("message", Value::str("SHOW DATABASES is not allowed")),
+                    ]);
+                    write_message(&mut stream, FAILURE, vec![meta]).await;
+                } else if cypher.contains("lastCommittedTxn") {
+                    let last = cluster.lock().unwrap().last_transaction;
+                    records = vec![Value::List(vec![Value::Int(last)])];
+                    let fields = Value::List(vec![Value::str("transaction")]);
+                    let meta = Value::map(&[("fields", fields)]);
+                    write_message(&mut stream, SUCCESS, vec![meta]).await;
+                } else if cypher.contains("dbms.components") {
+                    let component = ["Neo4j Kernel", "4.4.0", "enterprise"].map(Value::str);
+                    records = vec![Value::List(component.to_vec())];
+                    let fields = ["name", "version", "edition"].map(Value::str).to_vec();
+                    let meta = Value::map(&[("fields", Value::List(fields))]);
+                    write_message(&mut stream, SUCCESS, vec![meta]).await;
+                } else if leader != me && fields[1].get("rows").is_some() {
+                    failed = true;
+                    let meta = Value::map(&[
+                        ("code", Value::str("Neo.ClientError.Cluster.NotALeader")),
+                        ("message", Value::str("No write operations are allowed")),
+                    ]);
+                    write_message(&mut stream, FAILURE, vec![meta]).await;
+                } else {
+                    pending_rows = match fields[1].get("rows") {
+                        Some(Value::List(rows)) => rows.len(),
+                        _ => 0,
+                    };
+                    let meta = Value::map(&[("fields", Value::List(vec![]))]);
+                    write_message(&mut stream, SUCCESS, vec![meta]).await;
+                }
+            }
+            // PULL
+            0x3F => {
+                if let Some(code) = payload_error.take() {
+                    failed = true;
+                    let meta = Value::map(&[
+                        ("code", Value::str(code)),
+                        ("message", Value::str("Payload failed while running")),
+                    ]);
+                    write_message(&mut stream, FAILURE, vec![meta]).await;
+                    continue;
+                }
+                for record in std::mem::take(&mut records) {
+                    write_message(&mut stream, RECORD, vec![record]).await;
+                }
+                let meta = Value::map(&[("has_more", Value::Bool(false))]);
+                write_message(&mut stream, SUCCESS, vec![meta]).await;
+            }
+            // COMMIT
+            0x12 => {
+                {
+                    let mut state = cluster.lock().unwrap();
+                    *state.committed.entry(me.clone()).or_default() += pending_rows;
+                    state.last_transaction += 1;
+                    let count = state.transactions.entry(me.clone()).or_default();
+                    *count += 1;
+                    let count = *count;
+                    if let Some((after, next)) = state.fail_over.clone()
+                        && count >= after
+                    {
+                        state.leader = next;
+                        state.fail_over = None;
+                    }
+                }
+                pending_rows = 0;
+                let meta = Value::map(&[("bookmark", Value::str("bookmark"))]);
+                write_message(&mut stream, SUCCESS, vec![meta]).await;
+            }
+            // BEGIN, DISCARD, ROLLBACK
+            _ => {
+                write_message(&mut stream, SUCCESS, vec![Value::map(&[])]).await;
+            }
+        }
+    }
+}
+
+fn config(uri: String) -> Neo4jConfig {
+    Neo4jConfig {
+        uri,
+        auth: Auth::EmptyBasic,
+        database: None,
+        tls: TlsConfig::default(),
+        max_connections: None,
+        fetch_size: None,
+        connect_timeout: None,
+        transaction_timeout: None,
+        dialect: None,
+    }
+}
+
+fn write_csv(name: &str, rows: usize) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("{}-{name}.csv", std::process::id()));
+    let body: String = (0..rows).map(|i| format!("{i}\n")).collect();
+    std::fs::write(&path, format!("id\n{body}")).unwrap();
+    path
+}
+
+#[tokio::test]
+async fn retries_batches_on_the_new_leader() {
+    let cluster = start_cluster(3).await;
+    let (first, second) = {
+        let mut state = cluster.lock().unwrap();
+        let second = state.members[1].clone();
+        state.fail_over = Some((2, second.clone()));
+        (state.members[0].clone(), second)
+    };
+    let router = Router::connect(&config(format!(
+        "neo4j://{}",
+        cluster.lock().unwrap().members[2]
+    )))
+    .await
+    .unwrap();
+    assert_eq!(router.leader(), first);
+    assert_eq!(router.dialect(), Dialect::Neo4j4);
+
+    let path = write_csv("failover", 20);
+    let summary = NodeLoad::new(&path, "Person")
+        .key("id")
+        .batch_size(2)
+        .concurrency(2)
+        .run(&router)
+        .await
+        .unwrap();
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(summary.rows, 20);
+    assert_eq!(router.leader(), second);
+    let state = cluster.lock().unwrap();
+    assert_eq!(state.committed.values().sum::<usize>(), 20);
+    assert_eq!(state.transactions[&first], 2);
+    assert!(state.committed[&second] > 0);
+}
+
+#[tokio::test]
+async fn adaptive_loads_back_off_and_finish() {
+    let cluster = start_cluster(2).await;
+    let second = {
+        let mut state = cluster.lock().unwrap();
+        let second = state.members[1].clone();
+        state.fail_over = Some((3, second.clone()));
+        second
+    };
+    let router = Router::connect(&config(format!(
+        "neo4j://{}",
+        cluster.lock().unwrap().members[0]
+    )))
+    .await
+    .unwrap();
+
+    let path = write_csv("adaptive", 50);
+    let summary = NodeLoad::new(&path, "Person")
+        .key("id")
+        .batch_size(2)
+        .concurrency(1)
+        .adaptive(Adaptive {
+            max_concurrency: 4,
+            min_batch_size: 1,
+            max_batch_size: 8,
+            latency_spike: 10.0,
+        })
+        .run(&router)
+        .await
+        .unwrap();
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(summary.rows, 50);
+    assert_eq!(router.leader(), second);
+    let state = cluster.lock().unwrap();
+    assert_eq!(state.committed.values().sum::<usize>(), 50);
+    // Batches grew beyond the initial two rows.
+    assert!(summary.transactions < 25);
+}
+
+#[tokio::test]
+async fn cancelled_loads_report_what_was_committed() {
+    let cluster = start_cluster(1).await;
+    let router = Router::connect(&config(format!(
+        "neo4j://{}",
+        cluster.lock().unwrap().members[0]
+    )))
+    .await
+    .unwrap();
+
+    let path = write_csv("cancel", 1000);
+    let handle = LoadHandle::new();
+    let load = tokio::spawn({
+        let load = NodeLoad::new(&path, "Person")
+            .key("id")
+            .batch_size(10)
+            .concurrency(2)
+            .throttle(Throttle {
+                rows_per_second: Some(200),
+                ..Throttle::default()
+            })
+            .handle(handle.clone());
+        async move { load.run(&router).await.unwrap() }
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    handle.cancel();
+    let summary = load.await.unwrap();
+    std::fs::remove_file(&path).ok();
+
+    assert!(summary.cancelled);
+    assert!(summary.rows > 0 && summary.rows < 1000);
+    let state = cluster.lock().unwrap();
+    assert_eq!(state.committed.values().sum::<usize>(), summary.rows);
+    assert_eq!(
+        state.transactions.values().sum::<usize>(),
+        summary.transactions
+    );
+}
+
+#[tokio::test]
+async fn paused_loads_wait_for_resume() {
+    let cluster = start_cluster(1).await;
+    let router = Router::connect(&config(format!(
+        "neo4j://{}",
+        cluster.lock().unwrap().members[0]
+    )))
+    .await
+    .unwrap();
+
+    let path = write_csv("pause", 20);
+    let handle = LoadHandle::new();
+    handle.pause();
+    let load = tokio::spawn({
+        let load = NodeLoad::new(&path, "Person")
+            .batch_size(5)
+            .handle(handle.clone());
+        async move { load.run(&router).await.unwrap() }
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert!(!load.is_finished());
+    assert_eq!(cluster.lock().unwrap().committed.values().sum::<usize>(), 0);
+    handle.resume();
+    let summary = load.await.unwrap();
+    std::fs::remove_file(&path).ok();
+
+    assert!(!summary.cancelled);
+    assert_eq!((summary.rows, summary.transactions), (20, 4));
+    assert_eq!(
+        cluster.lock().unwrap().committed.values().sum::<usize>(),
+        20
+    );
+}
+
+#[tokio::test]
+async fn direct_connections_report_not_a_leader() {
+    let cluster = start_cluster(2).await;
+    let follower = cluster.lock().unwrap().members[1].clone();
+    let graph = connect(&config(format!("bolt://{follower}")))
+        .await
+        .unwrap();
+
+    let path = write_csv("direct", 4);
+    let err = NodeLoad::new(&path, "Person")
+        .run(&graph)
+        .await
+        .unwrap_err();
+    std::fs::remove_file(&path).ok();
+    assert!(err.to_string().contains("NotALeader"), "{err}");
+}
+
+#[tokio::test]
+async fn server_batched_payloads_are_not_retried() {
+    let cluster = start_cluster(1).await;
+    let leader = {
+        let mut state = cluster.lock().unwrap();
+        // A deadlock would make a write transaction retry.
+        state.fail_payloads = Some("Neo.TransientError.Transaction.DeadlockDetected");
+        state.members[0].clone()
+    };
+    let router = Router::connect(&config(format!("neo4j://{leader}")))
+        .await
+        .unwrap();
+
+    let path = write_csv("server-batching", 10);
+    let err = NodeLoad::new(&path, "Person")
+        .concurrency(1)
+        .server_batching(5)
+        .run(&router)
+        .await
+        .unwrap_err()
+        .to_string();
+    std::fs::remove_file(&path).ok();
+
+    assert!(err.contains("not retried"), "{err}");
+    assert!(err.contains("DeadlockDetected"), "{err}");
+    // The failed payload was sent once and the load stopped there.
+    assert_eq!(cluster.lock().unwrap().payloads[&leader], 1);
+}
+
+#[tokio::test]
+async fn graphs_detect_their_dialect() {
+    let cluster = start_cluster(1).await;
+    let leader = cluster.lock().unwrap().members[0].clone();
+    let graph = connect(&config(format!("bolt://{leader}"))).await.unwrap();
+    let router = Router::from(&graph);
+    assert_eq!(router.detect_dialect().await.unwrap(), Dialect::Neo4j4);
+    assert_eq!(router.dialect(), Dialect::Neo4j4);
+
+    // Neo4j 4.4 cannot run concurrent server-side transactions, which a
+    // load must notice before sending anything.
+    let path = write_csv("graph-dialect", 10);
+    let err = NodeLoad::new(&path, "Person")
+        .concurrency(4)
+        .server_batching(5)
+        .run(&graph)
+        .await
+        .unwrap_err()
+        .to_string();
+    std::fs::remove_file(&path).ok();
+    assert!(err.contains("neo4j-4"), "{err}");
+    assert!(cluster.lock().unwrap().payloads.is_empty());
+}
+
+#[tokio::test]
+async fn later_loads_wait_for_bookmarks() {
+    let cluster = start_cluster(1).await;
+    let leader = cluster.lock().unwrap().members[0].clone();
+    let router = Router::connect(&config(format!("neo4j://{leader}")))
+        .await
+        .unwrap();
+
+    let path = write_csv("bookmarks", 10);
+    let summary = NodeLoad::new(&path, "Person")
+        .batch_size(5)
+        .run(&router)
+        .await
+        .unwrap();
+    assert_eq!(summary.bookmarks, ["neo4j:2".parse::<Bookmark>().unwrap()]);
+
+    // A load through another connection, as in another process, waits
+    // until the writer has committed the bookmark's transaction.
+    let graph = connect(&config(format!("bolt://{leader}"))).await.unwrap();
+    let load = tokio::spawn({
+        let load = NodeLoad::new(&path, "City")
+            .batch_size(5)
+            .bookmarks(["neo4j:4".parse().unwrap()]);
+        async move { load.run(&graph).await.unwrap() }
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    assert!(!load.is_finished());
+    assert_eq!(cluster.lock().unwrap().committed[&leader], 10);
+    // The writer catches up, as after replicating from a former leader.
+    cluster.lock().unwrap().last_transaction = 4;
+    let summary = load.await.unwrap();
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(summary.rows, 10);
+    assert_eq!(summary.bookmarks, ["neo4j:6".parse::<Bookmark>().unwrap()]);
+}
+
+#[tokio::test]
+async fn bookmark_failures_only_fail_loads_that_require_them() {
+    let cluster = start_cluster(1).await;
+    let leader = {
+        let mut state = cluster.lock().unwrap();
+        state.fail_bookmarks = true;
+        state.members[0].clone()
+    };
+    let router = Router::connect(&config(format!("neo4j://{leader}")))
+        .await
+        .unwrap();
+
+    let path = write_csv("bookmark-failures", 10);
+    let summary = NodeLoad::new(&path, "Person")
+        .batch_size(5)
+        .run(&router)
+        .await
+        .unwrap();
+    assert_eq!(summary.rows, 10);
+    assert!(summary.bookmarks.is_empty());
+
+    let err = NodeLoad::new(&path, "City")
+        .batch_size(5)
+        .capture_bookmarks(true)
+        .run(&router)
+        .await
+        .unwrap_err()
+        .to_string();
+    std::fs::remove_file(&path).ok();
+
+    assert!(err.contains("reading its bookmark failed"), "{err}");
+    // Both loads committed their rows before the bookmark was read.
+    assert_eq!(cluster.lock().unwrap().committed[&leader], 20);
+}
+
+#[tokio::test]
+async fn record_batches_load_like_files() {
+    use arrow::array::Int64Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+
+    let cluster = start_cluster(1).await;
+    let leader = cluster.lock().unwrap().members[0].clone();
+    let graph = connect(&config(format!("bolt://{leader}"))).await.unwrap();
+    let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+    let batches = [0..6, 6..10].map(|ids| {
+        RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int64Array::from_iter_values(ids))],
+        )
+        .unwrap()
+    });
+
+    let summary = NodeLoad::from_batches(batches, "Person")
+        .key("id")
+        .batch_size(5)
+        .run(&graph)
+        .await
+        .unwrap();
+    assert_eq!((summary.rows, summary.transactions), (10, 2));
+    assert_eq!(summary.bookmarks, ["neo4j:2".parse::<Bookmark>().unwrap()]);
+    assert_eq!(cluster.lock().unwrap().committed[&leader], 10);
+}